@@ -12,9 +12,11 @@ public partial class MainMenu : MarginContainer
 	{
 		public static readonly NodePath Connect = new("%Connect");
 		public static readonly NodePath ConnectUi = new("%ConnectUI");
+		public static readonly NodePath CreateAccount = new("%CreateAccount");
 		public static readonly NodePath IpAddress = new("%IpAddress");
 		public static readonly NodePath Login = new("%Login");
 		public static readonly NodePath LoginUi = new("%LoginUI");
+		public static readonly NodePath PasswordInput = new("%PasswordInput");
 		public static readonly NodePath Quit = new("%Quit");
 		public static readonly NodePath StartOffline = new("%OfflineButton");
 		public static readonly NodePath UsernameInput = new("%UsernameInput");
@@ -27,6 +29,7 @@ public partial class MainMenu : MarginContainer
 	
 	private VttClient client;
 	private LineEdit ipAddress;
+	private LineEdit passwordInput;
 	private LineEdit usernameInput;
 	
 	public override void _ExitTree()
@@ -44,15 +47,18 @@ public partial class MainMenu : MarginContainer
 		
 		client = GetNode<VttClient>(VttClient.NodePath);
 		ipAddress = GetNode<LineEdit>(NodePaths.IpAddress);
+		passwordInput = GetNode<LineEdit>(NodePaths.PasswordInput);
 		usernameInput = GetNode<LineEdit>(NodePaths.UsernameInput);
 		
 		GetNode<Button>(NodePaths.StartOffline).Pressed += () => GetTree().ChangeSceneToFile(Scenes.OfflineBoard);
 		GetNode<Button>(NodePaths.Connect).Pressed += handleConnectButton;
+		GetNode<Button>(NodePaths.CreateAccount).Pressed += handleCreateAccountButton;
 		GetNode<Button>(NodePaths.Login).Pressed += handleLoginButton;
 		GetNode<Button>(NodePaths.Quit).Pressed += handleQuit;
 		
 		ipAddress.TextSubmitted += _ => handleConnectButton();
-		usernameInput.TextSubmitted += _ => handleLoginButton();
+		passwordInput.TextSubmitted += _ => handleLoginButton();
+		usernameInput.TextSubmitted += _ => passwordInput.GrabFocus();
 		
 		client.LoginResponse += handleLoginResponse;
 		client.SocketConnected += handleSocketConnected;
@@ -88,18 +94,8 @@ public partial class MainMenu : MarginContainer
 		}
 	}
 	
-	private void handleLoginButton()
-	{
-		if(!string.IsNullOrEmpty(usernameInput.Text))
-		{
-			usernameInput.Editable = false;
-			client.SendMessage(
-				client.Status.id,
-				Commands.AuthenticateSend,
-				new AuthenticationPayload(usernameInput.Text)
-			);
-		}
-	}
+	private void handleCreateAccountButton() => sendCredentials(Commands.AuthenticateCreate);
+	private void handleLoginButton() => sendCredentials(Commands.AuthenticateSend);
 	
 	private void handleLoginResponse(bool success)
 	{
@@ -108,6 +104,8 @@ public partial class MainMenu : MarginContainer
 			loginUi.Hide();
 			connectUi.Hide();
 			
+			passwordInput.Editable = false;
+			passwordInput.Clear();
 			usernameInput.Editable = false;
 			usernameInput.Clear();
 			
@@ -116,6 +114,8 @@ public partial class MainMenu : MarginContainer
 		else
 		{
 			//TODO: alert user
+			passwordInput.Editable = true;
+			passwordInput.Clear();
 			usernameInput.Editable = true;
 		}
 	}
@@ -133,4 +133,18 @@ public partial class MainMenu : MarginContainer
 		ipAddress.Clear();
 		usernameInput.GrabFocus();
 	}
+	
+	private void sendCredentials(Commands type)
+	{
+		if(!string.IsNullOrEmpty(usernameInput.Text) && !string.IsNullOrEmpty(passwordInput.Text))
+		{
+			passwordInput.Editable = false;
+			usernameInput.Editable = false;
+			client.SendMessage(
+				client.Status.id,
+				type,
+				new AuthenticationPayload(usernameInput.Text, passwordInput.Text)
+			);
+		}
+	}
 }
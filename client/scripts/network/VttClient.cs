@@ -22,6 +22,8 @@ public partial class VttClient : Node
 	
 	public static readonly NodePath NodePath = new("/root/VttClient");
 	
+	private const uint ProtocolVersion = 1;
+	
 	public ClientStatus Status => status;
 	
 	private ClientStatus status;
@@ -100,6 +102,10 @@ public partial class VttClient : Node
 		{
 			switch(command.Type)
 			{
+				case Commands.HelloRequest:
+					SendMessage(status.id, Commands.HelloSend, new HelloPayload(ProtocolVersion));
+					break;
+				
 				case Commands.AuthenticateFail:
 					EmitSignal(SignalName.LoginResponse, false);
 					break;
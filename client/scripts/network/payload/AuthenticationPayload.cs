@@ -2,10 +2,11 @@ using System.Collections.Generic;
 
 namespace Vtt.Network.Payload;
 
-public struct AuthenticationPayload(string username) : Serializable
+public struct AuthenticationPayload(string username, string password) : Serializable
 {
 	public readonly string Name => username;
+	public readonly string Password => password;
 	
 	public readonly Dictionary<string, string> Serialize()
-		=> new() { { "name", Name } };
+		=> new() { { "name", Name }, { "password", Password } };
 }
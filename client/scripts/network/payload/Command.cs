@@ -8,6 +8,7 @@ public class Command() : Message
 	public Dictionary<string, byte[]> BinaryData { get; set; } = [];
 	public Dictionary<string, string> Data { get; set; } = [];
 	public long Id { get; set; } = -1;
+	public string RequestId { get; set; }
 	public long Timestamp { get; set; } = DateTimeOffset.UtcNow.ToUnixTimeSeconds();
 	public Commands Type { get; set; }
 	
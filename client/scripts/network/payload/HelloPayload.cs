@@ -0,0 +1,11 @@
+using System.Collections.Generic;
+
+namespace Vtt.Network.Payload;
+
+public struct HelloPayload(uint version) : Serializable
+{
+	public readonly uint Version => version;
+	
+	public readonly Dictionary<string, string> Serialize()
+		=> new() { { "version", Version.ToString() } };
+}
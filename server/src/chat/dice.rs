@@ -0,0 +1,862 @@
+use std::fmt::{Display, Formatter};
+use ::anyhow::{Error, Result};
+use ::rand::Rng;
+use ::rand::rngs::ThreadRng;
+use ::serde::{Deserialize, Serialize};
+
+/// Maximum number of dice rolled by a single term, before explosions.
+const MaxDicePerTerm: u32 = 100;
+/// Maximum number of sides on a die.
+const MaxSides: u32 = 1000;
+/// Maximum number of dice rolled by a whole expression, including explosions.
+const MaxDiceTotal: usize = 1000;
+/// Maximum number of terms in an expression.
+const MaxTerms: usize = 20;
+/// Maximum number of extra dice a single exploding die may add.
+const MaxExplosionsPerDie: u32 = 100;
+/// Maximum value of a constant, which keeps the total of any expression far from overflowing.
+const MaxConstant: i64 = 1_000_000;
+
+/**
+A source of die rolls.
+*/
+pub trait Roller
+{
+	/// Roll a single die, returning a value from 1 to `sides` inclusive.
+	fn roll(&mut self, sides: u32) -> u32;
+}
+
+/**
+Rolls dice using the thread local random number generator.
+*/
+#[derive(Default)]
+pub struct RandomRoller(ThreadRng);
+
+impl Roller for RandomRoller
+{
+	fn roll(&mut self, sides: u32) -> u32
+	{
+		return self.0.gen_range(1..=sides);
+	}
+}
+
+/**
+How a target number is compared against each die of a dice pool.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison
+{
+	Equal,
+	Greater,
+	GreaterOrEqual,
+	Less,
+	LessOrEqual,
+}
+
+impl Comparison
+{
+	fn symbol(&self) -> &'static str
+	{
+		return match self
+		{
+			Self::Equal => "=",
+			Self::Greater => ">",
+			Self::GreaterOrEqual => ">=",
+			Self::Less => "<",
+			Self::LessOrEqual => "<=",
+		};
+	}
+	
+	fn test(&self, value: u32, target: u32) -> bool
+	{
+		return match self
+		{
+			Self::Equal => value == target,
+			Self::Greater => value > target,
+			Self::GreaterOrEqual => value >= target,
+			Self::Less => value < target,
+			Self::LessOrEqual => value <= target,
+		};
+	}
+}
+
+/**
+Which dice of a term count towards its value.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Keep
+{
+	Highest(u32),
+	Lowest(u32),
+}
+
+/**
+A single dice term such as `4d6kh3`, `1d20adv`, `3d6!` or `6d10>=7`.
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiceTerm
+{
+	pub count: u32,
+	pub sides: u32,
+	/// Whether each die showing its highest face is rolled again.
+	pub explode: bool,
+	pub keep: Option<Keep>,
+	/// Count the dice meeting the target instead of summing them.
+	pub target: Option<(Comparison, u32)>,
+	/// The notation used for advantage or disadvantage, which replaces `count` and `keep` when displayed.
+	advantage: Option<&'static str>,
+}
+
+impl Display for DiceTerm
+{
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result
+	{
+		if let Some(notation) = self.advantage
+		{
+			write!(f, "{}d{}{}", self.count / 2, self.sides, notation)?;
+		}
+		else
+		{
+			write!(f, "{}d{}", self.count, self.sides)?;
+			
+			match self.keep
+			{
+				None => {},
+				Some(Keep::Highest(n)) => write!(f, "kh{}", n)?,
+				Some(Keep::Lowest(n)) => write!(f, "kl{}", n)?,
+			}
+		}
+		
+		if self.explode
+		{
+			write!(f, "!")?;
+		}
+		
+		if let Some((comparison, target)) = self.target
+		{
+			write!(f, "{}{}", comparison.symbol(), target)?;
+		}
+		
+		return Ok(());
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Term
+{
+	Constant(i64),
+	Dice(DiceTerm),
+}
+
+impl Display for Term
+{
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result
+	{
+		return match self
+		{
+			Self::Constant(value) => write!(f, "{}", value),
+			Self::Dice(dice) => write!(f, "{}", dice),
+		};
+	}
+}
+
+/**
+A parsed dice expression: terms added or subtracted from left to right.
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiceExpression
+{
+	/// Each term along with whether it is subtracted.
+	pub terms: Vec<(bool, Term)>,
+}
+
+impl Display for DiceExpression
+{
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result
+	{
+		for (i, (negative, term)) in self.terms.iter().enumerate()
+		{
+			match (i, negative)
+			{
+				(0, false) => {},
+				(0, true) => write!(f, "-")?,
+				(_, false) => write!(f, "+")?,
+				(_, true) => write!(f, "-")?,
+			}
+			
+			write!(f, "{}", term)?;
+		}
+		
+		return Ok(());
+	}
+}
+
+impl DiceExpression
+{
+	/**
+	Parse an expression from the start of `input`, returning it along with the
+	rest of the input, which is typically a label such as "for initiative".
+	
+	Whitespace is allowed between terms and operators.
+	*/
+	pub fn parse(input: &str) -> Result<(Self, &str)>
+	{
+		let mut parser = Parser { input, position: 0 };
+		let mut terms = vec![];
+		let mut negative = parser.consumeSign().unwrap_or(false);
+		
+		loop
+		{
+			terms.push((negative, parser.term()?));
+			
+			if terms.len() > MaxTerms
+			{
+				return Err(Error::msg(format!("Dice expressions may have at most {} terms", MaxTerms)));
+			}
+			
+			match parser.consumeSign()
+			{
+				Some(sign) => negative = sign,
+				None => break,
+			}
+		}
+		
+		parser.skipWhitespace();
+		return Ok((Self { terms }, parser.rest()));
+	}
+	
+	/**
+	Roll every die in the expression.
+	*/
+	pub fn roll(&self, roller: &mut impl Roller) -> Result<RollResult>
+	{
+		let mut results = vec![];
+		let mut diceRolled = 0;
+		let mut total = 0;
+		
+		for (negative, term) in self.terms.iter()
+		{
+			let result = match term
+			{
+				Term::Constant(value) => TermResult::Constant { negative: *negative, value: *value },
+				Term::Dice(dice) => {
+					let rolled = rollDice(dice, roller, MaxDiceTotal - diceRolled)?;
+					diceRolled += rolled.len();
+					
+					TermResult::Dice
+					{
+						negative: *negative,
+						notation: dice.to_string(),
+						value: termValue(dice, &rolled),
+						dice: rolled,
+					}
+				},
+			};
+			
+			total += result.signedValue();
+			results.push(result);
+		}
+		
+		return Ok(RollResult
+		{
+			expression: self.to_string(),
+			successes: self.terms.iter().any(|(_, term)| matches!(term, Term::Dice(DiceTerm { target: Some(_), .. }))),
+			terms: results,
+			total,
+		});
+	}
+}
+
+/**
+A single rolled die.
+*/
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct DieResult
+{
+	pub value: u32,
+	/// Not counted, due to a keep or drop modifier.
+	#[serde(default)]
+	pub dropped: bool,
+	/// Showed its highest face and caused another die to be rolled.
+	#[serde(default)]
+	pub exploded: bool,
+	/// Whether the die met the target of a dice pool.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub success: Option<bool>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum TermResult
+{
+	Constant
+	{
+		negative: bool,
+		value: i64,
+	},
+	
+	Dice
+	{
+		negative: bool,
+		notation: String,
+		dice: Vec<DieResult>,
+		/// The sum of the kept dice, or the number of successes for a dice pool.
+		value: i64,
+	},
+}
+
+impl TermResult
+{
+	fn signedValue(&self) -> i64
+	{
+		let (negative, value) = match self
+		{
+			Self::Constant { negative, value } => (*negative, *value),
+			Self::Dice { negative, value, .. } => (*negative, *value),
+		};
+		
+		return match negative
+		{
+			true => -value,
+			false => value,
+		};
+	}
+}
+
+/**
+The outcome of rolling a dice expression, with every individual die.
+*/
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct RollResult
+{
+	pub expression: String,
+	/// Whether the expression contains a dice pool, so `total` counts successes.
+	pub successes: bool,
+	pub terms: Vec<TermResult>,
+	pub total: i64,
+}
+
+impl Display for RollResult
+{
+	/**
+	Format the result for chat, for example `4d6kh3+2: [6, 5, 3, (1)] + 2 = 16`.
+	
+	Dropped dice are shown in parentheses, exploded dice are followed by `!`
+	and dice meeting a pool's target are followed by `*`.
+	*/
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result
+	{
+		write!(f, "{}: ", self.expression)?;
+		
+		for (i, term) in self.terms.iter().enumerate()
+		{
+			let (negative, text) = match term
+			{
+				TermResult::Constant { negative, value } => (*negative, value.to_string()),
+				TermResult::Dice { negative, dice, .. } => (*negative, formatDice(dice)),
+			};
+			
+			match (i, negative)
+			{
+				(0, false) => {},
+				(0, true) => write!(f, "-")?,
+				(_, false) => write!(f, " + ")?,
+				(_, true) => write!(f, " - ")?,
+			}
+			
+			write!(f, "{}", text)?;
+		}
+		
+		return match self.successes
+		{
+			true => write!(f, " = {} {}", self.total, if self.total == 1 { "success" } else { "successes" }),
+			false => write!(f, " = {}", self.total),
+		};
+	}
+}
+
+fn formatDice(dice: &[DieResult]) -> String
+{
+	let values = dice.iter()
+		.map(|die| {
+			let mut text = die.value.to_string();
+			if die.exploded
+			{
+				text.push('!');
+			}
+			
+			if die.success == Some(true)
+			{
+				text.push('*');
+			}
+			
+			match die.dropped
+			{
+				true => format!("({})", text),
+				false => text,
+			}
+		})
+		.collect::<Vec<_>>();
+	
+	return format!("[{}]", values.join(", "));
+}
+
+/**
+Roll the dice of a single term, including explosions, and mark which are
+dropped and which are successes.
+*/
+fn rollDice(term: &DiceTerm, roller: &mut impl Roller, remaining: usize) -> Result<Vec<DieResult>>
+{
+	let mut dice: Vec<DieResult> = vec![];
+	
+	for _ in 0..term.count
+	{
+		let mut explosions = 0;
+		
+		loop
+		{
+			if dice.len() >= remaining
+			{
+				return Err(Error::msg(format!("Dice expressions may roll at most {} dice", MaxDiceTotal)));
+			}
+			
+			let value = roller.roll(term.sides);
+			let exploded = term.explode && value == term.sides && explosions < MaxExplosionsPerDie;
+			
+			dice.push(DieResult { value, dropped: false, exploded, success: None });
+			
+			if !exploded
+			{
+				break;
+			}
+			
+			explosions += 1;
+		}
+	}
+	
+	if let Some(keep) = term.keep
+	{
+		let mut order = (0..dice.len()).collect::<Vec<_>>();
+		//Stable sorts keep the earliest of equal dice
+		match keep
+		{
+			Keep::Highest(_) => order.sort_by(|a, b| dice[*b].value.cmp(&dice[*a].value)),
+			Keep::Lowest(_) => order.sort_by(|a, b| dice[*a].value.cmp(&dice[*b].value)),
+		}
+		
+		let kept = match keep
+		{
+			Keep::Highest(n) | Keep::Lowest(n) => n as usize,
+		};
+		
+		for index in order.into_iter().skip(kept)
+		{
+			dice[index].dropped = true;
+		}
+	}
+	
+	if let Some((comparison, target)) = term.target
+	{
+		for die in dice.iter_mut().filter(|die| !die.dropped)
+		{
+			die.success = Some(comparison.test(die.value, target));
+		}
+	}
+	
+	return Ok(dice);
+}
+
+fn termValue(term: &DiceTerm, dice: &[DieResult]) -> i64
+{
+	let kept = dice.iter().filter(|die| !die.dropped);
+	
+	return match term.target
+	{
+		Some(_) => kept.filter(|die| die.success == Some(true)).count() as i64,
+		None => kept.map(|die| die.value as i64).sum(),
+	};
+}
+
+struct Parser<'a>
+{
+	input: &'a str,
+	position: usize,
+}
+
+impl<'a> Parser<'a>
+{
+	fn rest(&self) -> &'a str
+	{
+		return &self.input[self.position..];
+	}
+	
+	fn skipWhitespace(&mut self)
+	{
+		let rest = self.rest();
+		self.position += rest.len() - rest.trim_start().len();
+	}
+	
+	/**
+	Consume `token`, ignoring case, if the input continues with it.
+	*/
+	fn consume(&mut self, token: &str) -> bool
+	{
+		let rest = self.rest();
+		let matches = rest.len() >= token.len()
+			&& rest.is_char_boundary(token.len())
+			&& rest[..token.len()].eq_ignore_ascii_case(token);
+		
+		if matches
+		{
+			self.position += token.len();
+		}
+		
+		return matches;
+	}
+	
+	/**
+	Consume a `+` or `-` operator, returning whether it was a `-`.
+	*/
+	fn consumeSign(&mut self) -> Option<bool>
+	{
+		let start = self.position;
+		self.skipWhitespace();
+		
+		if self.consume("+")
+		{
+			self.skipWhitespace();
+			return Some(false);
+		}
+		
+		if self.consume("-")
+		{
+			self.skipWhitespace();
+			return Some(true);
+		}
+		
+		self.position = start;
+		return None;
+	}
+	
+	fn number(&mut self) -> Option<u64>
+	{
+		let digits = self.rest()
+			.chars()
+			.take_while(|c| c.is_ascii_digit())
+			.count();
+		
+		if digits == 0
+		{
+			return None;
+		}
+		
+		let text = &self.rest()[..digits];
+		self.position += digits;
+		//Anything too long to parse is far beyond every limit anyway
+		return Some(text.parse::<u64>().unwrap_or(u64::MAX));
+	}
+	
+	fn term(&mut self) -> Result<Term>
+	{
+		let count = self.number();
+		
+		if !self.consume("d")
+		{
+			return match count
+			{
+				Some(value) if value <= MaxConstant as u64 => Ok(Term::Constant(value as i64)),
+				Some(_) => Err(Error::msg(format!("Numbers must be at most {}", MaxConstant))),
+				None => Err(Error::msg(format!("Expected a number or dice at \"{}\"", self.rest()))),
+			};
+		}
+		
+		let count = count.unwrap_or(1);
+		let sides = match self.number()
+		{
+			Some(sides) => sides,
+			None if self.consume("%") => 100,
+			None => return Err(Error::msg("Expected the number of sides after 'd'")),
+		};
+		
+		if count == 0 || count > MaxDicePerTerm as u64
+		{
+			return Err(Error::msg(format!("The number of dice must be between 1 and {}", MaxDicePerTerm)));
+		}
+		
+		if sides == 0 || sides > MaxSides as u64
+		{
+			return Err(Error::msg(format!("The number of sides must be between 1 and {}", MaxSides)));
+		}
+		
+		let mut term = DiceTerm
+		{
+			count: count as u32,
+			sides: sides as u32,
+			explode: false,
+			keep: None,
+			target: None,
+			advantage: None,
+		};
+		
+		self.modifiers(&mut term)?;
+		return Ok(Term::Dice(term));
+	}
+	
+	fn modifiers(&mut self, term: &mut DiceTerm) -> Result<()>
+	{
+		loop
+		{
+			if self.consume("adv") || self.consume("dis")
+			{
+				if term.keep.is_some() || term.advantage.is_some()
+				{
+					return Err(Error::msg("Advantage cannot be combined with keep or drop"));
+				}
+				
+				let advantage = self.input[self.position - 3..self.position].eq_ignore_ascii_case("adv");
+				let count = term.count;
+				term.count *= 2;
+				term.keep = Some(match advantage
+				{
+					true => Keep::Highest(count),
+					false => Keep::Lowest(count),
+				});
+				term.advantage = Some(if advantage { "adv" } else { "dis" });
+			}
+			else if let Some(keep) = self.keepModifier(term.count)?
+			{
+				if term.keep.is_some() || term.advantage.is_some()
+				{
+					return Err(Error::msg("Only one keep or drop modifier is allowed per term"));
+				}
+				
+				term.keep = Some(keep);
+			}
+			else if self.consume("!")
+			{
+				if term.sides < 2
+				{
+					return Err(Error::msg("Dice must have at least 2 sides to explode"));
+				}
+				
+				term.explode = true;
+			}
+			else if let Some(comparison) = self.comparison()
+			{
+				if term.target.is_some()
+				{
+					return Err(Error::msg("Only one target number is allowed per term"));
+				}
+				
+				let target = self.number()
+					.ok_or(Error::msg("Expected a target number"))?;
+				term.target = Some((comparison, target.min(u32::MAX as u64) as u32));
+			}
+			else
+			{
+				return Ok(());
+			}
+		}
+	}
+	
+	/**
+	Parse `khN`, `klN`, `kN`, `dhN` or `dlN`, converting drops into the
+	equivalent keep.
+	*/
+	fn keepModifier(&mut self, count: u32) -> Result<Option<Keep>>
+	{
+		let start = self.position;
+		//Longer modifiers first, so that "kl" is not read as "k"
+		let kind = if self.consume("kh") { "kh" }
+			else if self.consume("kl") { "kl" }
+			else if self.consume("dh") { "dh" }
+			else if self.consume("dl") { "dl" }
+			else if self.consume("k") { "kh" }
+			else { return Ok(None) };
+		
+		let n = match self.number()
+		{
+			Some(n) => n,
+			None => {
+				self.position = start;
+				return Ok(None);
+			},
+		};
+		
+		if n > count as u64
+		{
+			return Err(Error::msg(format!("Cannot keep or drop {} of {} dice", n, count)));
+		}
+		
+		let n = n as u32;
+		return Ok(Some(match kind
+		{
+			"kh" => Keep::Highest(n),
+			"kl" => Keep::Lowest(n),
+			"dh" => Keep::Lowest(count - n),
+			_ => Keep::Highest(count - n),
+		}));
+	}
+	
+	fn comparison(&mut self) -> Option<Comparison>
+	{
+		//Longer operators first, so that ">=" is not read as ">"
+		for (token, comparison) in [
+			(">=", Comparison::GreaterOrEqual),
+			("<=", Comparison::LessOrEqual),
+			(">", Comparison::Greater),
+			("<", Comparison::Less),
+			("=", Comparison::Equal),
+		]
+		{
+			if self.consume(token)
+			{
+				return Some(comparison);
+			}
+		}
+		
+		return None;
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use std::collections::VecDeque;
+	
+	/// Returns a fixed sequence of rolls.
+	struct FixedRoller(VecDeque<u32>);
+	
+	impl Roller for FixedRoller
+	{
+		fn roll(&mut self, sides: u32) -> u32
+		{
+			let value = self.0.pop_front().expect("Ran out of fixed rolls");
+			assert!(value >= 1 && value <= sides);
+			return value;
+		}
+	}
+	
+	fn roll(expression: &str, rolls: &[u32]) -> RollResult
+	{
+		let (parsed, rest) = DiceExpression::parse(expression).expect("Error parsing expression");
+		assert!(rest.is_empty());
+		
+		let mut roller = FixedRoller(rolls.iter().copied().collect());
+		let result = parsed.roll(&mut roller).expect("Error rolling expression");
+		assert!(roller.0.is_empty(), "Not every fixed roll was used");
+		return result;
+	}
+	
+	#[test]
+	fn keepHighest()
+	{
+		let result = roll("4d6kh3+2", &[3, 6, 1, 5]);
+		assert_eq!(result.total, 16);
+		assert_eq!(result.to_string(), "4d6kh3+2: [3, 6, (1), 5] + 2 = 16");
+	}
+	
+	#[test]
+	fn drops()
+	{
+		assert_eq!(roll("4d6dl1", &[3, 6, 1, 5]).total, 14);
+		assert_eq!(roll("4d6dh1", &[3, 6, 1, 5]).total, 9);
+		assert_eq!(roll("3d6kl1", &[4, 2, 2]).to_string(), "3d6kl1: [(4), 2, (2)] = 2");
+	}
+	
+	#[test]
+	fn advantage()
+	{
+		let result = roll("1d20adv", &[7, 15]);
+		assert_eq!(result.total, 15);
+		assert_eq!(result.to_string(), "1d20adv: [(7), 15] = 15");
+		
+		assert_eq!(roll("1d20dis+3", &[7, 15]).total, 10);
+	}
+	
+	#[test]
+	fn exploding()
+	{
+		let result = roll("3d6!", &[6, 6, 2, 3, 4]);
+		assert_eq!(result.total, 21);
+		assert_eq!(result.to_string(), "3d6!: [6!, 6!, 2, 3, 4] = 21");
+	}
+	
+	#[test]
+	fn explosionsAreCapped()
+	{
+		let mut rolls = vec![2; MaxExplosionsPerDie as usize + 1];
+		rolls.push(1);
+		let result = roll("2d2!", &rolls);
+		
+		let dice = match &result.terms[0]
+		{
+			TermResult::Dice { dice, .. } => dice,
+			other => panic!("Expected a dice term, found {:?}", other),
+		};
+		
+		assert_eq!(dice.len(), MaxExplosionsPerDie as usize + 2);
+		assert!(!dice[MaxExplosionsPerDie as usize].exploded);
+	}
+	
+	#[test]
+	fn pools()
+	{
+		let result = roll("6d10>=7", &[7, 3, 10, 1, 6, 8]);
+		assert_eq!(result.total, 3);
+		assert!(result.successes);
+		assert_eq!(result.to_string(), "6d10>=7: [7*, 3, 10*, 1, 6, 8*] = 3 successes");
+		
+		assert_eq!(roll("3d6<3", &[1, 2, 3]).total, 2);
+	}
+	
+	#[test]
+	fn arithmetic()
+	{
+		let result = roll("2d8 + 1d6 - 3", &[4, 5, 6]);
+		assert_eq!(result.expression, "2d8+1d6-3");
+		assert_eq!(result.total, 12);
+		assert_eq!(result.to_string(), "2d8+1d6-3: [4, 5] + [6] - 3 = 12");
+		
+		assert_eq!(roll("-1+d4", &[2]).total, 1);
+		assert_eq!(roll("d%", &[42]).total, 42);
+	}
+	
+	#[test]
+	fn labels()
+	{
+		let (expression, rest) = DiceExpression::parse("1d20 + 5 for initiative").expect("Error parsing expression");
+		assert_eq!(expression.to_string(), "1d20+5");
+		assert_eq!(rest, "for initiative");
+		
+		//A trailing operator without a term is an error rather than part of the label
+		assert!(DiceExpression::parse("1d20 + for").is_err());
+	}
+	
+	#[test]
+	fn invalid()
+	{
+		for expression in ["", "d", "dice", "0d6", "101d6", "1d0", "1d1001", "1d1!", "4d6kh5", "1d20advkh1", "2d6>=", "2d6kh1kl1"]
+		{
+			assert!(DiceExpression::parse(expression).is_err(), "{} should not parse", expression);
+		}
+		
+		//Constants large enough to overflow the total
+		for expression in ["1000001", "9223372036854775807 + 1", "1d6 + 99999999999999999999"]
+		{
+			assert!(DiceExpression::parse(expression).is_err(), "{} should not parse", expression);
+		}
+		
+		assert!(DiceExpression::parse("1000000").is_ok());
+	}
+	
+	#[test]
+	fn resultRoundTrip()
+	{
+		let result = roll("2d6!>4+1", &[6, 3, 5]);
+		let json = serde_json::to_string(&result).expect("Error serializing result");
+		let parsed: RollResult = serde_json::from_str(&json).expect("Error deserializing result");
+		assert_eq!(parsed, result);
+	}
+}
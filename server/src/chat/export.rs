@@ -0,0 +1,327 @@
+use std::str::FromStr;
+use ::anyhow::{Error, Result};
+use ::chrono::{DateTime, Utc};
+use ::serde::{Deserialize, Serialize};
+use crate::data::MessageKind;
+use super::log::LogEntry;
+
+const TimestampFormat: &'static str = "%Y-%m-%d %H:%M:%S";
+
+const HtmlStyle: &'static str = r#"body { font-family: sans-serif; margin: 2em auto; max-width: 60em; }
+ol { list-style: none; padding: 0; }
+li { margin: 0.25em 0; }
+time { color: #777; font-family: monospace; margin-right: 0.5em; }
+.emote { font-style: italic; }
+.outOfCharacter, .secret, .whisper { color: #555; }
+.roll { background: #f4f0e6; }"#;
+
+/**
+The file formats a chat log may be exported to.
+*/
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExportFormat
+{
+	#[default]
+	Markdown,
+	/// A single HTML page with its styles included.
+	Html,
+	Json,
+}
+
+impl ExportFormat
+{
+	/**
+	The usual file extension, without the leading `.`.
+	*/
+	pub fn extension(&self) -> &'static str
+	{
+		return match self
+		{
+			Self::Markdown => "md",
+			Self::Html => "html",
+			Self::Json => "json",
+		};
+	}
+}
+
+impl FromStr for ExportFormat
+{
+	type Err = Error;
+	
+	fn from_str(s: &str) -> Result<Self>
+	{
+		return match s.to_lowercase().as_str()
+		{
+			"markdown" | "md" => Ok(Self::Markdown),
+			"html" => Ok(Self::Html),
+			"json" => Ok(Self::Json),
+			_ => Err(Error::msg(format!("Unknown export format: {}", s))),
+		};
+	}
+}
+
+/**
+The title of the chat log of `campaignId`, or of the lobby when `None`.
+*/
+pub fn logTitle(campaignId: Option<i32>) -> String
+{
+	return match campaignId
+	{
+		Some(id) => format!("Campaign {} chat log", id),
+		None => "Lobby chat log".to_string(),
+	};
+}
+
+/**
+A file name for the chat log of `campaignId` exported at `exported`.
+*/
+pub fn logFileName(campaignId: Option<i32>, format: ExportFormat, exported: DateTime<Utc>) -> String
+{
+	let room = match campaignId
+	{
+		Some(id) => format!("campaign-{}", id),
+		None => "lobby".to_string(),
+	};
+	
+	return format!("{}-{}.{}", room, exported.format("%Y-%m-%d"), format.extension());
+}
+
+#[derive(Serialize)]
+struct JsonLog<'a>
+{
+	exported: DateTime<Utc>,
+	messages: Vec<&'a LogEntry>,
+	title: &'a str,
+}
+
+/**
+Write `entries` as a session log titled `title`, in `format`. Deleted messages
+are left out.
+*/
+pub fn exportLog(title: &str, entries: &[LogEntry], format: ExportFormat, exported: DateTime<Utc>) -> Result<String>
+{
+	let entries = entries.iter()
+		.filter(|entry| !entry.deleted)
+		.collect::<Vec<_>>();
+	
+	return match format
+	{
+		ExportFormat::Markdown => Ok(exportMarkdown(title, &entries, exported)),
+		ExportFormat::Html => Ok(exportHtml(title, &entries, exported)),
+		ExportFormat::Json => Ok(serde_json::to_string_pretty(&JsonLog { exported, messages: entries, title })?),
+	};
+}
+
+fn exportMarkdown(title: &str, entries: &[&LogEntry], exported: DateTime<Utc>) -> String
+{
+	let mut output = format!("# {}\n\nExported {} UTC\n\n", escapeMarkdown(title), exported.format(TimestampFormat));
+	
+	for entry in entries
+	{
+		let text = escapeMarkdown(&entry.text);
+		let line = match (entry.sender.as_deref().map(escapeMarkdown), &entry.recipient, entry.kind)
+		{
+			(None, _, _) => text,
+			(Some(sender), Some(recipient), _) => format!("**{}** (to {}): {}", sender, escapeMarkdown(recipient), text),
+			(Some(sender), None, MessageKind::Say) => format!("**{}**: {}", sender, text),
+			(Some(sender), None, MessageKind::Emote) => format!("*{} {}*", sender, text),
+			(Some(sender), None, MessageKind::OutOfCharacter) => format!("**{}** (OOC): {}", sender, text),
+			(Some(sender), None, MessageKind::Secret) => format!("**{}** (to GM): {}", sender, text),
+		};
+		
+		let edited = match entry.edited
+		{
+			Some(_) => " *(edited)*",
+			None => "",
+		};
+		
+		output.push_str(&format!("- `{}` {}{}\n", entry.timestamp.format(TimestampFormat), line, edited));
+	}
+	
+	return output;
+}
+
+fn exportHtml(title: &str, entries: &[&LogEntry], exported: DateTime<Utc>) -> String
+{
+	let title = escapeHtml(title);
+	let mut output = format!(
+		"<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n{}\n</style>\n</head>\n<body>\n<h1>{}</h1>\n<p>Exported {} UTC</p>\n<ol>\n",
+		title,
+		HtmlStyle,
+		title,
+		exported.format(TimestampFormat)
+	);
+	
+	for entry in entries
+	{
+		let mut classes = vec![match (&entry.recipient, entry.kind)
+		{
+			(Some(_), _) => "whisper",
+			(None, MessageKind::Say) => "say",
+			(None, MessageKind::Emote) => "emote",
+			(None, MessageKind::OutOfCharacter) => "outOfCharacter",
+			(None, MessageKind::Secret) => "secret",
+		}];
+		
+		if entry.roll.is_some()
+		{
+			classes.push("roll");
+		}
+		
+		let text = escapeHtml(&entry.text);
+		let line = match (entry.sender.as_deref().map(escapeHtml), &entry.recipient, entry.kind)
+		{
+			(None, _, _) => text,
+			(Some(sender), Some(recipient), _) => format!("<b>{}</b> (to {}): {}", sender, escapeHtml(recipient), text),
+			(Some(sender), None, MessageKind::Say) => format!("<b>{}</b>: {}", sender, text),
+			(Some(sender), None, MessageKind::Emote) => format!("<b>{}</b> {}", sender, text),
+			(Some(sender), None, MessageKind::OutOfCharacter) => format!("<b>{}</b> (OOC): {}", sender, text),
+			(Some(sender), None, MessageKind::Secret) => format!("<b>{}</b> (to GM): {}", sender, text),
+		};
+		
+		let edited = match entry.edited
+		{
+			Some(_) => " <small>(edited)</small>",
+			None => "",
+		};
+		
+		output.push_str(&format!(
+			"<li class=\"{}\"><time datetime=\"{}\">{}</time>{}{}</li>\n",
+			classes.join(" "),
+			entry.timestamp.to_rfc3339(),
+			entry.timestamp.format(TimestampFormat),
+			line,
+			edited
+		));
+	}
+	
+	output.push_str("</ol>\n</body>\n</html>\n");
+	return output;
+}
+
+fn escapeHtml(text: &str) -> String
+{
+	return text.replace('&', "&amp;")
+		.replace('<', "&lt;")
+		.replace('>', "&gt;")
+		.replace('"', "&quot;")
+		.replace('\'', "&#39;");
+}
+
+/**
+Escape the characters Markdown treats as formatting, and fold line breaks so
+that each message stays in its list item.
+*/
+fn escapeMarkdown(text: &str) -> String
+{
+	let mut escaped = String::with_capacity(text.len());
+	for c in text.chars()
+	{
+		match c
+		{
+			'\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|' | '~' => {
+				escaped.push('\\');
+				escaped.push(c);
+			},
+			'\r' => {},
+			'\n' => escaped.push(' '),
+			_ => escaped.push(c),
+		}
+	}
+	
+	return escaped;
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use ::chrono::TimeZone;
+	
+	fn entries() -> Vec<LogEntry>
+	{
+		let timestamp = Utc.with_ymd_and_hms(2026, 3, 14, 19, 30, 5).unwrap();
+		let entry = |id, kind, sender: Option<&str>, recipient: Option<&str>, text: &str| LogEntry
+		{
+			id,
+			channelId: None,
+			deleted: false,
+			edited: None,
+			kind,
+			recipient: recipient.map(Into::into),
+			roll: None,
+			sender: sender.map(Into::into),
+			senderId: sender.map(|_| 1),
+			text: text.into(),
+			timestamp,
+		};
+		
+		let mut entries = vec![
+			entry(1, MessageKind::Say, Some("alice"), None, "Where is the *amulet*?"),
+			entry(2, MessageKind::Emote, Some("alice"), None, "searches the room"),
+			entry(3, MessageKind::Say, Some("alice"), Some("bob"), "<psst>"),
+			entry(4, MessageKind::Say, None, None, "bob (2) connected!"),
+			entry(5, MessageKind::Say, Some("bob"), None, ""),
+		];
+		
+		entries[1].edited = Some(timestamp);
+		entries[4].deleted = true;
+		entries[4].edited = Some(timestamp);
+		return entries;
+	}
+	
+	#[test]
+	fn markdown()
+	{
+		let exported = Utc.with_ymd_and_hms(2026, 3, 15, 0, 0, 0).unwrap();
+		let output = exportLog("Session 12", &entries(), ExportFormat::Markdown, exported).expect("Error exporting");
+		
+		assert_eq!(output, "# Session 12\n\nExported 2026-03-15 00:00:00 UTC\n\n\
+			- `2026-03-14 19:30:05` **alice**: Where is the \\*amulet\\*?\n\
+			- `2026-03-14 19:30:05` *alice searches the room* *(edited)*\n\
+			- `2026-03-14 19:30:05` **alice** (to bob): \\<psst\\>\n\
+			- `2026-03-14 19:30:05` bob (2) connected!\n");
+	}
+	
+	#[test]
+	fn html()
+	{
+		let output = exportLog("Session <12>", &entries(), ExportFormat::Html, Utc::now()).expect("Error exporting");
+		
+		assert!(output.starts_with("<!DOCTYPE html>"));
+		assert!(output.contains("<title>Session &lt;12&gt;</title>"));
+		assert!(output.contains("<li class=\"whisper\"><time datetime=\"2026-03-14T19:30:05+00:00\">2026-03-14 19:30:05</time><b>alice</b> (to bob): &lt;psst&gt;</li>"));
+		assert!(output.trim_end().ends_with("</html>"));
+	}
+	
+	#[test]
+	fn json()
+	{
+		let output = exportLog("Session 12", &entries(), ExportFormat::Json, Utc::now()).expect("Error exporting");
+		let value: serde_json::Value = serde_json::from_str(&output).expect("Export is not valid JSON");
+		
+		assert_eq!(value["title"], "Session 12");
+		assert_eq!(value["messages"][1]["kind"], "emote");
+		assert_eq!(value["messages"][2]["recipient"], "bob");
+		assert!(value["messages"][3].get("sender").is_none());
+		assert_eq!(value["messages"].as_array().map(Vec::len), Some(4));
+	}
+	
+	#[test]
+	fn names()
+	{
+		let exported = Utc.with_ymd_and_hms(2026, 3, 15, 0, 0, 0).unwrap();
+		assert_eq!(logFileName(Some(3), ExportFormat::Html, exported), "campaign-3-2026-03-15.html");
+		assert_eq!(logFileName(None, ExportFormat::Markdown, exported), "lobby-2026-03-15.md");
+		assert_eq!(logTitle(Some(3)), "Campaign 3 chat log");
+	}
+	
+	#[test]
+	fn formats()
+	{
+		assert_eq!("MD".parse::<ExportFormat>().ok(), Some(ExportFormat::Markdown));
+		assert_eq!("html".parse::<ExportFormat>().ok(), Some(ExportFormat::Html));
+		assert!("pdf".parse::<ExportFormat>().is_err());
+	}
+}
@@ -0,0 +1,101 @@
+use ::anyhow::Result;
+use ::chrono::{DateTime, Utc};
+use ::serde::Serialize;
+use crate::data::dao;
+use crate::data::{Message, MessageKind};
+use super::dice::RollResult;
+use super::messageText;
+
+/**
+A message from the chat log with the names of its sender and recipient
+resolved, along with any dice rolled for it.
+*/
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct LogEntry
+{
+	pub id: i32,
+	/// The channel the message was sent to, or `None` for the main chat.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub channelId: Option<i32>,
+	/// Whether the message was deleted, leaving its `text` empty.
+	#[serde(skip_serializing_if = "std::ops::Not::not")]
+	pub deleted: bool,
+	/// When the message was last edited or deleted.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub edited: Option<DateTime<Utc>>,
+	pub kind: MessageKind,
+	/// The username a whisper was sent to.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub recipient: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub roll: Option<RollResult>,
+	/// The username of the sender, if the message was sent by a user.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub sender: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub senderId: Option<i32>,
+	/// The message as it was sent, without the sender's name.
+	pub text: String,
+	pub timestamp: DateTime<Utc>,
+}
+
+impl LogEntry
+{
+	/**
+	The text shown in chat, including the sender's name.
+	*/
+	pub fn displayText(&self) -> String
+	{
+		return messageText(
+			self.kind,
+			self.sender.as_deref().unwrap_or_default(),
+			self.recipient.as_deref(),
+			&self.text
+		);
+	}
+}
+
+/**
+Resolve the sender, recipient and dice rolls of each of `messages`, keeping
+their order. The dice rolled for deleted messages are left out along with
+their text.
+*/
+pub async fn logEntries(messages: Vec<Message>) -> Result<Vec<LogEntry>>
+{
+	if messages.is_empty()
+	{
+		return Ok(vec![]);
+	}
+	
+	let users = dao::userGetAll().await?;
+	let rolls = dao::rollFindByMessages(messages.iter().map(|m| m.id).collect()).await?;
+	
+	let nameOf = |id: Option<i32>| match id
+	{
+		None => None,
+		Some(userId) => users.iter()
+			.find(|u| u.id == userId)
+			.map(|user| user.name.to_owned()),
+	};
+	
+	let entries = messages.into_iter()
+		.map(|m| LogEntry
+		{
+			id: m.id,
+			channelId: m.channelId,
+			deleted: m.deleted,
+			edited: m.edited.map(|edited| edited.and_utc()),
+			kind: m.kind(),
+			recipient: m.recipientId.map(|id| nameOf(Some(id)).unwrap_or_default()),
+			roll: rolls.iter()
+				.find(|r| r.messageId == m.id && !m.deleted)
+				.and_then(|r| serde_json::from_str::<RollResult>(&r.result).ok()),
+			sender: nameOf(m.userId),
+			senderId: m.userId,
+			text: m.text,
+			timestamp: m.timestamp.and_utc(),
+		})
+		.collect();
+	
+	return Ok(entries);
+}
@@ -0,0 +1,42 @@
+pub mod dice;
+pub mod export;
+pub mod log;
+pub mod sanitize;
+
+use crate::data::MessageKind;
+
+/**
+The text shown for a message from `sender`, which was whispered if it has a
+`recipient`.
+*/
+pub fn messageText(kind: MessageKind, sender: &str, recipient: Option<&str>, text: &str) -> String
+{
+	if let Some(recipient) = recipient
+	{
+		return format!("{} (to {}): {}", sender, recipient, text);
+	}
+	
+	return match (kind, sender.is_empty())
+	{
+		(_, true) => text.to_string(),
+		(MessageKind::Say, false) => format!("{}: {}", sender, text),
+		(MessageKind::Emote, false) => format!("{} {}", sender, text),
+		(MessageKind::OutOfCharacter, false) => format!("{} (OOC): {}", sender, text),
+		(MessageKind::Secret, false) => format!("{} (to GM): {}", sender, text),
+	};
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	
+	#[test]
+	fn formatting()
+	{
+		assert_eq!(messageText(MessageKind::Say, "alice", None, "hi"), "alice: hi");
+		assert_eq!(messageText(MessageKind::Emote, "alice", None, "waves"), "alice waves");
+		assert_eq!(messageText(MessageKind::Say, "alice", Some("bob"), "psst"), "alice (to bob): psst");
+		assert_eq!(messageText(MessageKind::Say, "", None, "Server restarting"), "Server restarting");
+	}
+}
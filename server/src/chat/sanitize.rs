@@ -0,0 +1,129 @@
+use ::anyhow::{Error, Result};
+use ::unicode_normalization::UnicodeNormalization;
+use crate::config::{ConfigChat, Normalization};
+
+/**
+Prepare chat `text` typed by a user to be stored and shown to other users.
+
+The text is normalized, stripped of control characters and bidirectional
+overrides and checked against the maximum length, as set in `config`. Text
+which is too long, or empty once stripped, is an error. BBCode is left as
+typed, to be escaped by `escapeBbcode` when the text is sent to clients.
+*/
+pub fn sanitize(text: &str, config: &ConfigChat) -> Result<String>
+{
+	let normalized: String = match config.normalization
+	{
+		Normalization::None => text.to_owned(),
+		Normalization::Nfc => text.nfc().collect(),
+		Normalization::Nfkc => text.nfkc().collect(),
+	};
+	
+	let stripped = match config.stripControl
+	{
+		true => normalized.chars().filter(|c| !isStripped(*c)).collect(),
+		false => normalized,
+	};
+	
+	if stripped.trim().is_empty()
+	{
+		return Err(Error::msg("text must not be empty"));
+	}
+	
+	let length = stripped.chars().count();
+	if config.maxLength > 0 && length > config.maxLength
+	{
+		return Err(Error::msg(format!("text is {} characters long, the limit is {}", length, config.maxLength)));
+	}
+	
+	return Ok(stripped);
+}
+
+/**
+Escape the square brackets which open and close BBCode tags, using the
+`[lb]` and `[rb]` tags understood by Godot's `RichTextLabel`.
+*/
+pub fn escapeBbcode(text: &str) -> String
+{
+	let mut escaped = String::with_capacity(text.len());
+	for c in text.chars()
+	{
+		match c
+		{
+			'[' => escaped.push_str("[lb]"),
+			']' => escaped.push_str("[rb]"),
+			_ => escaped.push(c),
+		}
+	}
+	
+	return escaped;
+}
+
+/**
+Whether `c` is removed when stripping control characters.
+
+Line breaks and tabs are kept, carriage returns are not.
+*/
+fn isStripped(c: char) -> bool
+{
+	return match c
+	{
+		'\n' | '\t' => false,
+		// Bidirectional embeddings, overrides and isolates
+		'\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}' => true,
+		_ => c.is_control(),
+	};
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	
+	#[test]
+	fn pipeline()
+	{
+		let config = ConfigChat::default();
+		
+		assert_eq!(sanitize("e\u{301}t\u{e9}", &config).ok(), Some("\u{e9}t\u{e9}".to_string()));
+		assert_eq!(sanitize("a\u{7}b\r\n\tc", &config).ok(), Some("ab\n\tc".to_string()));
+		assert_eq!(sanitize("evil\u{202E}txt.exe\u{2066}", &config).ok(), Some("eviltxt.exe".to_string()));
+		assert_eq!(sanitize("[b]loud[/b]", &config).ok(), Some("[b]loud[/b]".to_string()));
+	}
+	
+	#[test]
+	fn escaping()
+	{
+		assert_eq!(escapeBbcode("[b]loud[/b]"), "[lb]b[rb]loud[lb]/b[rb]");
+		assert_eq!(escapeBbcode("[[]]"), "[lb][lb][rb][rb]");
+		assert_eq!(escapeBbcode("plain"), "plain");
+	}
+	
+	#[test]
+	fn violations()
+	{
+		let config = ConfigChat { maxLength: 5, ..Default::default() };
+		
+		assert_eq!(sanitize("12345", &config).ok(), Some("12345".to_string()));
+		assert!(sanitize("123456", &config).is_err());
+		assert!(sanitize("\u{0}\u{202E} ", &config).is_err());
+		
+		// The limit applies to the text as typed, which escaping may later lengthen
+		assert_eq!(sanitize("[[]]", &config).ok(), Some("[[]]".to_string()));
+	}
+	
+	#[test]
+	fn configurable()
+	{
+		let config = ConfigChat
+		{
+			escapeBbcode: false,
+			maxLength: 0,
+			normalization: Normalization::Nfkc,
+			stripControl: false,
+		};
+		
+		assert_eq!(sanitize("\u{ff21}[i]\u{7}", &config).ok(), Some("A[i]\u{7}".to_string()));
+		assert!(sanitize(&"a".repeat(100_000), &config).is_ok());
+	}
+}
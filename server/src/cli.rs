@@ -0,0 +1,240 @@
+use std::path::PathBuf;
+use ::anyhow::{Error, Result};
+use ::chrono::{DateTime, Utc};
+use crate::chat::export::{exportLog, logTitle, ExportFormat};
+use crate::chat::log::logEntries;
+use crate::data::{dao, Role};
+use crate::net::hashPassword;
+
+const Usage: &'static str = "Usage:
+	server                  Start the server
+	server export [options] Export a chat log
+	server help             Show this message
+	server set-password <name>
+	                        Set a user's password, read from standard input
+	server set-role <name> <spectator|player|gm>
+	                        Set a user's server wide role
+
+Export options:
+	--campaign <id|lobby>   The campaign whose chat log is exported (default: lobby)
+	--format <format>       markdown, html or json (default: markdown)
+	--start <seconds>       Only export messages sent at or after this Unix timestamp
+	--end <seconds>         Only export messages sent at or before this Unix timestamp
+	--output <path>         Write the export to a file instead of standard output";
+
+/**
+A command given to the server on the command line.
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CliCommand
+{
+	Export(ExportOptions),
+	Help,
+	/// Set the password of the named user.
+	SetPassword(String),
+	/// Set the server wide role of the named user.
+	SetRole(String, Role),
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExportOptions
+{
+	/// The campaign whose chat log is exported, or `None` for the lobby.
+	pub campaignId: Option<i32>,
+	pub end: Option<DateTime<Utc>>,
+	pub format: ExportFormat,
+	/// Where the export is written, or `None` for standard output.
+	pub output: Option<PathBuf>,
+	pub start: Option<DateTime<Utc>>,
+}
+
+/**
+Parse the command line `arguments`, not including the program name.
+*/
+pub fn parseArguments(arguments: &[String]) -> Result<CliCommand>
+{
+	let mut iter = arguments.iter();
+	return match iter.next().map(String::as_str)
+	{
+		Some("export") => {
+			let mut options = ExportOptions::default();
+			while let Some(flag) = iter.next()
+			{
+				let value = iter.next()
+					.ok_or_else(|| Error::msg(format!("Missing value for {}\n\n{}", flag, Usage)))?;
+				
+				match flag.as_str()
+				{
+					"--campaign" => options.campaignId = match value.as_str()
+					{
+						"lobby" => None,
+						id => Some(id.parse().map_err(|_| Error::msg(format!("Invalid campaign: {}", id)))?),
+					},
+					"--end" => options.end = Some(parseTimestamp(value)?),
+					"--format" => options.format = value.parse()?,
+					"--output" => options.output = Some(PathBuf::from(value)),
+					"--start" => options.start = Some(parseTimestamp(value)?),
+					_ => return Err(Error::msg(format!("Unknown option: {}\n\n{}", flag, Usage))),
+				}
+			}
+			
+			Ok(CliCommand::Export(options))
+		},
+		Some("help") | Some("--help") | Some("-h") => Ok(CliCommand::Help),
+		Some("set-password") => match (iter.next(), iter.next())
+		{
+			(Some(name), None) => Ok(CliCommand::SetPassword(name.to_owned())),
+			_ => Err(Error::msg(format!("set-password takes a single username\n\n{}", Usage))),
+		},
+		Some("set-role") => match (iter.next(), iter.next(), iter.next())
+		{
+			(Some(name), Some(role), None) => Ok(CliCommand::SetRole(name.to_owned(), role.parse()?)),
+			_ => Err(Error::msg(format!("set-role takes a username and a role\n\n{}", Usage))),
+		},
+		Some(other) => Err(Error::msg(format!("Unknown command: {}\n\n{}", other, Usage))),
+		None => Ok(CliCommand::Help),
+	};
+}
+
+/**
+Run `command`. The database must already be initialized.
+*/
+pub async fn run(command: CliCommand) -> Result<()>
+{
+	match command
+	{
+		CliCommand::Export(options) => export(options).await?,
+		CliCommand::Help => println!("{}", Usage),
+		CliCommand::SetPassword(name) => setPassword(name).await?,
+		CliCommand::SetRole(name, role) => setRole(name, role).await?,
+	}
+	
+	return Ok(());
+}
+
+async fn export(options: ExportOptions) -> Result<()>
+{
+	let start = options.start.unwrap_or(DateTime::UNIX_EPOCH).naive_utc();
+	let end = options.end.unwrap_or(Utc::now()).naive_utc();
+	
+	let messages = dao::messageGetAll().await?
+		.into_iter()
+		.filter(|m| m.campaignId == options.campaignId && m.timestamp >= start && m.timestamp <= end)
+		.collect();
+	
+	let entries = logEntries(messages).await?;
+	let content = exportLog(&logTitle(options.campaignId), &entries, options.format, Utc::now())?;
+	
+	match options.output
+	{
+		Some(path) => std::fs::write(path, content)?,
+		None => print!("{}", content),
+	}
+	
+	return Ok(());
+}
+
+/**
+Set the password of the user called `name` to the first line of standard input.
+
+This is how accounts created before passwords were required are given one.
+*/
+async fn setPassword(name: String) -> Result<()>
+{
+	let user = dao::userFind(name.to_owned()).await?
+		.ok_or_else(|| Error::msg(format!("Unknown user: {}", name)))?;
+	
+	let mut line = String::new();
+	std::io::stdin().read_line(&mut line)?;
+	let password = line.trim_end_matches(['\r', '\n']);
+	if password.is_empty()
+	{
+		return Err(Error::msg("The password must not be empty"));
+	}
+	
+	dao::userUpdatePassword(user.id, hashPassword(password)?).await?;
+	println!("Password set for {}", name);
+	
+	return Ok(());
+}
+
+/**
+Set the server wide role of the user called `name`.
+
+This is how a game master is chosen on a server whose accounts all predate roles.
+*/
+async fn setRole(name: String, role: Role) -> Result<()>
+{
+	let user = dao::userFind(name.to_owned()).await?
+		.ok_or_else(|| Error::msg(format!("Unknown user: {}", name)))?;
+	
+	dao::userUpdateRole(user.id, role).await?;
+	println!("{} is now a {:?}", name, role);
+	
+	return Ok(());
+}
+
+fn parseTimestamp(value: &str) -> Result<DateTime<Utc>>
+{
+	return value.parse::<i64>()
+		.ok()
+		.and_then(|seconds| DateTime::from_timestamp(seconds, 0))
+		.ok_or_else(|| Error::msg(format!("Invalid timestamp: {}", value)));
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	
+	fn arguments(text: &str) -> Vec<String>
+	{
+		return text.split_whitespace().map(String::from).collect();
+	}
+	
+	#[test]
+	fn export()
+	{
+		let command = parseArguments(&arguments("export --campaign 3 --format html --start 60 --output log.html"))
+			.expect("Error parsing arguments");
+		
+		assert_eq!(command, CliCommand::Export(ExportOptions
+		{
+			campaignId: Some(3),
+			end: None,
+			format: ExportFormat::Html,
+			output: Some(PathBuf::from("log.html")),
+			start: DateTime::from_timestamp(60, 0),
+		}));
+		
+		assert_eq!(parseArguments(&arguments("export --campaign lobby")).ok(), Some(CliCommand::Export(ExportOptions::default())));
+	}
+	
+	#[test]
+	fn setPassword()
+	{
+		assert_eq!(parseArguments(&arguments("set-password alice")).ok(), Some(CliCommand::SetPassword("alice".to_owned())));
+	}
+	
+	#[test]
+	fn setRole()
+	{
+		assert_eq!(parseArguments(&arguments("set-role alice gm")).ok(), Some(CliCommand::SetRole("alice".to_owned(), Role::GameMaster)));
+		assert_eq!(parseArguments(&arguments("set-role bob Spectator")).ok(), Some(CliCommand::SetRole("bob".to_owned(), Role::Spectator)));
+	}
+	
+	#[test]
+	fn invalid()
+	{
+		assert!(parseArguments(&arguments("export --campaign")).is_err());
+		assert!(parseArguments(&arguments("export --campaign three")).is_err());
+		assert!(parseArguments(&arguments("export --format pdf")).is_err());
+		assert!(parseArguments(&arguments("export --verbose yes")).is_err());
+		assert!(parseArguments(&arguments("serve")).is_err());
+		assert!(parseArguments(&arguments("set-password")).is_err());
+		assert!(parseArguments(&arguments("set-password alice bob")).is_err());
+		assert!(parseArguments(&arguments("set-role alice")).is_err());
+		assert!(parseArguments(&arguments("set-role alice admin")).is_err());
+		assert_eq!(parseArguments(&arguments("help")).ok(), Some(CliCommand::Help));
+	}
+}
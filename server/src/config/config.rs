@@ -4,8 +4,12 @@ use ::serde::Deserialize;
 pub struct Config
 {
 	pub assets: ConfigAssets,
+	#[serde(default)]
+	pub chat: ConfigChat,
 	pub database: ConfigDatabase,
 	pub network: ConfigNetwork,
+	#[serde(default)]
+	pub session: ConfigSession,
 }
 
 impl Config
@@ -20,16 +24,22 @@ impl Config
 				path: None,
 			},
 			
+			chat: ConfigChat::default(),
+			
 			database: ConfigDatabase
 			{
-				path: "testData.sqlite".into(),
+				path: crate::data::InMemoryDatabase.into(),
 			},
 			
 			network: ConfigNetwork
 			{
 				ip: "127.0.0.1".into(),
 				port: 8080,
+				heartbeat: ConfigHeartbeat::default(),
+				tls: ConfigTls::default(),
 			},
+			
+			session: ConfigSession::default(),
 		};
 	}
 }
@@ -40,6 +50,47 @@ pub struct ConfigAssets
 	pub path: Option<String>,
 }
 
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ConfigChat
+{
+	/// Escape BBCode tags in chat sent to clients, so that they display them as typed.
+	pub escapeBbcode: bool,
+	/// Maximum number of characters in a chat message, or 0 for no limit.
+	pub maxLength: usize,
+	pub normalization: Normalization,
+	/// Remove control characters, other than line breaks and tabs, and bidirectional overrides.
+	pub stripControl: bool,
+}
+
+impl Default for ConfigChat
+{
+	fn default() -> Self
+	{
+		return Self
+		{
+			escapeBbcode: true,
+			maxLength: 2000,
+			normalization: Normalization::Nfc,
+			stripControl: true,
+		};
+	}
+}
+
+/**
+The Unicode normalization form applied to chat text.
+*/
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Normalization
+{
+	None,
+	/// Canonical composition, which keeps the text as typed.
+	Nfc,
+	/// Compatibility composition, which also folds look-alike characters such as full width letters.
+	Nfkc,
+}
+
 #[derive(Clone, Debug, Default, Deserialize)]
 pub struct ConfigDatabase
 {
@@ -51,6 +102,10 @@ pub struct ConfigNetwork
 {
 	pub ip: String,
 	pub port: u16,
+	#[serde(default)]
+	pub heartbeat: ConfigHeartbeat,
+	#[serde(default)]
+	pub tls: ConfigTls,
 }
 
 impl ConfigNetwork
@@ -63,3 +118,77 @@ impl ConfigNetwork
 		return format!("{}:{}", self.ip, self.port);
 	}
 }
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ConfigHeartbeat
+{
+	/// Seconds without any frame other than pongs before a client is disconnected, or 0 to never disconnect idle clients.
+	pub idleTimeout: u64,
+	/// Seconds between pings sent to each client, or 0 to disable pings.
+	pub pingInterval: u64,
+	/// Seconds to wait for any frame after a ping before the connection is considered dead, or 0 to wait forever.
+	pub pongTimeout: u64,
+}
+
+impl Default for ConfigHeartbeat
+{
+	fn default() -> Self
+	{
+		return Self
+		{
+			idleTimeout: 0,
+			pingInterval: 30,
+			pongTimeout: 10,
+		};
+	}
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct ConfigTls
+{
+	#[serde(default)]
+	pub mode: TlsMode,
+	/// Path to the PEM encoded certificate chain, relative to the local data directory.
+	pub certificate: Option<String>,
+	/// Path to the PEM encoded private key, relative to the local data directory.
+	pub key: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum TlsMode
+{
+	/// Plain `ws://` connections.
+	None,
+	/// `wss://` using the configured certificate and key files.
+	Files,
+	/// `wss://` using a self-signed certificate, generated on first use, for LAN play.
+	SelfSigned,
+}
+
+impl Default for TlsMode
+{
+	fn default() -> Self
+	{
+		return Self::None;
+	}
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ConfigSession
+{
+	/// Number of seconds a disconnected client may resume its session.
+	pub gracePeriod: u64,
+}
+
+impl Default for ConfigSession
+{
+	fn default() -> Self
+	{
+		return Self
+		{
+			gracePeriod: 300,
+		};
+	}
+}
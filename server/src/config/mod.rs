@@ -7,7 +7,7 @@ use ::anyhow::Result;
 use ::directories::ProjectDirs;
 #[cfg(not(test))]
 use ::toml;
-pub use self::config::Config;
+pub use self::config::{Config, ConfigChat, ConfigHeartbeat, ConfigTls, Normalization, TlsMode};
 
 #[cfg(not(test))]
 pub const ConfigPath: &'static str = "./config.toml";
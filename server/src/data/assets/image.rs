@@ -1,15 +1,29 @@
 use std::fs::read;
 use std::path::PathBuf;
 use ::anyhow::Result;
+use ::imagesize::blob_size;
 use super::Asset;
 
 pub struct Image
 {
 	/// Absolute path to the image file.
+	#[allow(dead_code)]
 	path: PathBuf,
 	data: Vec<u8>,
 }
 
+impl Image
+{
+	/**
+	The `(width, height)` of the image in pixels, read from its header.
+	*/
+	pub fn dimensions(&self) -> Result<(i64, i64)>
+	{
+		let size = blob_size(&self.data)?;
+		return Ok((size.width as i64, size.height as i64));
+	}
+}
+
 impl Asset for Image
 {
 	fn bytes(&self) -> Result<Vec<u8>>
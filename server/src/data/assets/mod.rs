@@ -1,6 +1,6 @@
 mod image;
 
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
 use ::anyhow::{Error, Result};
 use crate::getConfig;
 use crate::config::localDataPath;
@@ -26,6 +26,32 @@ pub fn loadAsset<T>(relativePath: String) -> Result<T>
 	};
 }
 
+/**
+Normalize `path` as a path relative to the asset directory.
+
+Returns `None` for empty or absolute paths and for paths which would leave the
+asset directory, so that clients cannot name arbitrary files on the server.
+*/
+pub fn relativeAssetPath(path: &str) -> Option<String>
+{
+	let mut parts = vec![];
+	for component in Path::new(path.trim()).components()
+	{
+		match component
+		{
+			Component::Normal(part) => parts.push(part.to_str()?),
+			Component::CurDir => {},
+			_ => return None,
+		}
+	}
+	
+	return match parts.is_empty()
+	{
+		true => None,
+		false => Some(parts.join("/")),
+	};
+}
+
 fn getAssetDirectory() -> Option<PathBuf>
 {
 	let config = getConfig();
@@ -42,3 +68,20 @@ fn getAssetDirectory() -> Option<PathBuf>
 		Some(root) => Some(PathBuf::from(root).join(AssetDirectoryName)),
 	}
 }
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	
+	#[test]
+	fn relativePaths()
+	{
+		assert_eq!(relativeAssetPath("maps/cave.png"), Some("maps/cave.png".into()));
+		assert_eq!(relativeAssetPath(" ./maps//cave.png "), Some("maps/cave.png".into()));
+		assert_eq!(relativeAssetPath("../config.toml"), None);
+		assert_eq!(relativeAssetPath("maps/../../secret.png"), None);
+		assert_eq!(relativeAssetPath("/etc/passwd"), None);
+		assert_eq!(relativeAssetPath(""), None);
+	}
+}
@@ -1,11 +1,306 @@
 use ::anyhow::Result;
 use ::chrono::NaiveDateTime;
-use ::diesel::{ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl, SelectableHelper};
+use ::diesel::{BoolExpressionMethods, Connection, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl, SelectableHelper,
+	SqliteConnection};
+use ::diesel::SqliteExpressionMethods;
+use ::diesel::sql_types::{BigInt, Bool, Integer, Nullable, Text, Timestamp};
+use ::diesel::sqlite::Sqlite;
 use super::db::getDatabase;
-use super::model::{Message, NewMessage, NewUser, User};
+use super::model::{CampaignRole, Channel, ChannelMember, DoorState, FogRegion, ImageAsset, Message, MessageKind,
+	MessageRevision, MessageScope, MessageSearch, MessageSearchHit, NewCampaignRole, NewChannel, NewChannelMember,
+	NewFogRegion, NewImageAsset, NewMessage, NewMessageRevision, NewRoll, NewScene2D, NewToken, NewUser, NewWall, Role,
+	Roll, Scene2D, Token, User, Wall};
 use super::schema;
+use super::schema::campaignRoles::dsl::campaignRoles;
+use super::schema::channelMembers::dsl::channelMembers;
+use super::schema::channels::dsl::channels;
+use super::schema::fogRegions::dsl::fogRegions;
+use super::schema::imageAssets::dsl::imageAssets;
+use super::schema::messageRevisions::dsl::messageRevisions;
 use super::schema::messages::dsl::messages;
+use super::schema::rolls::dsl::rolls;
+use super::schema::scenes2d::dsl::scenes2d;
+use super::schema::tokens::dsl::tokens;
 use super::schema::users::dsl::users;
+use super::schema::walls::dsl::walls;
+
+pub async fn campaignRoleFind(campaignId: i32, userId: i32) -> Result<Option<CampaignRole>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => None,
+		Some(ref mut conn) => campaignRoles
+			.filter(super::schema::campaignRoles::dsl::campaignId.eq(campaignId))
+			.filter(super::schema::campaignRoles::dsl::userId.eq(userId))
+			.first(conn)
+			.optional()?
+	};
+	
+	return Ok(result);
+}
+
+/**
+Create or replace the role of a user within a campaign.
+*/
+pub async fn campaignRoleSet(newCampaignRole: NewCampaignRole) -> Result<Option<CampaignRole>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => None,
+		Some(ref mut conn) => Some(diesel::insert_into(schema::campaignRoles::table)
+			.values(&newCampaignRole)
+			.on_conflict((
+				super::schema::campaignRoles::dsl::campaignId,
+				super::schema::campaignRoles::dsl::userId
+			))
+			.do_update()
+			.set(super::schema::campaignRoles::dsl::role.eq(newCampaignRole.role))
+			.returning(CampaignRole::as_returning())
+			.get_result(conn)?)
+	};
+	
+	return Ok(result);
+}
+
+/**
+Create a channel with the users `memberIds` as its members, in a single
+transaction.
+*/
+pub async fn channelCreate(newChannel: NewChannel, memberIds: Vec<i32>) -> Result<Option<Channel>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => None,
+		Some(ref mut conn) => Some(conn.transaction(|conn| {
+			let channel = diesel::insert_into(schema::channels::table)
+				.values(newChannel)
+				.returning(Channel::as_returning())
+				.get_result(conn)?;
+			
+			insertChannelMembers(conn, channel.id, memberIds)?;
+			return diesel::QueryResult::Ok(channel);
+		})?)
+	};
+	
+	return Ok(result);
+}
+
+/**
+Find the channels of the campaign `campaignId`, or of the lobby when `None`,
+in the order they were created.
+*/
+pub async fn channelFindByCampaign(campaignId: Option<i32>) -> Result<Vec<Channel>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => vec![],
+		Some(ref mut conn) => channels
+			.filter(super::schema::channels::dsl::campaignId.is(campaignId))
+			.order(super::schema::channels::dsl::id.asc())
+			.load(conn)?
+	};
+	
+	return Ok(result);
+}
+
+pub async fn channelGet(id: i32) -> Result<Option<Channel>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => None,
+		Some(ref mut conn) => channels
+			.filter(super::schema::channels::dsl::id.eq(id))
+			.first(conn)
+			.optional()?
+	};
+	
+	return Ok(result);
+}
+
+/**
+Find the members of any of the channels `channelIds`.
+*/
+pub async fn channelMemberFindByChannels(channelIds: Vec<i32>) -> Result<Vec<ChannelMember>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => vec![],
+		Some(ref mut conn) => channelMembers
+			.filter(super::schema::channelMembers::dsl::channelId.eq_any(channelIds))
+			.load(conn)?
+	};
+	
+	return Ok(result);
+}
+
+/**
+Update the name and visibility of `channel`, and replace its members with
+`memberIds` unless it is `None`, in a single transaction.
+*/
+pub async fn channelUpdate(channel: Channel, memberIds: Option<Vec<i32>>) -> Result<Option<Channel>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => None,
+		Some(ref mut conn) => Some(conn.transaction(|conn| {
+			let updated = diesel::update(channels.filter(super::schema::channels::dsl::id.eq(channel.id)))
+				.set((
+					super::schema::channels::dsl::name.eq(channel.name),
+					super::schema::channels::dsl::visibility.eq(channel.visibility),
+				))
+				.returning(Channel::as_returning())
+				.get_result(conn)?;
+			
+			if let Some(memberIds) = memberIds
+			{
+				diesel::delete(channelMembers.filter(super::schema::channelMembers::dsl::channelId.eq(channel.id)))
+					.execute(conn)?;
+				
+				insertChannelMembers(conn, channel.id, memberIds)?;
+			}
+			
+			return diesel::QueryResult::Ok(updated);
+		})?)
+	};
+	
+	return Ok(result);
+}
+
+/**
+Create fog regions, in a single transaction, in the order given.
+*/
+pub async fn fogRegionCreate(newRegions: Vec<NewFogRegion>) -> Result<Vec<FogRegion>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => vec![],
+		Some(ref mut conn) => conn.transaction(|conn| {
+			let mut created = vec![];
+			for newRegion in newRegions
+			{
+				created.push(diesel::insert_into(schema::fogRegions::table)
+					.values(newRegion)
+					.returning(FogRegion::as_returning())
+					.get_result(conn)?);
+			}
+			
+			return diesel::QueryResult::Ok(created);
+		})?
+	};
+	
+	return Ok(result);
+}
+
+/**
+Find the fog regions of the scene `sceneId`, in the order they apply.
+*/
+pub async fn fogRegionFindByScene(sceneId: i32) -> Result<Vec<FogRegion>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => vec![],
+		Some(ref mut conn) => fogRegions
+			.filter(super::schema::fogRegions::dsl::sceneId.eq(sceneId))
+			.order(super::schema::fogRegions::dsl::id.asc())
+			.load(conn)?
+	};
+	
+	return Ok(result);
+}
+
+pub async fn imageAssetCreate(newImageAsset: NewImageAsset) -> Result<Option<ImageAsset>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => None,
+		Some(ref mut conn) => Some(diesel::insert_into(schema::imageAssets::table)
+			.values(newImageAsset)
+			.returning(ImageAsset::as_returning())
+			.get_result(conn)?)
+	};
+	
+	return Ok(result);
+}
+
+/**
+Find the image asset registered for `path`, relative to the assets directory.
+*/
+pub async fn imageAssetFindByPath(path: String) -> Result<Option<ImageAsset>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => None,
+		Some(ref mut conn) => imageAssets
+			.filter(super::schema::imageAssets::dsl::path.eq(path))
+			.order(super::schema::imageAssets::dsl::id.asc())
+			.first(conn)
+			.optional()?
+	};
+	
+	return Ok(result);
+}
+
+pub async fn imageAssetGet(id: i32) -> Result<Option<ImageAsset>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => None,
+		Some(ref mut conn) => imageAssets
+			.find(id)
+			.first(conn)
+			.optional()?
+	};
+	
+	return Ok(result);
+}
+
+/**
+Update the recorded dimensions of the image asset `id`, after its file has
+been replaced.
+*/
+pub async fn imageAssetUpdateSize(id: i32, width: i64, height: i64) -> Result<Option<ImageAsset>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => None,
+		Some(ref mut conn) => diesel::update(imageAssets.filter(super::schema::imageAssets::dsl::id.eq(id)))
+			.set((
+				super::schema::imageAssets::dsl::height.eq(height),
+				super::schema::imageAssets::dsl::width.eq(width),
+			))
+			.returning(ImageAsset::as_returning())
+			.get_result(conn)
+			.optional()?
+	};
+	
+	return Ok(result);
+}
 
 pub async fn messageCreate(newMessage: NewMessage) -> Result<Option<Message>>
 {
@@ -22,95 +317,721 @@ pub async fn messageCreate(newMessage: NewMessage) -> Result<Option<Message>>
 		}
 	};
 	
-	return Ok(message);
+	return Ok(message);
+}
+
+/**
+Create a message along with the dice roll it reports, in a single transaction.
+`newRoll.messageId` is replaced with the id of the new message.
+*/
+pub async fn messageCreateWithRoll(newMessage: NewMessage, mut newRoll: NewRoll) -> Result<Option<(Message, Roll)>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => None,
+		Some(ref mut conn) => Some(conn.transaction(|conn| {
+			let message = diesel::insert_into(schema::messages::table)
+				.values(newMessage)
+				.returning(Message::as_returning())
+				.get_result(conn)?;
+			
+			newRoll.messageId = message.id;
+			let roll = diesel::insert_into(schema::rolls::table)
+				.values(newRoll)
+				.returning(Roll::as_returning())
+				.get_result(conn)?;
+			
+			return diesel::QueryResult::Ok((message, roll));
+		})?)
+	};
+	
+	return Ok(result);
+}
+
+/**
+Find the message `id`, provided it is visible within `scope`.
+*/
+pub async fn messageFind(scope: MessageScope, id: i32) -> Result<Option<Message>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => None,
+		Some(ref mut conn) => visibleMessages(scope)
+			.filter(super::schema::messages::dsl::id.eq(id))
+			.first(conn)
+			.optional()?
+	};
+	
+	return Ok(result);
+}
+
+/**
+Find the messages visible within `scope` which were sent between `start` and
+`end`.
+*/
+pub async fn messageFindByDateRange(scope: MessageScope, start: NaiveDateTime, end: NaiveDateTime) -> Result<Vec<Message>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => vec![],
+		Some(ref mut conn) => visibleMessages(scope)
+			.filter(super::schema::messages::dsl::timestamp.ge(start))
+			.filter(super::schema::messages::dsl::timestamp.le(end))
+			.load(conn)?
+	};
+	
+	return Ok(result);
+}
+
+/**
+Find up to `limit` of the latest messages visible within `scope` whose ids are
+lower than `before`, ordered from oldest to newest.
+*/
+pub async fn messageFindPage(scope: MessageScope, before: Option<i32>, limit: i64) -> Result<Vec<Message>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let mut result: Vec<Message> = match db.connection
+	{
+		None => vec![],
+		Some(ref mut conn) => visibleMessages(scope)
+			.filter(super::schema::messages::dsl::id.lt(before.unwrap_or(i32::MAX)))
+			.order(super::schema::messages::dsl::id.desc())
+			.limit(limit)
+			.load(conn)?
+	};
+	
+	result.reverse();
+	return Ok(result);
+}
+
+/**
+Search the text of the messages visible within `scope`, returning the best
+matches first.
+
+Messages which are not visible are excluded by the same rules as
+`visibleMessages`.
+*/
+pub async fn messageSearch(scope: MessageScope, search: MessageSearch) -> Result<Vec<MessageSearchHit>>
+{
+	let terms = match searchTerms(&search.terms)
+	{
+		Some(terms) => terms,
+		None => return Ok(vec![]),
+	};
+	
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => vec![],
+		Some(ref mut conn) => diesel::sql_query(r#"SELECT messages.*,
+				snippet(messagesSearch, 0, ?, ?, '...', 16) AS marked,
+				snippet(messagesSearch, 0, '', '', '...', 16) AS snippet
+			FROM messagesSearch
+			JOIN messages ON messages.id = messagesSearch.rowid
+			WHERE messagesSearch MATCH ?
+				AND messages.campaignId IS ?
+				AND ((? AND messages.channelId IS NULL) OR messages.channelId IN (SELECT value FROM json_each(?)))
+				AND (messages.recipientId IS NULL OR messages.recipientId IS ? OR messages.userId IS ?)
+				AND (? OR messages.kind != ? OR messages.userId IS ?)
+				AND (? IS NULL OR messages.userId = ?)
+				AND (? IS NULL OR messages.timestamp >= ?)
+				AND (? IS NULL OR messages.timestamp <= ?)
+			ORDER BY rank
+			LIMIT ?"#)
+			.bind::<Text, _>(MessageSearchHit::MatchStart.to_string())
+			.bind::<Text, _>(MessageSearchHit::MatchEnd.to_string())
+			.bind::<Text, _>(terms)
+			.bind::<Nullable<Integer>, _>(scope.campaignId)
+			.bind::<Bool, _>(scope.mainChat)
+			.bind::<Text, _>(serde_json::to_string(&scope.channels)?)
+			.bind::<Nullable<Integer>, _>(scope.userId)
+			.bind::<Nullable<Integer>, _>(scope.userId)
+			.bind::<Bool, _>(scope.gameMaster)
+			.bind::<Integer, _>(i32::from(MessageKind::Secret))
+			.bind::<Nullable<Integer>, _>(scope.userId)
+			.bind::<Nullable<Integer>, _>(search.userId)
+			.bind::<Nullable<Integer>, _>(search.userId)
+			.bind::<Nullable<Timestamp>, _>(search.start)
+			.bind::<Nullable<Timestamp>, _>(search.start)
+			.bind::<Nullable<Timestamp>, _>(search.end)
+			.bind::<Nullable<Timestamp>, _>(search.end)
+			.bind::<BigInt, _>(search.limit)
+			.load(conn)?
+	};
+	
+	return Ok(result);
+}
+
+/**
+Replace the text of the message `revision.messageId` with `text`, marking it
+as edited and, if `deleted`, as deleted. `revision` records the text being
+replaced, in the same transaction.
+*/
+pub async fn messageRevise(text: String, deleted: bool, revision: NewMessageRevision) -> Result<Option<Message>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => None,
+		Some(ref mut conn) => Some(conn.transaction(|conn| {
+			let edited = revision.timestamp;
+			let id = revision.messageId;
+			
+			diesel::insert_into(schema::messageRevisions::table)
+				.values(revision)
+				.execute(conn)?;
+			
+			return diesel::update(messages.filter(super::schema::messages::dsl::id.eq(id)))
+				.set((
+					super::schema::messages::dsl::deleted.eq(deleted),
+					super::schema::messages::dsl::edited.eq(Some(edited)),
+					super::schema::messages::dsl::text.eq(text),
+				))
+				.returning(Message::as_returning())
+				.get_result(conn);
+		})?)
+	};
+	
+	return Ok(result);
+}
+
+/**
+Find the earlier versions of the message `messageId`, oldest first.
+*/
+pub async fn messageRevisionFindByMessage(messageId: i32) -> Result<Vec<MessageRevision>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => vec![],
+		Some(ref mut conn) => messageRevisions
+			.filter(super::schema::messageRevisions::dsl::messageId.eq(messageId))
+			.order(super::schema::messageRevisions::dsl::id.asc())
+			.load(conn)?
+	};
+	
+	return Ok(result);
+}
+
+#[allow(dead_code)]
+pub async fn messageFindByUser(userId: i32) -> Result<Vec<Message>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => vec![],
+		Some(ref mut conn) => messages
+			.filter(super::schema::messages::dsl::userId.eq(userId))
+			.load(conn)?
+	};
+	
+	return Ok(result);
+}
+
+pub async fn messageGetAll() -> Result<Vec<Message>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => vec![],
+		Some(ref mut conn) => messages.load(conn)?
+	};
+	
+	return Ok(result);
+}
+
+/**
+Find the dice rolls reported by any of the messages `messageIds`.
+*/
+pub async fn rollFindByMessages(messageIds: Vec<i32>) -> Result<Vec<Roll>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => vec![],
+		Some(ref mut conn) => rolls
+			.filter(super::schema::rolls::dsl::messageId.eq_any(messageIds))
+			.load(conn)?
+	};
+	
+	return Ok(result);
+}
+
+pub async fn scene2dCreate(newScene: NewScene2D) -> Result<Option<Scene2D>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => None,
+		Some(ref mut conn) => Some(diesel::insert_into(schema::scenes2d::table)
+			.values(newScene)
+			.returning(Scene2D::as_returning())
+			.get_result(conn)?)
+	};
+	
+	return Ok(result);
+}
+
+/**
+Delete the scene `id` along with its tokens, fog and walls, in a single
+transaction, returning whether it existed.
+*/
+pub async fn scene2dDelete(id: i32) -> Result<bool>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => 0,
+		Some(ref mut conn) => conn.transaction(|conn| {
+			diesel::delete(tokens.filter(super::schema::tokens::dsl::sceneId.eq(id)))
+				.execute(conn)?;
+			
+			diesel::delete(fogRegions.filter(super::schema::fogRegions::dsl::sceneId.eq(id)))
+				.execute(conn)?;
+			
+			diesel::delete(walls.filter(super::schema::walls::dsl::sceneId.eq(id)))
+				.execute(conn)?;
+			
+			return diesel::delete(scenes2d.filter(super::schema::scenes2d::dsl::id.eq(id)))
+				.execute(conn);
+		})?
+	};
+	
+	return Ok(result > 0);
+}
+
+/**
+Find the scenes of the campaign `campaignId`, or of the lobby when `None`, in
+the order they were created.
+*/
+pub async fn scene2dFindByCampaign(campaignId: Option<i32>) -> Result<Vec<Scene2D>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => vec![],
+		Some(ref mut conn) => scenes2d
+			.filter(super::schema::scenes2d::dsl::campaignId.is(campaignId))
+			.order(super::schema::scenes2d::dsl::id.asc())
+			.load(conn)?
+	};
+	
+	return Ok(result);
+}
+
+pub async fn scene2dGet(id: i32) -> Result<Option<Scene2D>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => None,
+		Some(ref mut conn) => scenes2d
+			.find(id)
+			.first(conn)
+			.optional()?
+	};
+	
+	return Ok(result);
+}
+
+pub async fn scene2dRename(id: i32, name: String) -> Result<Option<Scene2D>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => None,
+		Some(ref mut conn) => diesel::update(scenes2d.filter(super::schema::scenes2d::dsl::id.eq(id)))
+			.set(super::schema::scenes2d::dsl::name.eq(name))
+			.returning(Scene2D::as_returning())
+			.get_result(conn)
+			.optional()?
+	};
+	
+	return Ok(result);
+}
+
+/**
+Remove every fog region of the scene `id`, covering it in fog again if
+`enabled` or lifting its fog otherwise, in a single transaction.
+*/
+pub async fn scene2dResetFog(id: i32, enabled: bool) -> Result<Option<Scene2D>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => None,
+		Some(ref mut conn) => conn.transaction(|conn| {
+			diesel::delete(fogRegions.filter(super::schema::fogRegions::dsl::sceneId.eq(id)))
+				.execute(conn)?;
+			
+			return diesel::update(scenes2d.filter(super::schema::scenes2d::dsl::id.eq(id)))
+				.set(super::schema::scenes2d::dsl::fogEnabled.eq(enabled))
+				.returning(Scene2D::as_returning())
+				.get_result(conn)
+				.optional();
+		})?
+	};
+	
+	return Ok(result);
+}
+
+pub async fn scene2dUpdateBackground(id: i32, backgroundId: i32) -> Result<Option<Scene2D>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => None,
+		Some(ref mut conn) => diesel::update(scenes2d.filter(super::schema::scenes2d::dsl::id.eq(id)))
+			.set(super::schema::scenes2d::dsl::backgroundId.eq(backgroundId))
+			.returning(Scene2D::as_returning())
+			.get_result(conn)
+			.optional()?
+	};
+	
+	return Ok(result);
+}
+
+/**
+Update the grid settings of `scene`.
+*/
+pub async fn scene2dUpdateGrid(scene: Scene2D) -> Result<Option<Scene2D>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => None,
+		Some(ref mut conn) => diesel::update(scenes2d.filter(super::schema::scenes2d::dsl::id.eq(scene.id)))
+			.set((
+				super::schema::scenes2d::dsl::gridCellSize.eq(scene.gridCellSize),
+				super::schema::scenes2d::dsl::gridDistance.eq(scene.gridDistance),
+				super::schema::scenes2d::dsl::gridOriginX.eq(scene.gridOriginX),
+				super::schema::scenes2d::dsl::gridOriginY.eq(scene.gridOriginY),
+				super::schema::scenes2d::dsl::gridType.eq(scene.gridType),
+				super::schema::scenes2d::dsl::gridUnit.eq(scene.gridUnit),
+			))
+			.returning(Scene2D::as_returning())
+			.get_result(conn)
+			.optional()?
+	};
+	
+	return Ok(result);
+}
+
+pub async fn tokenCreate(newToken: NewToken) -> Result<Option<Token>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => None,
+		Some(ref mut conn) => Some(diesel::insert_into(schema::tokens::table)
+			.values(newToken)
+			.returning(Token::as_returning())
+			.get_result(conn)?)
+	};
+	
+	return Ok(result);
+}
+
+/**
+Delete the token `id`, returning whether it existed.
+*/
+pub async fn tokenDelete(id: i32) -> Result<bool>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => 0,
+		Some(ref mut conn) => diesel::delete(tokens.filter(super::schema::tokens::dsl::id.eq(id)))
+			.execute(conn)?
+	};
+	
+	return Ok(result > 0);
+}
+
+/**
+Find the tokens on the scene `sceneId`, in the order they were created.
+*/
+pub async fn tokenFindByScene(sceneId: i32) -> Result<Vec<Token>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => vec![],
+		Some(ref mut conn) => tokens
+			.filter(super::schema::tokens::dsl::sceneId.eq(sceneId))
+			.order(super::schema::tokens::dsl::id.asc())
+			.load(conn)?
+	};
+	
+	return Ok(result);
+}
+
+pub async fn tokenGet(id: i32) -> Result<Option<Token>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => None,
+		Some(ref mut conn) => tokens
+			.find(id)
+			.first(conn)
+			.optional()?
+	};
+	
+	return Ok(result);
+}
+
+/**
+Move the centre of the token `id` to the pixel `(x, y)`.
+*/
+pub async fn tokenMove(id: i32, x: i64, y: i64) -> Result<Option<Token>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => None,
+		Some(ref mut conn) => diesel::update(tokens.filter(super::schema::tokens::dsl::id.eq(id)))
+			.set((
+				super::schema::tokens::dsl::x.eq(x),
+				super::schema::tokens::dsl::y.eq(y),
+			))
+			.returning(Token::as_returning())
+			.get_result(conn)
+			.optional()?
+	};
+	
+	return Ok(result);
+}
+
+pub async fn userCount() -> Result<i64>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => 0,
+		Some(ref mut conn) => users.count().get_result(conn)?
+	};
+	
+	return Ok(result);
+}
+
+pub async fn userCreate(newUser: NewUser) -> Result<Option<User>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let user: Option<User> = match db.connection
+	{
+		None => None,
+		Some(ref mut conn) => Some(diesel::insert_into(schema::users::table)
+			.values(newUser)
+			.returning(User::as_returning())
+			.get_result(conn)?)
+	};
+	
+	return Ok(user);
+}
+
+#[allow(dead_code)]
+pub async fn userDelete(user: User) -> Result<()>
+{
+	let mut db = getDatabase().lock().await;
+	
+	if let Some(ref mut conn) = db.connection
+	{
+		diesel::delete(users)
+			.filter(super::schema::users::dsl::id.eq(user.id))
+			.execute(conn)?;
+	}
+	
+	return Ok(());
+}
+
+pub async fn userFind(username: String) -> Result<Option<User>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => None,
+		Some(ref mut conn) => users
+			.filter(super::schema::users::dsl::name.eq(username))
+			.first(conn)
+			.optional()?
+	};
+	
+	return Ok(result);
+}
+
+pub async fn userGet(id: i32) -> Result<Option<User>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => None,
+		Some(ref mut conn) => users
+			.find(id)
+			.first(conn)
+			.optional()?
+	};
+	
+	return Ok(result);
+}
+
+#[allow(dead_code)]
+pub async fn userGetAll() -> Result<Vec<User>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => vec![],
+		Some(ref mut conn) => users.load(conn)?
+	};
+	
+	return Ok(result);
+}
+
+#[allow(dead_code)]
+pub async fn userUpdate(user: User) -> Result<Option<User>>
+{
+	let mut db = getDatabase().lock().await;
+	
+	let result = match db.connection
+	{
+		None => None,
+		Some(ref mut conn) => Some(diesel::update(users.filter(super::schema::users::dsl::id.eq(user.id)))
+			.set((
+				super::schema::users::dsl::name.eq(user.name),
+				super::schema::users::dsl::label.eq(user.label)
+			))
+			.returning(User::as_returning())
+			.get_result(conn)?)
+	};
+	
+	return Ok(result);
 }
 
-pub async fn messageFindByDateRange(start: NaiveDateTime, end: NaiveDateTime) -> Result<Vec<Message>>
+pub async fn userUpdatePassword(id: i32, passwordHash: String) -> Result<Option<User>>
 {
 	let mut db = getDatabase().lock().await;
 	
 	let result = match db.connection
 	{
-		None => vec![],
-		Some(ref mut conn) => messages
-			.filter(super::schema::messages::dsl::timestamp.ge(start))
-			.filter(super::schema::messages::dsl::timestamp.le(end))
-			.load(conn)?
+		None => None,
+		Some(ref mut conn) => Some(diesel::update(users.filter(super::schema::users::dsl::id.eq(id)))
+			.set(super::schema::users::dsl::passwordHash.eq(Some(passwordHash)))
+			.returning(User::as_returning())
+			.get_result(conn)?)
 	};
 	
 	return Ok(result);
 }
 
-#[allow(dead_code)]
-pub async fn messageFindByUser(userId: i32) -> Result<Vec<Message>>
+pub async fn userUpdateRole(id: i32, role: Role) -> Result<Option<User>>
 {
 	let mut db = getDatabase().lock().await;
 	
 	let result = match db.connection
 	{
-		None => vec![],
-		Some(ref mut conn) => messages
-			.filter(super::schema::messages::dsl::userId.eq(userId))
-			.load(conn)?
+		None => None,
+		Some(ref mut conn) => Some(diesel::update(users.filter(super::schema::users::dsl::id.eq(id)))
+			.set(super::schema::users::dsl::role.eq(i32::from(role)))
+			.returning(User::as_returning())
+			.get_result(conn)?)
 	};
 	
 	return Ok(result);
 }
 
-#[allow(dead_code)]
-pub async fn messageGetAll() -> Result<Vec<Message>>
+pub async fn wallCreate(newWall: NewWall) -> Result<Option<Wall>>
 {
 	let mut db = getDatabase().lock().await;
 	
 	let result = match db.connection
 	{
-		None => vec![],
-		Some(ref mut conn) => messages.load(conn)?
+		None => None,
+		Some(ref mut conn) => Some(diesel::insert_into(schema::walls::table)
+			.values(newWall)
+			.returning(Wall::as_returning())
+			.get_result(conn)?)
 	};
 	
 	return Ok(result);
 }
 
-pub async fn userCreate(newUser: NewUser) -> Result<Option<User>>
+/**
+Delete the wall `id`, returning whether it existed.
+*/
+pub async fn wallDelete(id: i32) -> Result<bool>
 {
 	let mut db = getDatabase().lock().await;
 	
-	let user: Option<User> = match db.connection
+	let result = match db.connection
 	{
-		None => None,
-		Some(ref mut conn) => Some(diesel::insert_into(schema::users::table)
-			.values(newUser)
-			.returning(User::as_returning())
-			.get_result(conn)?)
+		None => 0,
+		Some(ref mut conn) => diesel::delete(walls.filter(super::schema::walls::dsl::id.eq(id)))
+			.execute(conn)?
 	};
 	
-	return Ok(user);
+	return Ok(result > 0);
 }
 
-#[allow(dead_code)]
-pub async fn userDelete(user: User) -> Result<()>
+/**
+Find the walls of the scene `sceneId`, in the order they were created.
+*/
+pub async fn wallFindByScene(sceneId: i32) -> Result<Vec<Wall>>
 {
 	let mut db = getDatabase().lock().await;
 	
-	if let Some(ref mut conn) = db.connection
+	let result = match db.connection
 	{
-		diesel::delete(users)
-			.filter(super::schema::users::dsl::id.eq(user.id))
-			.execute(conn)?;
-	}
+		None => vec![],
+		Some(ref mut conn) => walls
+			.filter(super::schema::walls::dsl::sceneId.eq(sceneId))
+			.order(super::schema::walls::dsl::id.asc())
+			.load(conn)?
+	};
 	
-	return Ok(());
+	return Ok(result);
 }
 
-pub async fn userFind(username: String) -> Result<Option<User>>
+pub async fn wallGet(id: i32) -> Result<Option<Wall>>
 {
 	let mut db = getDatabase().lock().await;
 	
 	let result = match db.connection
 	{
 		None => None,
-		Some(ref mut conn) => users
-			.filter(super::schema::users::dsl::name.eq(username))
+		Some(ref mut conn) => walls
+			.find(id)
 			.first(conn)
 			.optional()?
 	};
@@ -118,55 +1039,99 @@ pub async fn userFind(username: String) -> Result<Option<User>>
 	return Ok(result);
 }
 
-#[allow(dead_code)]
-pub async fn userGet(id: i32) -> Result<Option<User>>
+/**
+Open, close or lock the door `id`.
+*/
+pub async fn wallUpdateDoor(id: i32, state: DoorState) -> Result<Option<Wall>>
 {
 	let mut db = getDatabase().lock().await;
 	
 	let result = match db.connection
 	{
 		None => None,
-		Some(ref mut conn) => users
-			.find(id)
-			.first(conn)
+		Some(ref mut conn) => diesel::update(walls.filter(super::schema::walls::dsl::id.eq(id)))
+			.set(super::schema::walls::dsl::doorState.eq(i32::from(state)))
+			.returning(Wall::as_returning())
+			.get_result(conn)
 			.optional()?
 	};
 	
 	return Ok(result);
 }
 
-#[allow(dead_code)]
-pub async fn userGetAll() -> Result<Vec<User>>
+fn insertChannelMembers(conn: &mut SqliteConnection, channelId: i32, memberIds: Vec<i32>) -> diesel::QueryResult<usize>
 {
-	let mut db = getDatabase().lock().await;
+	let members = memberIds.into_iter()
+		.map(|userId| NewChannelMember { channelId, userId })
+		.collect::<Vec<_>>();
 	
-	let result = match db.connection
+	return diesel::insert_or_ignore_into(schema::channelMembers::table)
+		.values(members)
+		.execute(conn);
+}
+
+/**
+Select the messages which may be seen within `scope`.
+
+Only messages in the main chat and channels of `scope` are visible. Whispers
+are only visible to their sender and recipient, and `Secret` messages to their
+sender and game masters.
+*/
+fn visibleMessages(scope: MessageScope) -> schema::messages::BoxedQuery<'static, Sqlite>
+{
+	let mut query = messages
+		.filter(super::schema::messages::dsl::campaignId.is(scope.campaignId))
+		.filter(super::schema::messages::dsl::recipientId.is_null()
+			.or(super::schema::messages::dsl::recipientId.is(scope.userId))
+			.or(super::schema::messages::dsl::userId.is(scope.userId)))
+		.into_boxed();
+	
+	query = match scope.mainChat
 	{
-		None => vec![],
-		Some(ref mut conn) => users.load(conn)?
+		true => query.filter(super::schema::messages::dsl::channelId.is_null()
+			.or(super::schema::messages::dsl::channelId.eq_any(scope.channels))),
+		false => query.filter(super::schema::messages::dsl::channelId.eq_any(scope.channels)),
 	};
 	
-	return Ok(result);
+	if !scope.gameMaster
+	{
+		query = query.filter(super::schema::messages::dsl::kind.ne(i32::from(MessageKind::Secret))
+			.or(super::schema::messages::dsl::userId.is(scope.userId)));
+	}
+	
+	return query;
 }
 
-#[allow(dead_code)]
-pub async fn userUpdate(user: User) -> Result<Option<User>>
+/**
+Convert search text into an FTS5 query matching messages containing every
+word.
+
+Each word is quoted so that FTS5 operators typed by users are searched for
+literally. A trailing `*` is kept to match words by prefix.
+*/
+fn searchTerms(text: &str) -> Option<String>
 {
-	let mut db = getDatabase().lock().await;
+	let terms = text.split_whitespace()
+		.filter_map(|word| {
+			let (word, prefix) = match word.strip_suffix('*')
+			{
+				Some(stem) => (stem, "*"),
+				None => (word, ""),
+			};
+			
+			match word.is_empty()
+			{
+				true => None,
+				false => Some(format!("\"{}\"{}", word.replace('"', "\"\""), prefix)),
+			}
+		})
+		.collect::<Vec<_>>();
 	
-	let result = match db.connection
+	return match terms.is_empty()
 	{
-		None => None,
-		Some(ref mut conn) => Some(diesel::update(users.filter(super::schema::users::dsl::id.eq(user.id)))
-			.set((
-				super::schema::users::dsl::name.eq(user.name),
-				super::schema::users::dsl::label.eq(user.label)
-			))
-			.returning(User::as_returning())
-			.get_result(conn)?)
+		true => None,
+		false => Some(terms.join(" ")),
 	};
-	
-	return Ok(result);
 }
 
 #[cfg(test)]
@@ -174,15 +1139,53 @@ mod tests
 {
 	use super::*;
 	use crate::data::dao;
+	use crate::data::{resetTestDatabase, ChannelVisibility, MainChannel};
+	
+	/**
+	A message from `userId` to the lobby's main chat.
+	*/
+	fn newMessage(text: &str, userId: i32) -> NewMessage
+	{
+		return NewMessage { text: text.into(), userId: Some(userId), ..Default::default() };
+	}
+	
+	async fn createMessage(newMessage: NewMessage) -> Message
+	{
+		return dao::messageCreate(newMessage).await
+			.expect("Error creating test message")
+			.expect("No database connection");
+	}
+	
+	/**
+	The text of the messages visible within `scope`, oldest first.
+	*/
+	async fn visibleTexts(scope: MessageScope) -> Vec<String>
+	{
+		return dao::messageFindPage(scope, None, 100).await
+			.expect("Error finding messages")
+			.into_iter()
+			.map(|m| m.text)
+			.collect();
+	}
+	
+	/**
+	The text of the messages visible within `scope` which contain `terms`, best
+	match first.
+	*/
+	async fn searchTexts(scope: MessageScope, terms: &str) -> Vec<String>
+	{
+		let search = MessageSearch { limit: 100, terms: terms.into(), ..Default::default() };
+		return dao::messageSearch(scope, search).await
+			.expect("Error searching messages")
+			.into_iter()
+			.map(|hit| hit.message.text)
+			.collect();
+	}
 	
 	#[tokio::test]
 	async fn createGetUpdateUser()
 	{
-		{
-			let mut db = getDatabase().lock().await;
-			db.dropAll().expect("Failed to drop all tables");
-			db.initialize().expect("Failed to initialize database");
-		}
+		let _database = resetTestDatabase().await;
 		
 		let username = "myusername".to_string();
 		
@@ -192,7 +1195,6 @@ mod tests
 			..Default::default()
 		};
 		
-		println!("new user: {:?}", newUser);
 		let mut user = dao::userCreate(newUser).await.expect("Error creating test user");
 		assert!(user.is_some());
 		
@@ -225,6 +1227,16 @@ mod tests
 		assert!(found.is_some());
 		let foundUser = found.unwrap();
 		assert_eq!(foundUser.name, username);
+		assert!(foundUser.passwordHash.is_none());
+		
+		let withPassword = dao::userUpdatePassword(foundUser.id, "hash".into()).await.expect("Error updating user password");
+		assert!(withPassword.is_some_and(|u| u.passwordHash == Some("hash".into()) && u.name == username));
+		
+		assert_eq!(foundUser.role(), Role::Player);
+		let withRole = dao::userUpdateRole(foundUser.id, Role::GameMaster).await.expect("Error updating user role");
+		assert!(withRole.is_some_and(|u| u.role() == Role::GameMaster));
+		
+		assert_eq!(dao::userCount().await.expect("Error counting users"), 1);
 		
 		let deleted = dao::userDelete(user.unwrap()).await;
 		assert!(deleted.is_ok());
@@ -232,4 +1244,415 @@ mod tests
 		let usersAgain = dao::userGetAll().await.expect("Error getting all users again");
 		assert!(usersAgain.is_empty());
 	}
+	
+	#[tokio::test]
+	async fn whispersAndSecretsAreHidden()
+	{
+		let _database = resetTestDatabase().await;
+		
+		let gameMaster = 4;
+		createMessage(newMessage("public", 1)).await;
+		createMessage(NewMessage { recipientId: Some(2), ..newMessage("to 2", 1) }).await;
+		createMessage(NewMessage { recipientId: Some(3), ..newMessage("to 3", 1) }).await;
+		createMessage(NewMessage { kind: MessageKind::Secret.into(), ..newMessage("secret 2", 2) }).await;
+		createMessage(NewMessage { kind: MessageKind::Secret.into(), ..newMessage("secret 3", 3) }).await;
+		createMessage(NewMessage { campaignId: Some(1), ..newMessage("campaign", 1) }).await;
+		
+		let scope = |userId: Option<i32>, gameMaster: bool| MessageScope
+		{
+			gameMaster,
+			mainChat: true,
+			userId,
+			..Default::default()
+		};
+		
+		//Whispers are seen by their sender and recipient
+		assert_eq!(visibleTexts(scope(Some(1), false)).await, vec!["public", "to 2", "to 3"]);
+		assert_eq!(visibleTexts(scope(Some(2), false)).await, vec!["public", "to 2", "secret 2"]);
+		assert_eq!(visibleTexts(scope(Some(3), false)).await, vec!["public", "to 3", "secret 3"]);
+		
+		//Game masters see every secret message, but not other users' whispers
+		assert_eq!(visibleTexts(scope(Some(gameMaster), true)).await, vec!["public", "secret 2", "secret 3"]);
+		
+		//Clients which have not authenticated see only public messages
+		assert_eq!(visibleTexts(scope(None, false)).await, vec!["public"]);
+		
+		let campaign = MessageScope { campaignId: Some(1), ..scope(Some(2), false) };
+		assert_eq!(visibleTexts(campaign).await, vec!["campaign"]);
+	}
+	
+	#[tokio::test]
+	async fn hiddenMessagesCannotBeFound()
+	{
+		let _database = resetTestDatabase().await;
+		
+		let whisper = createMessage(NewMessage { recipientId: Some(2), ..newMessage("to 2", 1) }).await;
+		let secret = createMessage(NewMessage { kind: MessageKind::Secret.into(), ..newMessage("secret", 1) }).await;
+		
+		let recipient = MessageScope { mainChat: true, userId: Some(2), ..Default::default() };
+		let other = MessageScope { mainChat: true, userId: Some(3), ..Default::default() };
+		let gameMaster = MessageScope { gameMaster: true, mainChat: true, userId: Some(4), ..Default::default() };
+		
+		let find = |scope: MessageScope, id: i32| async move {
+			return dao::messageFind(scope, id).await.expect("Error finding message").is_some();
+		};
+		
+		assert!(find(recipient.clone(), whisper.id).await);
+		assert!(!find(other.clone(), whisper.id).await);
+		assert!(!find(gameMaster.clone(), whisper.id).await);
+		
+		assert!(!find(recipient, secret.id).await);
+		assert!(!find(other, secret.id).await);
+		assert!(find(gameMaster, secret.id).await);
+	}
+	
+	#[tokio::test]
+	async fn channelMessagesAreFiltered()
+	{
+		let _database = resetTestDatabase().await;
+		
+		let mut ids = vec![];
+		for (name, visibility) in [("public", ChannelVisibility::Public), ("gm", ChannelVisibility::GameMasters)]
+		{
+			let newChannel = NewChannel { name: name.into(), visibility: visibility.into(), ..Default::default() };
+			let channel = dao::channelCreate(newChannel, vec![]).await
+				.expect("Error creating channel")
+				.expect("No database connection");
+			
+			createMessage(NewMessage { channelId: Some(channel.id), ..newMessage(name, 1) }).await;
+			ids.push(channel.id);
+		}
+		
+		createMessage(newMessage("main", 1)).await;
+		let (public, gm) = (ids[0], ids[1]);
+		
+		let scope = |ids: Vec<i32>| MessageScope
+		{
+			channels: ids,
+			mainChat: true,
+			userId: Some(2),
+			..Default::default()
+		};
+		
+		assert_eq!(visibleTexts(scope(vec![public])).await, vec!["public", "main"]);
+		assert_eq!(visibleTexts(scope(vec![public, gm])).await, vec!["public", "gm", "main"]);
+		
+		assert_eq!(visibleTexts(scope(vec![public, gm]).onlyChannel(gm)).await, vec!["gm"]);
+		assert_eq!(visibleTexts(scope(vec![public]).onlyChannel(MainChannel)).await, vec!["main"]);
+		
+		//Narrowing to a channel outside the scope finds nothing
+		assert!(visibleTexts(scope(vec![public]).onlyChannel(gm)).await.is_empty());
+	}
+	
+	#[tokio::test]
+	async fn pagesEndBeforeTheCursor()
+	{
+		let _database = resetTestDatabase().await;
+		
+		let mut ids = vec![];
+		for text in ["one", "two", "three", "four", "five"]
+		{
+			ids.push(createMessage(newMessage(text, 1)).await.id);
+		}
+		
+		let scope = MessageScope { mainChat: true, userId: Some(1), ..Default::default() };
+		let page = |before: Option<i32>, limit: i64| {
+			let scope = scope.clone();
+			async move {
+				return dao::messageFindPage(scope, before, limit).await
+					.expect("Error finding messages")
+					.into_iter()
+					.map(|m| m.text)
+					.collect::<Vec<_>>();
+			}
+		};
+		
+		//The latest messages, oldest first
+		assert_eq!(page(None, 2).await, vec!["four", "five"]);
+		
+		//The cursor itself is not repeated
+		assert_eq!(page(Some(ids[3]), 2).await, vec!["two", "three"]);
+		assert_eq!(page(Some(ids[1]), 2).await, vec!["one"]);
+		assert!(page(Some(ids[0]), 2).await.is_empty());
+		
+		assert_eq!(page(None, 10).await.len(), 5);
+	}
+	
+	#[tokio::test]
+	async fn searchIndexFollowsMessages()
+	{
+		let _database = resetTestDatabase().await;
+		
+		let scope = MessageScope { mainChat: true, userId: Some(1), ..Default::default() };
+		let message = createMessage(newMessage("the innkeeper", 1)).await;
+		assert_eq!(searchTexts(scope.clone(), "innkeeper").await, vec!["the innkeeper"]);
+		
+		let revision = NewMessageRevision
+		{
+			messageId: message.id,
+			text: message.text.to_owned(),
+			timestamp: message.timestamp,
+			userId: Some(1),
+		};
+		
+		dao::messageRevise("the blacksmith".into(), false, revision.clone()).await.expect("Error editing message");
+		assert!(searchTexts(scope.clone(), "innkeeper").await.is_empty());
+		assert_eq!(searchTexts(scope.clone(), "blacksmith").await, vec!["the blacksmith"]);
+		
+		dao::messageRevise(String::new(), true, revision).await.expect("Error deleting message");
+		assert!(searchTexts(scope.clone(), "blacksmith").await.is_empty());
+		
+		let removed = createMessage(newMessage("the bard", 1)).await;
+		{
+			let mut db = getDatabase().lock().await;
+			let conn = db.connection.as_mut().expect("No database connection");
+			diesel::delete(messages.filter(super::schema::messages::dsl::id.eq(removed.id)))
+				.execute(conn)
+				.expect("Error removing message");
+		}
+		
+		assert!(searchTexts(scope, "bard").await.is_empty());
+	}
+	
+	#[tokio::test]
+	async fn searchHidesWhatHistoryHides()
+	{
+		let _database = resetTestDatabase().await;
+		
+		let visibility = ChannelVisibility::GameMasters.into();
+		let newChannel = NewChannel { name: "gm".into(), visibility, ..Default::default() };
+		let channel = dao::channelCreate(newChannel, vec![]).await
+			.expect("Error creating channel")
+			.expect("No database connection");
+		
+		createMessage(newMessage("amulet public", 1)).await;
+		createMessage(NewMessage { recipientId: Some(2), ..newMessage("amulet to 2", 1) }).await;
+		createMessage(NewMessage { kind: MessageKind::Secret.into(), ..newMessage("amulet secret", 2) }).await;
+		createMessage(NewMessage { channelId: Some(channel.id), ..newMessage("amulet channel", 1) }).await;
+		createMessage(NewMessage { campaignId: Some(1), ..newMessage("amulet campaign", 1) }).await;
+		
+		let player = MessageScope { mainChat: true, userId: Some(3), ..Default::default() };
+		let gameMaster = MessageScope
+		{
+			channels: vec![channel.id],
+			gameMaster: true,
+			mainChat: true,
+			userId: Some(4),
+			..Default::default()
+		};
+		
+		assert_eq!(searchTexts(player.clone(), "amulet").await, vec!["amulet public"]);
+		
+		let scopes = [
+			MessageScope { userId: Some(2), ..player.clone() },
+			MessageScope { campaignId: Some(1), ..player.clone() },
+			MessageScope { userId: None, ..player.clone() },
+			player,
+			MessageScope { mainChat: false, ..gameMaster.clone() },
+			gameMaster,
+		];
+		
+		//Search must find exactly the messages the same reader sees in the history
+		for scope in scopes
+		{
+			let mut found = searchTexts(scope.clone(), "amulet").await;
+			found.sort();
+			
+			let mut visible = visibleTexts(scope.clone()).await;
+			visible.sort();
+			
+			assert!(!visible.is_empty());
+			assert_eq!(found, visible, "{:?}", scope);
+		}
+	}
+	
+	#[tokio::test]
+	async fn revisionsKeepEarlierText()
+	{
+		let _database = resetTestDatabase().await;
+		
+		let message = createMessage(newMessage("first", 1)).await;
+		let revision = |text: &str| NewMessageRevision
+		{
+			messageId: message.id,
+			text: text.into(),
+			timestamp: message.timestamp,
+			userId: Some(1),
+		};
+		
+		let edited = dao::messageRevise("second".into(), false, revision("first")).await
+			.expect("Error editing message")
+			.expect("No database connection");
+		assert_eq!(edited.text, "second");
+		assert!(edited.edited.is_some() && !edited.deleted);
+		
+		let deleted = dao::messageRevise(String::new(), true, revision("second")).await
+			.expect("Error deleting message")
+			.expect("No database connection");
+		assert!(deleted.text.is_empty() && deleted.deleted);
+		
+		let history = dao::messageRevisionFindByMessage(message.id).await
+			.expect("Error finding revisions")
+			.into_iter()
+			.map(|r| r.text)
+			.collect::<Vec<_>>();
+		assert_eq!(history, vec!["first", "second"]);
+		
+		assert!(dao::messageRevisionFindByMessage(message.id + 1).await.expect("Error finding revisions").is_empty());
+	}
+	
+	async fn createScene(name: &str, campaignId: Option<i32>) -> Scene2D
+	{
+		let newScene = NewScene2D { name: name.into(), campaignId, ..Default::default() };
+		return dao::scene2dCreate(newScene).await
+			.expect("Error creating scene")
+			.expect("No database connection");
+	}
+	
+	/**
+	Add a token, a wall and a fog region to the scene `sceneId`.
+	*/
+	async fn furnishScene(sceneId: i32)
+	{
+		dao::tokenCreate(NewToken { name: "token".into(), sceneId, ..Default::default() }).await
+			.expect("Error creating token");
+		dao::wallCreate(NewWall { endX: 10, sceneId, ..Default::default() }).await
+			.expect("Error creating wall");
+		dao::fogRegionCreate(vec![NewFogRegion { points: "[]".into(), sceneId, ..Default::default() }]).await
+			.expect("Error creating fog region");
+	}
+	
+	/**
+	The number of tokens, walls and fog regions on the scene `sceneId`.
+	*/
+	async fn sceneContents(sceneId: i32) -> (usize, usize, usize)
+	{
+		return (
+			dao::tokenFindByScene(sceneId).await.expect("Error finding tokens").len(),
+			dao::wallFindByScene(sceneId).await.expect("Error finding walls").len(),
+			dao::fogRegionFindByScene(sceneId).await.expect("Error finding fog").len(),
+		);
+	}
+	
+	#[tokio::test]
+	async fn scenesAreListedByCampaign()
+	{
+		let _database = resetTestDatabase().await;
+		
+		let first = createScene("first", Some(1)).await;
+		let lobby = createScene("lobby", None).await;
+		let second = createScene("second", Some(1)).await;
+		
+		let names = |campaignId: Option<i32>| async move {
+			return dao::scene2dFindByCampaign(campaignId).await
+				.expect("Error finding scenes")
+				.into_iter()
+				.map(|s| s.name)
+				.collect::<Vec<_>>();
+		};
+		
+		assert_eq!(names(Some(1)).await, vec!["first", "second"]);
+		assert_eq!(names(None).await, vec!["lobby"]);
+		assert!(names(Some(2)).await.is_empty());
+		
+		let got = dao::scene2dGet(lobby.id).await.expect("Error getting scene");
+		assert_eq!(got, Some(lobby));
+		
+		let renamed = dao::scene2dRename(first.id, "renamed".into()).await.expect("Error renaming scene");
+		assert!(renamed.is_some_and(|s| s.name == "renamed" && s.campaignId == Some(1)));
+		
+		let background = dao::scene2dUpdateBackground(second.id, 7).await.expect("Error updating background");
+		assert!(background.is_some_and(|s| s.backgroundId == 7 && s.name == "second"));
+		
+		let grid = Scene2D { gridCellSize: 50, gridUnit: "m".into(), ..second.clone() };
+		let updated = dao::scene2dUpdateGrid(grid).await.expect("Error updating grid");
+		assert!(updated.is_some_and(|s| s.gridCellSize == 50 && s.gridUnit == "m" && s.backgroundId == 7));
+		
+		//Changing a scene which does not exist changes nothing
+		let missing = second.id + 100;
+		assert!(dao::scene2dRename(missing, "missing".into()).await.expect("Error renaming scene").is_none());
+		assert!(dao::scene2dUpdateBackground(missing, 7).await.expect("Error updating background").is_none());
+	}
+	
+	#[tokio::test]
+	async fn deletingSceneRemovesItsContents()
+	{
+		let _database = resetTestDatabase().await;
+		
+		let deleted = createScene("deleted", Some(1)).await;
+		let kept = createScene("kept", Some(1)).await;
+		furnishScene(deleted.id).await;
+		furnishScene(kept.id).await;
+		assert_eq!(sceneContents(deleted.id).await, (1, 1, 1));
+		
+		assert!(dao::scene2dDelete(deleted.id).await.expect("Error deleting scene"));
+		assert!(dao::scene2dGet(deleted.id).await.expect("Error getting scene").is_none());
+		assert_eq!(sceneContents(deleted.id).await, (0, 0, 0));
+		
+		//Other scenes keep their contents
+		assert_eq!(sceneContents(kept.id).await, (1, 1, 1));
+		
+		assert!(!dao::scene2dDelete(deleted.id).await.expect("Error deleting scene"));
+	}
+	
+	#[tokio::test]
+	async fn resettingFogRemovesRegions()
+	{
+		let _database = resetTestDatabase().await;
+		
+		let scene = createScene("fog", None).await;
+		furnishScene(scene.id).await;
+		
+		let reset = dao::scene2dResetFog(scene.id, true).await.expect("Error resetting fog");
+		assert!(reset.is_some_and(|s| s.fogEnabled));
+		assert_eq!(sceneContents(scene.id).await, (1, 1, 0));
+		
+		let lifted = dao::scene2dResetFog(scene.id, false).await.expect("Error resetting fog");
+		assert!(lifted.is_some_and(|s| !s.fogEnabled));
+	}
+	
+	#[tokio::test]
+	async fn tokensMoveAndDelete()
+	{
+		let _database = resetTestDatabase().await;
+		
+		let scene = createScene("tokens", None).await;
+		let other = createScene("other", None).await;
+		
+		let mut created = vec![];
+		for (name, sceneId) in [("first", scene.id), ("elsewhere", other.id), ("second", scene.id)]
+		{
+			created.push(dao::tokenCreate(NewToken { name: name.into(), sceneId, ..Default::default() }).await
+				.expect("Error creating token")
+				.expect("No database connection"));
+		}
+		
+		let names = |sceneId: i32| async move {
+			return dao::tokenFindByScene(sceneId).await
+				.expect("Error finding tokens")
+				.into_iter()
+				.map(|t| t.name)
+				.collect::<Vec<_>>();
+		};
+		
+		assert_eq!(names(scene.id).await, vec!["first", "second"]);
+		assert_eq!(dao::tokenGet(created[1].id).await.expect("Error getting token"), Some(created[1].clone()));
+		
+		let moved = dao::tokenMove(created[0].id, 150, 250).await.expect("Error moving token");
+		assert!(moved.is_some_and(|t| (t.x, t.y) == (150, 250) && t.name == "first"));
+		assert!(dao::tokenMove(created[2].id + 100, 0, 0).await.expect("Error moving token").is_none());
+		
+		assert!(dao::tokenDelete(created[0].id).await.expect("Error deleting token"));
+		assert!(!dao::tokenDelete(created[0].id).await.expect("Error deleting token"));
+		assert_eq!(names(scene.id).await, vec!["second"]);
+		assert_eq!(names(other.id).await, vec!["elsewhere"]);
+	}
+	
+	#[test]
+	fn searchTermsAreQuoted()
+	{
+		assert_eq!(searchTerms("innkeeper amulet"), Some(r#""innkeeper" "amulet""#.into()));
+		assert_eq!(searchTerms(r#"amu* "NOT" *"#), Some(r#""amu"* """NOT""""#.into()));
+		assert_eq!(searchTerms("  "), None);
+	}
 }
@@ -1,15 +1,26 @@
+use std::fs::create_dir_all;
 use std::path::PathBuf;
 use std::sync::OnceLock;
 use ::anyhow::Result;
 use diesel::RunQueryDsl;
-use ::diesel::{Connection, SqliteConnection};
+use ::diesel::{Connection, QueryableByName, SqliteConnection};
+use ::diesel::sql_types::{BigInt, Text};
 use ::tokio::sync::Mutex;
+#[cfg(test)]
+use ::tokio::sync::MutexGuard;
 use crate::config::localDataPath;
-use crate::data::model::{CreateTable_ImageAssets, CreateTable_Messages,
-	CreateTable_Scenes2D, CreateTable_Users, DropTable_ImageAssets,
-	DropTable_Messages, DropTable_Scenes2D, DropTable_Users};
+use crate::data::model::{AddedColumns_Messages, AddedColumns_Scenes2D, AddedColumns_Users, CreateTable_CampaignRoles,
+	CreateTable_ChannelMembers, CreateTable_Channels, CreateTable_FogRegions, CreateTable_ImageAssets,
+	CreateTable_MessageRevisions, CreateTable_Messages, CreateTable_MessagesSearch, CreateTable_Rolls,
+	CreateTable_Scenes2D, CreateTable_Tokens, CreateTable_Users, CreateTable_Walls, CreateTriggers_MessagesSearch,
+	DropTable_CampaignRoles, DropTable_ChannelMembers, DropTable_Channels, DropTable_FogRegions,
+	DropTable_ImageAssets, DropTable_MessageRevisions, DropTable_Messages, DropTable_MessagesSearch, DropTable_Rolls,
+	DropTable_Scenes2D, DropTable_Tokens, DropTable_Users, DropTable_Walls, Rebuild_MessagesSearch};
 use crate::getConfig;
 
+/// Database path which opens a private database in memory instead of a file.
+pub const InMemoryDatabase: &'static str = ":memory:";
+
 pub fn getDatabase() -> &'static Mutex<Database>
 {
 	static DbLock: OnceLock<Mutex<Database>> = OnceLock::new();
@@ -39,10 +50,19 @@ impl Database
 	{
 		if let Some(ref mut conn) = self.connection
 		{
+			diesel::sql_query(DropTable_CampaignRoles).execute(conn)?;
+			diesel::sql_query(DropTable_ChannelMembers).execute(conn)?;
+			diesel::sql_query(DropTable_Channels).execute(conn)?;
+			diesel::sql_query(DropTable_FogRegions).execute(conn)?;
 			diesel::sql_query(DropTable_ImageAssets).execute(conn)?;
+			diesel::sql_query(DropTable_MessageRevisions).execute(conn)?;
 			diesel::sql_query(DropTable_Messages).execute(conn)?;
+			diesel::sql_query(DropTable_MessagesSearch).execute(conn)?;
+			diesel::sql_query(DropTable_Rolls).execute(conn)?;
 			diesel::sql_query(DropTable_Scenes2D).execute(conn)?;
+			diesel::sql_query(DropTable_Tokens).execute(conn)?;
 			diesel::sql_query(DropTable_Users).execute(conn)?;
+			diesel::sql_query(DropTable_Walls).execute(conn)?;
 		}
 		
 		return Ok(());
@@ -55,8 +75,10 @@ impl Database
 			let config = getConfig();
 			
 			let mut filePath = config.database.path.clone();
-			if let Some(dir) = localDataPath()
+			if let Some(dir) = localDataPath().filter(|_| filePath != InMemoryDatabase)
 			{
+				create_dir_all(&dir)?;
+				
 				let buf = PathBuf::from(dir)
 					.join(filePath.clone());
 				
@@ -71,12 +93,99 @@ impl Database
 		
 		if let Some(ref mut conn) = self.connection
 		{
+			diesel::sql_query(CreateTable_CampaignRoles).execute(conn)?;
+			diesel::sql_query(CreateTable_ChannelMembers).execute(conn)?;
+			diesel::sql_query(CreateTable_Channels).execute(conn)?;
+			diesel::sql_query(CreateTable_FogRegions).execute(conn)?;
 			diesel::sql_query(CreateTable_ImageAssets).execute(conn)?;
+			diesel::sql_query(CreateTable_MessageRevisions).execute(conn)?;
 			diesel::sql_query(CreateTable_Messages).execute(conn)?;
+			diesel::sql_query(CreateTable_Rolls).execute(conn)?;
 			diesel::sql_query(CreateTable_Scenes2D).execute(conn)?;
+			diesel::sql_query(CreateTable_Tokens).execute(conn)?;
 			diesel::sql_query(CreateTable_Users).execute(conn)?;
+			diesel::sql_query(CreateTable_Walls).execute(conn)?;
+			
+			addMissingColumns(conn, "messages", AddedColumns_Messages)?;
+			addMissingColumns(conn, "scenes2d", AddedColumns_Scenes2D)?;
+			addMissingColumns(conn, "users", AddedColumns_Users)?;
+			
+			let indexed = tableExists(conn, "messagesSearch")?;
+			diesel::sql_query(CreateTable_MessagesSearch).execute(conn)?;
+			for trigger in CreateTriggers_MessagesSearch
+			{
+				diesel::sql_query(*trigger).execute(conn)?;
+			}
+			
+			if !indexed
+			{
+				diesel::sql_query(Rebuild_MessagesSearch).execute(conn)?;
+			}
 		}
 		
 		return Ok(());
 	}
 }
+
+/**
+Empty the database for a test, returning a guard which keeps other tests from
+using the database until it is dropped.
+*/
+#[cfg(test)]
+pub async fn resetTestDatabase() -> MutexGuard<'static, ()>
+{
+	static TestLock: Mutex<()> = Mutex::const_new(());
+	let guard = TestLock.lock().await;
+	
+	let mut db = getDatabase().lock().await;
+	db.initialize().expect("Failed to initialize database");
+	db.dropAll().expect("Failed to drop all tables");
+	db.initialize().expect("Failed to initialize database");
+	
+	return guard;
+}
+
+#[derive(QueryableByName)]
+struct ColumnCount
+{
+	#[diesel(sql_type = BigInt)]
+	count: i64,
+}
+
+/**
+Whether `table` exists in the database.
+*/
+fn tableExists(conn: &mut SqliteConnection, table: &str) -> Result<bool>
+{
+	let existing = diesel::sql_query("SELECT COUNT(*) AS count FROM sqlite_master WHERE type = 'table' AND name = ?")
+		.bind::<Text, _>(table)
+		.get_result::<ColumnCount>(conn)?;
+	
+	return Ok(existing.count > 0);
+}
+
+/**
+Add any of the given `(name, definition)` columns which do not yet exist in
+`table`.
+
+`CREATE TABLE IF NOT EXISTS` leaves tables from older databases untouched, so
+columns added to a table after its initial release must be applied here.
+*/
+fn addMissingColumns(conn: &mut SqliteConnection, table: &str, columns: &[(&str, &str)]) -> Result<()>
+{
+	for (name, definition) in columns
+	{
+		let existing = diesel::sql_query("SELECT COUNT(*) AS count FROM pragma_table_info(?) WHERE name = ?")
+			.bind::<Text, _>(table)
+			.bind::<Text, _>(name)
+			.get_result::<ColumnCount>(conn)?;
+		
+		if existing.count == 0
+		{
+			diesel::sql_query(format!("ALTER TABLE {} ADD COLUMN {} {}", table, name, definition))
+				.execute(conn)?;
+		}
+	}
+	
+	return Ok(());
+}
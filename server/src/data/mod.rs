@@ -5,4 +5,9 @@ mod model;
 mod schema;
 
 pub use db::getDatabase;
-pub use model::{NewImageAsset, NewMessage, NewScene2D, NewUser, User};
+#[cfg(test)]
+pub use db::{resetTestDatabase, InMemoryDatabase};
+pub use model::{Channel, ChannelVisibility, DoorState, FogRegion, GridType, ImageAsset, MainChannel, Message,
+	MessageKind, MessageScope, MessageSearch, MessageSearchHit, NewCampaignRole, NewChannel, NewFogRegion,
+	NewImageAsset, NewMessage, NewMessageRevision, NewRoll, NewScene2D, NewToken, NewUser, NewWall, Role, Scene2D,
+	Token, User, Wall, WallKind};
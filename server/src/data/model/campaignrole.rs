@@ -0,0 +1,46 @@
+use ::diesel::{Insertable, Selectable, Queryable};
+use super::super::schema;
+use super::Role;
+
+pub const CreateTable_CampaignRoles: &'static str = r#"CREATE TABLE IF NOT EXISTS campaignRoles
+(
+	id INTEGER PRIMARY KEY,
+	campaignId INTEGER NOT NULL,
+	role INTEGER NOT NULL,
+	userId INTEGER NOT NULL,
+	UNIQUE(campaignId, userId)
+)"#;
+
+pub const DropTable_CampaignRoles: &'static str = "DROP TABLE campaignRoles";
+
+/**
+A user's role within a single campaign, overriding `users.role` while the
+user is participating in that campaign.
+*/
+#[derive(Clone, Debug, Default, PartialEq, Selectable, Queryable)]
+#[diesel(table_name = schema::campaignRoles)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct CampaignRole
+{
+	pub id: i32,
+	pub campaignId: i32,
+	pub role: i32,
+	pub userId: i32,
+}
+
+impl CampaignRole
+{
+	pub fn role(&self) -> Role
+	{
+		return Role::try_from(self.role).unwrap_or(Role::Spectator);
+	}
+}
+
+#[derive(Clone, Debug, Default, Insertable)]
+#[diesel(table_name = schema::campaignRoles)]
+pub struct NewCampaignRole
+{
+	pub campaignId: i32,
+	pub role: i32,
+	pub userId: i32,
+}
@@ -0,0 +1,158 @@
+use ::anyhow::{Error, Result};
+use ::diesel::{Insertable, Selectable, Queryable};
+use ::serde::{Deserialize, Serialize};
+use super::super::schema;
+
+/// The channel id which stands for a chat room's main chat in requests.
+pub const MainChannel: i32 = 0;
+
+pub const CreateTable_Channels: &'static str = r#"CREATE TABLE IF NOT EXISTS channels
+(
+	id INTEGER PRIMARY KEY,
+	campaignId INTEGER DEFAULT NULL,
+	name TEXT NOT NULL,
+	visibility INTEGER NOT NULL DEFAULT 0,
+	UNIQUE(campaignId, name)
+)"#;
+
+pub const DropTable_Channels: &'static str = "DROP TABLE channels";
+
+pub const CreateTable_ChannelMembers: &'static str = r#"CREATE TABLE IF NOT EXISTS channelMembers
+(
+	id INTEGER PRIMARY KEY,
+	channelId INTEGER NOT NULL,
+	userId INTEGER NOT NULL,
+	UNIQUE(channelId, userId)
+)"#;
+
+pub const DropTable_ChannelMembers: &'static str = "DROP TABLE channelMembers";
+
+/**
+A separate stream of chat within a campaign or the lobby, alongside its main
+chat.
+
+Messages sent to the main chat have no `messages.channelId`.
+*/
+#[derive(Clone, Debug, Default, PartialEq, Selectable, Queryable)]
+#[diesel(table_name = schema::channels)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Channel
+{
+	pub id: i32,
+	/// The campaign the channel belongs to, or `None` for the lobby.
+	pub campaignId: Option<i32>,
+	pub name: String,
+	pub visibility: i32,
+}
+
+impl Channel
+{
+	pub fn visibility(&self) -> ChannelVisibility
+	{
+		return ChannelVisibility::try_from(self.visibility).unwrap_or_default();
+	}
+	
+	/**
+	Whether the channel may be seen by a user who is a game master if
+	`gameMaster`, and one of the channel's listed members if `member`.
+	*/
+	pub fn isVisible(&self, gameMaster: bool, member: bool) -> bool
+	{
+		return match self.visibility()
+		{
+			ChannelVisibility::Public => true,
+			ChannelVisibility::GameMasters => gameMaster,
+			ChannelVisibility::Members => gameMaster || member,
+		};
+	}
+}
+
+#[derive(Clone, Debug, Default, Insertable)]
+#[diesel(table_name = schema::channels)]
+pub struct NewChannel
+{
+	pub campaignId: Option<i32>,
+	pub name: String,
+	pub visibility: i32,
+}
+
+/**
+A user listed as a member of a channel with `ChannelVisibility::Members`.
+*/
+#[derive(Clone, Debug, Default, PartialEq, Selectable, Queryable)]
+#[diesel(table_name = schema::channelMembers)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ChannelMember
+{
+	pub id: i32,
+	pub channelId: i32,
+	pub userId: i32,
+}
+
+#[derive(Clone, Debug, Default, Insertable)]
+#[diesel(table_name = schema::channelMembers)]
+pub struct NewChannelMember
+{
+	pub channelId: i32,
+	pub userId: i32,
+}
+
+/**
+Who may see a channel, stored as an integer in `channels.visibility`.
+*/
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[repr(i32)]
+#[serde(rename_all = "camelCase")]
+pub enum ChannelVisibility
+{
+	/// Everyone in the channel's chat room.
+	#[default]
+	Public = 0,
+	/// Only game masters.
+	GameMasters = 1,
+	/// Game masters and the channel's members.
+	Members = 2,
+}
+
+impl TryFrom<i32> for ChannelVisibility
+{
+	type Error = Error;
+	
+	fn try_from(value: i32) -> Result<Self>
+	{
+		return match value
+		{
+			0 => Ok(Self::Public),
+			1 => Ok(Self::GameMasters),
+			2 => Ok(Self::Members),
+			_ => Err(Error::msg(format!("Invalid channel visibility: {}", value))),
+		};
+	}
+}
+
+impl From<ChannelVisibility> for i32
+{
+	fn from(value: ChannelVisibility) -> Self
+	{
+		return value as i32;
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	
+	#[test]
+	fn visibility()
+	{
+		let channel = |visibility: ChannelVisibility| Channel { visibility: visibility.into(), ..Default::default() };
+		
+		assert!(channel(ChannelVisibility::Public).isVisible(false, false));
+		assert!(channel(ChannelVisibility::GameMasters).isVisible(true, false));
+		assert!(!channel(ChannelVisibility::GameMasters).isVisible(false, true));
+		assert!(channel(ChannelVisibility::Members).isVisible(false, true));
+		assert!(channel(ChannelVisibility::Members).isVisible(true, false));
+		assert!(!channel(ChannelVisibility::Members).isVisible(false, false));
+	}
+}
@@ -0,0 +1,63 @@
+use ::diesel::{Insertable, Selectable, Queryable};
+use super::super::schema;
+
+pub const CreateTable_FogRegions: &'static str = r#"CREATE TABLE IF NOT EXISTS fogRegions
+(
+	id INTEGER PRIMARY KEY,
+	points TEXT NOT NULL,
+	revealed BOOLEAN NOT NULL,
+	sceneId INTEGER NOT NULL,
+	userId INTEGER DEFAULT NULL
+)"#;
+
+pub const DropTable_FogRegions: &'static str = "DROP TABLE fogRegions";
+
+/**
+A polygon of a scene which a game master revealed, or hid again, for every
+player or for the single user `userId`.
+
+Regions apply in the order they were created, so later regions take
+precedence where they overlap.
+*/
+#[derive(Clone, Debug, Default, PartialEq, Selectable, Queryable)]
+#[diesel(table_name = schema::fogRegions)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct FogRegion
+{
+	pub id: i32,
+	/// The polygon's corners in pixels, as a JSON array of `[x, y]` pairs.
+	pub points: String,
+	pub revealed: bool,
+	pub sceneId: i32,
+	pub userId: Option<i32>,
+}
+
+impl FogRegion
+{
+	/**
+	The polygon's corners in pixels. A region whose points cannot be read has
+	none.
+	*/
+	pub fn points(&self) -> Vec<[i64; 2]>
+	{
+		return serde_json::from_str(&self.points).unwrap_or_default();
+	}
+	
+	/**
+	Whether the region applies to the user `userId`.
+	*/
+	pub fn appliesTo(&self, userId: i32) -> bool
+	{
+		return self.userId.is_none_or(|id| id == userId);
+	}
+}
+
+#[derive(Clone, Debug, Default, Insertable)]
+#[diesel(table_name = schema::fogRegions)]
+pub struct NewFogRegion
+{
+	pub points: String,
+	pub revealed: bool,
+	pub sceneId: i32,
+	pub userId: Option<i32>,
+}
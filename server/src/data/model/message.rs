@@ -1,33 +1,270 @@
+use std::ops::Range;
+use ::anyhow::{Error, Result};
 use ::chrono::NaiveDateTime;
-use ::diesel::{Insertable, Selectable, Queryable};
+use ::diesel::{Insertable, Selectable, Queryable, QueryableByName};
+use ::diesel::sql_types::Text;
+use ::serde::Serialize;
 use super::super::schema;
+use super::MainChannel;
 
 pub const CreateTable_Messages: &'static str = r#"CREATE TABLE IF NOT EXISTS messages
 (
 	id INTEGER PRIMARY KEY,
+	campaignId INTEGER DEFAULT NULL,
+	channelId INTEGER DEFAULT NULL,
+	deleted BOOLEAN NOT NULL DEFAULT 0,
+	edited TIMESTAMP DEFAULT NULL,
+	kind INTEGER NOT NULL DEFAULT 0,
+	recipientId INTEGER DEFAULT NULL,
 	text TEXT NOT NULL,
 	timestamp TIMESTAMP NOT NULL,
 	userId INTEGER
 )"#;
 
+/**
+Columns added to `messages` after its initial release, as `(name, definition)`
+pairs. Applied to existing databases by `Database::initialize`.
+
+Upgraded rows have no `campaignId`, so they appear in the lobby's history, and
+no `channelId`, so they belong to its main chat. They are plain `Say` messages
+and have no `recipientId`, so they are visible to everyone. None of them have
+been edited or deleted.
+*/
+pub const AddedColumns_Messages: &'static [(&'static str, &'static str)] = &[
+	("campaignId", "INTEGER DEFAULT NULL"),
+	("channelId", "INTEGER DEFAULT NULL"),
+	("deleted", "BOOLEAN NOT NULL DEFAULT 0"),
+	("edited", "TIMESTAMP DEFAULT NULL"),
+	("kind", "INTEGER NOT NULL DEFAULT 0"),
+	("recipientId", "INTEGER DEFAULT NULL"),
+];
+
 pub const DropTable_Messages: &'static str = "DROP TABLE messages";
 
-#[derive(Clone, Debug, Default, PartialEq, Selectable, Queryable)]
+/**
+Full text index of `messages.text`, searched with `dao::messageSearch`.
+
+The index reads its content from `messages` and is kept in sync by the triggers
+in `CreateTriggers_MessagesSearch`, so every path which creates, edits or
+deletes a message updates it.
+*/
+pub const CreateTable_MessagesSearch: &'static str = r#"CREATE VIRTUAL TABLE IF NOT EXISTS messagesSearch USING fts5
+(
+	text,
+	content = 'messages',
+	content_rowid = 'id'
+)"#;
+
+pub const CreateTriggers_MessagesSearch: &'static [&'static str] = &[
+	r#"CREATE TRIGGER IF NOT EXISTS messagesSearchInsert AFTER INSERT ON messages BEGIN
+		INSERT INTO messagesSearch(rowid, text) VALUES (new.id, new.text);
+	END"#,
+	r#"CREATE TRIGGER IF NOT EXISTS messagesSearchDelete AFTER DELETE ON messages BEGIN
+		INSERT INTO messagesSearch(messagesSearch, rowid, text) VALUES ('delete', old.id, old.text);
+	END"#,
+	r#"CREATE TRIGGER IF NOT EXISTS messagesSearchUpdate AFTER UPDATE OF text ON messages BEGIN
+		INSERT INTO messagesSearch(messagesSearch, rowid, text) VALUES ('delete', old.id, old.text);
+		INSERT INTO messagesSearch(rowid, text) VALUES (new.id, new.text);
+	END"#,
+];
+
+/// Index every existing message, for databases created before `messagesSearch` existed.
+pub const Rebuild_MessagesSearch: &'static str = "INSERT INTO messagesSearch(messagesSearch) VALUES ('rebuild')";
+
+pub const DropTable_MessagesSearch: &'static str = "DROP TABLE messagesSearch";
+
+#[derive(Clone, Debug, Default, PartialEq, Selectable, Queryable, QueryableByName)]
 #[diesel(table_name = schema::messages)]
 #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
 pub struct Message
 {
 	pub id: i32,
+	/// The campaign the message was sent in, or `None` for the lobby.
+	pub campaignId: Option<i32>,
+	/// The channel the message was sent to, or `None` for the main chat.
+	pub channelId: Option<i32>,
+	/// Whether the message was deleted. Its text is emptied, and kept in `messageRevisions`.
+	pub deleted: bool,
+	/// When the message was last edited or deleted.
+	pub edited: Option<NaiveDateTime>,
+	pub kind: i32,
+	/// The only user, besides the sender, who may see a whisper. `None` for public messages.
+	pub recipientId: Option<i32>,
 	pub text: String,
 	pub timestamp: NaiveDateTime,
 	pub userId: Option<i32>,
 }
 
+impl Message
+{
+	pub fn kind(&self) -> MessageKind
+	{
+		return MessageKind::try_from(self.kind).unwrap_or_default();
+	}
+}
+
 #[derive(Clone, Debug, Default, Insertable)]
 #[diesel(table_name = schema::messages)]
 pub struct NewMessage
 {
+	pub campaignId: Option<i32>,
+	pub channelId: Option<i32>,
+	pub kind: i32,
+	pub recipientId: Option<i32>,
 	pub text: String,
 	pub timestamp: NaiveDateTime,
 	pub userId: Option<i32>,
 }
+
+/**
+How a message is presented, stored as an integer in `messages.kind`.
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[repr(i32)]
+#[serde(rename_all = "camelCase")]
+pub enum MessageKind
+{
+	/// Ordinary in character chat.
+	#[default]
+	Say = 0,
+	/// An action, such as `/me waves`.
+	Emote = 1,
+	/// Out of character chat.
+	OutOfCharacter = 2,
+	/// Only visible to the sender and game masters, such as a `/gmroll`.
+	Secret = 3,
+}
+
+impl TryFrom<i32> for MessageKind
+{
+	type Error = Error;
+	
+	fn try_from(value: i32) -> Result<Self>
+	{
+		return match value
+		{
+			0 => Ok(Self::Say),
+			1 => Ok(Self::Emote),
+			2 => Ok(Self::OutOfCharacter),
+			3 => Ok(Self::Secret),
+			_ => Err(Error::msg(format!("Invalid message kind: {}", value))),
+		};
+	}
+}
+
+impl From<MessageKind> for i32
+{
+	fn from(value: MessageKind) -> Self
+	{
+		return value as i32;
+	}
+}
+
+/**
+Who is reading the chat log and where, which decides the messages they may see.
+*/
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MessageScope
+{
+	/// The campaign whose messages are read, or `None` for the lobby.
+	pub campaignId: Option<i32>,
+	/// The channels, besides the main chat, whose messages are read.
+	pub channels: Vec<i32>,
+	/// Whether messages sent to the main chat are read.
+	pub mainChat: bool,
+	/// Whether the reader may see `Secret` messages sent by others.
+	pub gameMaster: bool,
+	/// The reader, who may see whispers and secret messages they sent or received.
+	pub userId: Option<i32>,
+}
+
+impl MessageScope
+{
+	/**
+	Narrow the scope to the single `channel`, which is `MainChannel` for the
+	main chat. Channels outside the scope stay unreadable.
+	*/
+	pub fn onlyChannel(mut self, channel: i32) -> Self
+	{
+		self.mainChat = self.mainChat && channel == MainChannel;
+		self.channels.retain(|id| *id == channel);
+		return self;
+	}
+}
+
+/**
+The filters of a full text search of the chat log.
+*/
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MessageSearch
+{
+	/// Only include messages sent at or before this time.
+	pub end: Option<NaiveDateTime>,
+	pub limit: i64,
+	/// Only include messages sent at or after this time.
+	pub start: Option<NaiveDateTime>,
+	/// The words to search for, each of which must appear in a message.
+	pub terms: String,
+	/// Only include messages sent by this user.
+	pub userId: Option<i32>,
+}
+
+/**
+A message matching a search, with an excerpt of its text in which the matched
+words are found by `matches`.
+*/
+#[derive(Clone, Debug, Default, PartialEq, QueryableByName)]
+pub struct MessageSearchHit
+{
+	#[diesel(embed)]
+	pub message: Message,
+	/// `snippet` with each matched word wrapped in `MatchStart` and `MatchEnd`.
+	#[diesel(sql_type = Text)]
+	pub marked: String,
+	/// An excerpt of the text.
+	#[diesel(sql_type = Text)]
+	pub snippet: String,
+}
+
+impl MessageSearchHit
+{
+	/// Marks the start of a matched word in `marked`.
+	pub const MatchStart: char = '\u{2}';
+	/// Marks the end of a matched word in `marked`.
+	pub const MatchEnd: char = '\u{3}';
+	
+	/**
+	The byte ranges of `snippet` holding matched words.
+	
+	Chat text may itself contain `MatchStart` and `MatchEnd`, so only the
+	characters of `marked` missing from `snippet` are treated as markers.
+	*/
+	pub fn matches(&self) -> Vec<Range<usize>>
+	{
+		let mut matches = vec![];
+		let mut plain = self.snippet.char_indices().peekable();
+		let mut start = None;
+		
+		for c in self.marked.chars()
+		{
+			let offset = match plain.peek()
+			{
+				Some((_, p)) if *p == c => {
+					plain.next();
+					continue;
+				},
+				Some((i, _)) => *i,
+				None => self.snippet.len(),
+			};
+			
+			match c
+			{
+				Self::MatchStart => start = Some(offset),
+				Self::MatchEnd => matches.extend(start.take().map(|start| start..offset)),
+				_ => {},
+			}
+		}
+		
+		return matches;
+	}
+}
@@ -0,0 +1,42 @@
+use ::chrono::NaiveDateTime;
+use ::diesel::{Insertable, Selectable, Queryable};
+use super::super::schema;
+
+pub const CreateTable_MessageRevisions: &'static str = r#"CREATE TABLE IF NOT EXISTS messageRevisions
+(
+	id INTEGER PRIMARY KEY,
+	messageId INTEGER NOT NULL,
+	text TEXT NOT NULL,
+	timestamp TIMESTAMP NOT NULL,
+	userId INTEGER
+)"#;
+
+pub const DropTable_MessageRevisions: &'static str = "DROP TABLE messageRevisions";
+
+/**
+The text a message held before it was edited or deleted.
+
+`timestamp` and `userId` record when the change was made and by whom, so game
+masters can audit every version of a message.
+*/
+#[derive(Clone, Debug, Default, PartialEq, Selectable, Queryable)]
+#[diesel(table_name = schema::messageRevisions)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct MessageRevision
+{
+	pub id: i32,
+	pub messageId: i32,
+	pub text: String,
+	pub timestamp: NaiveDateTime,
+	pub userId: Option<i32>,
+}
+
+#[derive(Clone, Debug, Default, Insertable)]
+#[diesel(table_name = schema::messageRevisions)]
+pub struct NewMessageRevision
+{
+	pub messageId: i32,
+	pub text: String,
+	pub timestamp: NaiveDateTime,
+	pub userId: Option<i32>,
+}
@@ -1,9 +1,28 @@
+mod campaignrole;
+mod channel;
+mod fogregion;
 mod image;
 mod message;
+mod messagerevision;
+mod role;
+mod roll;
 mod scene2d;
+mod token;
 mod user;
+mod wall;
 
+pub use campaignrole::{CampaignRole, NewCampaignRole, CreateTable_CampaignRoles, DropTable_CampaignRoles};
+pub use channel::{Channel, ChannelMember, ChannelVisibility, NewChannel, NewChannelMember, MainChannel,
+	CreateTable_ChannelMembers, CreateTable_Channels, DropTable_ChannelMembers, DropTable_Channels};
+pub use fogregion::{FogRegion, NewFogRegion, CreateTable_FogRegions, DropTable_FogRegions};
 pub use image::{NewImageAsset, ImageAsset, CreateTable_ImageAssets, DropTable_ImageAssets};
-pub use message::{Message, NewMessage, CreateTable_Messages, DropTable_Messages};
-pub use scene2d::{NewScene2D, Scene2D, CreateTable_Scenes2D, DropTable_Scenes2D};
-pub use user::{NewUser, User, CreateTable_Users, DropTable_Users};
+pub use message::{Message, MessageKind, MessageScope, MessageSearch, MessageSearchHit, NewMessage, AddedColumns_Messages,
+	CreateTable_Messages, CreateTable_MessagesSearch, CreateTriggers_MessagesSearch,
+	DropTable_Messages, DropTable_MessagesSearch, Rebuild_MessagesSearch};
+pub use messagerevision::{MessageRevision, NewMessageRevision, CreateTable_MessageRevisions, DropTable_MessageRevisions};
+pub use role::Role;
+pub use roll::{NewRoll, Roll, CreateTable_Rolls, DropTable_Rolls};
+pub use scene2d::{GridType, NewScene2D, Scene2D, AddedColumns_Scenes2D, CreateTable_Scenes2D, DropTable_Scenes2D};
+pub use token::{NewToken, Token, CreateTable_Tokens, DropTable_Tokens};
+pub use user::{NewUser, User, AddedColumns_Users, CreateTable_Users, DropTable_Users};
+pub use wall::{DoorState, NewWall, Wall, WallKind, CreateTable_Walls, DropTable_Walls};
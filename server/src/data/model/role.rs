@@ -0,0 +1,67 @@
+use std::str::FromStr;
+use ::anyhow::{Error, Result};
+use ::serde_repr::{Deserialize_repr, Serialize_repr};
+
+/**
+The permission level of a user, stored as an integer in `users.role` and
+`campaignRoles.role`.
+
+Roles are ordered by privilege so that a permission check can require a
+minimum role. The default, `Player`, matches the default of the `role` columns.
+*/
+#[derive(Clone, Copy, Debug, Deserialize_repr, PartialEq, Eq, PartialOrd, Ord, Serialize_repr)]
+#[repr(i32)]
+pub enum Role
+{
+	Spectator = 0,
+	Player = 1,
+	GameMaster = 2,
+}
+
+impl Default for Role
+{
+	fn default() -> Self
+	{
+		return Self::Player;
+	}
+}
+
+impl FromStr for Role
+{
+	type Err = Error;
+	
+	fn from_str(value: &str) -> Result<Self>
+	{
+		return match value.to_ascii_lowercase().as_str()
+		{
+			"spectator" => Ok(Self::Spectator),
+			"player" => Ok(Self::Player),
+			"gm" | "gamemaster" => Ok(Self::GameMaster),
+			_ => Err(Error::msg(format!("Invalid role: {}", value))),
+		};
+	}
+}
+
+impl TryFrom<i32> for Role
+{
+	type Error = Error;
+	
+	fn try_from(value: i32) -> Result<Self>
+	{
+		return match value
+		{
+			0 => Ok(Self::Spectator),
+			1 => Ok(Self::Player),
+			2 => Ok(Self::GameMaster),
+			_ => Err(Error::msg(format!("Invalid role: {}", value))),
+		};
+	}
+}
+
+impl From<Role> for i32
+{
+	fn from(value: Role) -> Self
+	{
+		return value as i32;
+	}
+}
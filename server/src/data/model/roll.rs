@@ -0,0 +1,41 @@
+use ::diesel::{Insertable, Selectable, Queryable};
+use super::super::schema;
+
+pub const CreateTable_Rolls: &'static str = r#"CREATE TABLE IF NOT EXISTS rolls
+(
+	id INTEGER PRIMARY KEY,
+	expression TEXT NOT NULL,
+	messageId INTEGER NOT NULL UNIQUE,
+	result TEXT NOT NULL,
+	total BIGINT NOT NULL
+)"#;
+
+pub const DropTable_Rolls: &'static str = "DROP TABLE rolls";
+
+/**
+The outcome of a dice roll made by the server for a chat message.
+
+`result` holds every individual die as JSON, so that the message's text can be
+checked against what was actually rolled.
+*/
+#[derive(Clone, Debug, Default, PartialEq, Selectable, Queryable)]
+#[diesel(table_name = schema::rolls)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Roll
+{
+	pub id: i32,
+	pub expression: String,
+	pub messageId: i32,
+	pub result: String,
+	pub total: i64,
+}
+
+#[derive(Clone, Debug, Default, Insertable)]
+#[diesel(table_name = schema::rolls)]
+pub struct NewRoll
+{
+	pub expression: String,
+	pub messageId: i32,
+	pub result: String,
+	pub total: i64,
+}
@@ -1,15 +1,47 @@
+use ::anyhow::{Error, Result};
 use ::diesel::{Insertable, Selectable, Queryable};
+use ::serde::{Deserialize, Serialize};
 use super::super::schema;
 
 pub const CreateTable_Scenes2D: &'static str = r#"CREATE TABLE IF NOT EXISTS scenes2d
 (
 	id INTEGER PRIMARY KEY,
 	name TEXT NOT NULL,
-	backgroundId INTEGER NOT NULL
+	backgroundId INTEGER NOT NULL,
+	campaignId INTEGER DEFAULT NULL,
+	fogEnabled BOOLEAN NOT NULL DEFAULT 0,
+	gridCellSize BIGINT NOT NULL DEFAULT 100,
+	gridDistance DOUBLE NOT NULL DEFAULT 5,
+	gridOriginX BIGINT NOT NULL DEFAULT 0,
+	gridOriginY BIGINT NOT NULL DEFAULT 0,
+	gridType INTEGER NOT NULL DEFAULT 0,
+	gridUnit TEXT NOT NULL DEFAULT 'ft'
 )"#;
 
+/**
+Columns added to `scenes2d` after its initial release, as `(name, definition)`
+pairs. Applied to existing databases by `Database::initialize`.
+
+Upgraded rows have no `campaignId`, so they belong to the lobby, no fog of
+war, and a square grid of 100 pixel cells measuring 5 ft each.
+*/
+pub const AddedColumns_Scenes2D: &'static [(&'static str, &'static str)] = &[
+	("campaignId", "INTEGER DEFAULT NULL"),
+	("fogEnabled", "BOOLEAN NOT NULL DEFAULT 0"),
+	("gridCellSize", "BIGINT NOT NULL DEFAULT 100"),
+	("gridDistance", "DOUBLE NOT NULL DEFAULT 5"),
+	("gridOriginX", "BIGINT NOT NULL DEFAULT 0"),
+	("gridOriginY", "BIGINT NOT NULL DEFAULT 0"),
+	("gridType", "INTEGER NOT NULL DEFAULT 0"),
+	("gridUnit", "TEXT NOT NULL DEFAULT 'ft'"),
+];
+
 pub const DropTable_Scenes2D: &'static str = "DROP TABLE scenes2d";
 
+/**
+A map which a game master prepares for a campaign, drawn over the image asset
+`backgroundId`.
+*/
 #[derive(Clone, Debug, Default, PartialEq, Selectable, Queryable)]
 #[diesel(table_name = schema::scenes2d)]
 #[diesel(check_for_backend(diesel::sqlite::Sqlite))]
@@ -18,6 +50,27 @@ pub struct Scene2D
 	pub id: i32,
 	pub name: String,
 	pub backgroundId: i32,
+	/// The campaign the scene belongs to, or `None` for the lobby.
+	pub campaignId: Option<i32>,
+	/// Whether the scene is covered by fog of war, except where its `FogRegion`s reveal it.
+	pub fogEnabled: bool,
+	/// Size of a grid cell in pixels, measured between the centres of neighbouring cells.
+	pub gridCellSize: i64,
+	/// The distance across a grid cell, in `gridUnit`s.
+	pub gridDistance: f64,
+	/// Pixel position of the top left corner of the bounds of the grid's first cell.
+	pub gridOriginX: i64,
+	pub gridOriginY: i64,
+	pub gridType: i32,
+	pub gridUnit: String,
+}
+
+impl Scene2D
+{
+	pub fn gridType(&self) -> GridType
+	{
+		return GridType::try_from(self.gridType).unwrap_or_default();
+	}
 }
 
 #[derive(Clone, Debug, Default, Insertable)]
@@ -26,4 +79,55 @@ pub struct NewScene2D
 {
 	pub name: String,
 	pub backgroundId: i32,
+	pub campaignId: Option<i32>,
+	pub fogEnabled: bool,
+	pub gridCellSize: i64,
+	pub gridDistance: f64,
+	pub gridOriginX: i64,
+	pub gridOriginY: i64,
+	pub gridType: i32,
+	pub gridUnit: String,
+}
+
+/**
+The shape of a scene's grid cells, stored as an integer in `scenes2d.gridType`.
+*/
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[repr(i32)]
+#[serde(rename_all = "camelCase")]
+pub enum GridType
+{
+	#[default]
+	Square = 0,
+	/// Hexagons with flat tops, in columns.
+	HexFlat = 1,
+	/// Hexagons with pointed tops, in rows.
+	HexPointy = 2,
+	/// No grid, so tokens move freely.
+	Gridless = 3,
+}
+
+impl TryFrom<i32> for GridType
+{
+	type Error = Error;
+	
+	fn try_from(value: i32) -> Result<Self>
+	{
+		return match value
+		{
+			0 => Ok(Self::Square),
+			1 => Ok(Self::HexFlat),
+			2 => Ok(Self::HexPointy),
+			3 => Ok(Self::Gridless),
+			_ => Err(Error::msg(format!("Invalid grid type: {}", value))),
+		};
+	}
+}
+
+impl From<GridType> for i32
+{
+	fn from(value: GridType) -> Self
+	{
+		return value as i32;
+	}
 }
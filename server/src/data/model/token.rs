@@ -0,0 +1,101 @@
+use ::diesel::{Insertable, Selectable, Queryable};
+use super::super::schema;
+
+pub const CreateTable_Tokens: &'static str = r#"CREATE TABLE IF NOT EXISTS tokens
+(
+	id INTEGER PRIMARY KEY,
+	height BIGINT NOT NULL,
+	hidden BOOLEAN NOT NULL DEFAULT 0,
+	imageId INTEGER DEFAULT NULL,
+	name TEXT NOT NULL,
+	ownerId INTEGER DEFAULT NULL,
+	sceneId INTEGER NOT NULL,
+	width BIGINT NOT NULL,
+	x BIGINT NOT NULL,
+	y BIGINT NOT NULL
+)"#;
+
+pub const DropTable_Tokens: &'static str = "DROP TABLE tokens";
+
+/**
+A piece on a scene's board, such as a character or monster.
+
+Positions and sizes are in pixels of the scene's background, with `x` and `y`
+locating the centre of the token.
+*/
+#[derive(Clone, Debug, Default, PartialEq, Selectable, Queryable)]
+#[diesel(table_name = schema::tokens)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Token
+{
+	pub id: i32,
+	pub height: i64,
+	/// Hidden tokens are only seen by game masters and the token's owner.
+	pub hidden: bool,
+	/// The image asset drawn for the token, if any.
+	pub imageId: Option<i32>,
+	pub name: String,
+	/// The user who may move the token besides game masters.
+	pub ownerId: Option<i32>,
+	pub sceneId: i32,
+	pub width: i64,
+	pub x: i64,
+	pub y: i64,
+}
+
+impl Token
+{
+	/**
+	Whether the token may be moved by the user `userId`, who is a game master
+	if `gameMaster`.
+	*/
+	pub fn isMovableBy(&self, gameMaster: bool, userId: i32) -> bool
+	{
+		return gameMaster || self.ownerId == Some(userId);
+	}
+	
+	/**
+	Whether the token may be seen by the user `userId`, who is a game master if
+	`gameMaster`.
+	*/
+	pub fn isVisibleTo(&self, gameMaster: bool, userId: i32) -> bool
+	{
+		return !self.hidden || self.isMovableBy(gameMaster, userId);
+	}
+}
+
+#[derive(Clone, Debug, Default, Insertable)]
+#[diesel(table_name = schema::tokens)]
+pub struct NewToken
+{
+	pub height: i64,
+	pub hidden: bool,
+	pub imageId: Option<i32>,
+	pub name: String,
+	pub ownerId: Option<i32>,
+	pub sceneId: i32,
+	pub width: i64,
+	pub x: i64,
+	pub y: i64,
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	
+	#[test]
+	fn access()
+	{
+		let token = Token { ownerId: Some(2), ..Default::default() };
+		assert!(token.isMovableBy(true, 1));
+		assert!(token.isMovableBy(false, 2));
+		assert!(!token.isMovableBy(false, 1));
+		assert!(token.isVisibleTo(false, 1));
+		
+		let hidden = Token { hidden: true, ..token };
+		assert!(hidden.isVisibleTo(true, 1));
+		assert!(hidden.isVisibleTo(false, 2));
+		assert!(!hidden.isVisibleTo(false, 1));
+	}
+}
@@ -1,13 +1,30 @@
 use ::diesel::{Insertable, Selectable, Queryable};
 use super::super::schema;
+use super::Role;
 
 pub const CreateTable_Users: &'static str = r#"CREATE TABLE IF NOT EXISTS users
 (
 	id INTEGER PRIMARY KEY,
 	label TEXT DEFAULT NULL,
-	name TEXT NOT NULL
+	name TEXT NOT NULL,
+	passwordHash TEXT DEFAULT NULL,
+	role INTEGER NOT NULL DEFAULT 1
 )"#;
 
+/**
+Columns added to `users` after its initial release, as `(name, definition)`
+pairs. Applied to existing databases by `Database::initialize`.
+
+Rows upgraded this way have no `passwordHash`. Those accounts cannot log in
+until an operator sets their password with `server set-password <name>`.
+
+Upgraded rows are given the `Player` role.
+*/
+pub const AddedColumns_Users: &'static [(&'static str, &'static str)] = &[
+	("passwordHash", "TEXT DEFAULT NULL"),
+	("role", "INTEGER NOT NULL DEFAULT 1"),
+];
+
 pub const DropTable_Users: &'static str = "DROP TABLE users";
 
 #[derive(Clone, Debug, Default, PartialEq, Selectable, Queryable)]
@@ -18,12 +35,43 @@ pub struct User
 	pub id: i32,
 	pub label: Option<String>,
 	pub name: String,
+	/// PHC formatted Argon2 hash. `None` for accounts created before passwords were required.
+	pub passwordHash: Option<String>,
+	/// See `Role`.
+	pub role: i32,
 }
 
-#[derive(Clone, Debug, Default, Insertable)]
+#[derive(Clone, Debug, Insertable)]
 #[diesel(table_name = schema::users)]
 pub struct NewUser
 {
 	pub label: Option<String>,
 	pub name: String,
+	pub passwordHash: Option<String>,
+	pub role: i32,
+}
+
+impl Default for NewUser
+{
+	fn default() -> Self
+	{
+		return Self
+		{
+			label: None,
+			name: String::default(),
+			passwordHash: None,
+			role: Role::default().into(),
+		};
+	}
+}
+
+impl User
+{
+	/**
+	Unrecognized values are treated as the least privileged role.
+	*/
+	pub fn role(&self) -> Role
+	{
+		return Role::try_from(self.role).unwrap_or(Role::Spectator);
+	}
 }
@@ -0,0 +1,153 @@
+use ::anyhow::{Error, Result};
+use ::diesel::{Insertable, Selectable, Queryable};
+use ::serde::{Deserialize, Serialize};
+use super::super::schema;
+
+pub const CreateTable_Walls: &'static str = r#"CREATE TABLE IF NOT EXISTS walls
+(
+	id INTEGER PRIMARY KEY,
+	doorState INTEGER NOT NULL DEFAULT 0,
+	endX BIGINT NOT NULL,
+	endY BIGINT NOT NULL,
+	kind INTEGER NOT NULL,
+	sceneId INTEGER NOT NULL,
+	startX BIGINT NOT NULL,
+	startY BIGINT NOT NULL
+)"#;
+
+pub const DropTable_Walls: &'static str = "DROP TABLE walls";
+
+/**
+A straight segment of a scene which blocks sight, running from the pixel
+`(startX, startY)` to `(endX, endY)` of the scene's background.
+*/
+#[derive(Clone, Debug, Default, PartialEq, Selectable, Queryable)]
+#[diesel(table_name = schema::walls)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Wall
+{
+	pub id: i32,
+	/// Whether a door is open, closed or locked. Ignored for other kinds of wall.
+	pub doorState: i32,
+	pub endX: i64,
+	pub endY: i64,
+	pub kind: i32,
+	pub sceneId: i32,
+	pub startX: i64,
+	pub startY: i64,
+}
+
+impl Wall
+{
+	pub fn doorState(&self) -> DoorState
+	{
+		return DoorState::try_from(self.doorState).unwrap_or_default();
+	}
+	
+	pub fn kind(&self) -> WallKind
+	{
+		return WallKind::try_from(self.kind).unwrap_or_default();
+	}
+	
+	/**
+	Whether the wall currently lets sight through from either side, as an open
+	door does.
+	*/
+	pub fn isOpen(&self) -> bool
+	{
+		return self.kind() == WallKind::Door && self.doorState() == DoorState::Open;
+	}
+}
+
+#[derive(Clone, Debug, Default, Insertable)]
+#[diesel(table_name = schema::walls)]
+pub struct NewWall
+{
+	pub doorState: i32,
+	pub endX: i64,
+	pub endY: i64,
+	pub kind: i32,
+	pub sceneId: i32,
+	pub startX: i64,
+	pub startY: i64,
+}
+
+/**
+How a wall blocks sight, stored as an integer in `walls.kind`.
+*/
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[repr(i32)]
+#[serde(rename_all = "camelCase")]
+pub enum WallKind
+{
+	/// Blocks sight from both sides.
+	#[default]
+	Wall = 0,
+	/// Blocks sight only from the left of the wall, facing from its start to its end on screen.
+	OneWay = 1,
+	/// Blocks sight from both sides unless open.
+	Door = 2,
+}
+
+impl TryFrom<i32> for WallKind
+{
+	type Error = Error;
+	
+	fn try_from(value: i32) -> Result<Self>
+	{
+		return match value
+		{
+			0 => Ok(Self::Wall),
+			1 => Ok(Self::OneWay),
+			2 => Ok(Self::Door),
+			_ => Err(Error::msg(format!("Invalid wall kind: {}", value))),
+		};
+	}
+}
+
+impl From<WallKind> for i32
+{
+	fn from(value: WallKind) -> Self
+	{
+		return value as i32;
+	}
+}
+
+/**
+Whether a door is open, stored as an integer in `walls.doorState`.
+*/
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[repr(i32)]
+#[serde(rename_all = "camelCase")]
+pub enum DoorState
+{
+	#[default]
+	Closed = 0,
+	Open = 1,
+	/// Closed, and only opened by game masters.
+	Locked = 2,
+}
+
+impl TryFrom<i32> for DoorState
+{
+	type Error = Error;
+	
+	fn try_from(value: i32) -> Result<Self>
+	{
+		return match value
+		{
+			0 => Ok(Self::Closed),
+			1 => Ok(Self::Open),
+			2 => Ok(Self::Locked),
+			_ => Err(Error::msg(format!("Invalid door state: {}", value))),
+		};
+	}
+}
+
+impl From<DoorState> for i32
+{
+	fn from(value: DoorState) -> Self
+	{
+		return value as i32;
+	}
+}
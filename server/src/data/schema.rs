@@ -1,5 +1,49 @@
 use diesel::table;
 
+table!
+{
+	campaignRoles (id)
+	{
+		id -> Integer,
+		campaignId -> Integer,
+		role -> Integer,
+		userId -> Integer,
+	}
+}
+
+table!
+{
+	channelMembers (id)
+	{
+		id -> Integer,
+		channelId -> Integer,
+		userId -> Integer,
+	}
+}
+
+table!
+{
+	channels (id)
+	{
+		id -> Integer,
+		campaignId -> Nullable<Integer>,
+		name -> Text,
+		visibility -> Integer,
+	}
+}
+
+table!
+{
+	fogRegions (id)
+	{
+		id -> Integer,
+		points -> Text,
+		revealed -> Bool,
+		sceneId -> Integer,
+		userId -> Nullable<Integer>,
+	}
+}
+
 table!
 {
 	imageAssets (id)
@@ -11,17 +55,47 @@ table!
 	}
 }
 
+table!
+{
+	messageRevisions (id)
+	{
+		id -> Integer,
+		messageId -> Integer,
+		text -> Text,
+		timestamp -> Timestamp,
+		userId -> Nullable<Integer>,
+	}
+}
+
 table!
 {
 	messages (id)
 	{
 		id -> Integer,
+		campaignId -> Nullable<Integer>,
+		channelId -> Nullable<Integer>,
+		deleted -> Bool,
+		edited -> Nullable<Timestamp>,
+		kind -> Integer,
+		recipientId -> Nullable<Integer>,
 		text -> Text,
 		timestamp -> Timestamp,
 		userId -> Nullable<Integer>,
 	}
 }
 
+table!
+{
+	rolls (id)
+	{
+		id -> Integer,
+		expression -> Text,
+		messageId -> Integer,
+		result -> Text,
+		total -> BigInt,
+	}
+}
+
 table!
 {
 	scenes2d (id)
@@ -29,6 +103,31 @@ table!
 		id -> Integer,
 		name -> Text,
 		backgroundId -> Integer,
+		campaignId -> Nullable<Integer>,
+		fogEnabled -> Bool,
+		gridCellSize -> BigInt,
+		gridDistance -> Double,
+		gridOriginX -> BigInt,
+		gridOriginY -> BigInt,
+		gridType -> Integer,
+		gridUnit -> Text,
+	}
+}
+
+table!
+{
+	tokens (id)
+	{
+		id -> Integer,
+		height -> BigInt,
+		hidden -> Bool,
+		imageId -> Nullable<Integer>,
+		name -> Text,
+		ownerId -> Nullable<Integer>,
+		sceneId -> Integer,
+		width -> BigInt,
+		x -> BigInt,
+		y -> BigInt,
 	}
 }
 
@@ -39,5 +138,22 @@ table!
 		id -> Integer,
 		label -> Nullable<Text>,
 		name -> Text,
+		passwordHash -> Nullable<Text>,
+		role -> Integer,
+	}
+}
+
+table!
+{
+	walls (id)
+	{
+		id -> Integer,
+		doorState -> Integer,
+		endX -> BigInt,
+		endY -> BigInt,
+		kind -> Integer,
+		sceneId -> Integer,
+		startX -> BigInt,
+		startY -> BigInt,
 	}
 }
@@ -1,7 +1,9 @@
+mod chat;
+mod cli;
 mod config;
 mod data;
 mod net;
-mod util;
+mod scene;
 
 use std::path::PathBuf;
 use std::sync::OnceLock;
@@ -26,7 +28,7 @@ const LoggerDefaultBaseDir: &'static str = ".";
 pub fn getConfig() -> &'static Config
 {
 	static ConfigLock: OnceLock<Config> = OnceLock::new();
-	return ConfigLock.get_or_init(|| Config::getTestConfig());
+	return ConfigLock.get_or_init(Config::getTestConfig);
 }
 
 #[cfg(not(test))]
@@ -48,6 +50,14 @@ pub fn getConfig() -> &'static Config
 #[main(flavor = "current_thread")]
 async fn main() -> Result<()>
 {
+	let arguments: Vec<String> = std::env::args().skip(1).collect();
+	if !arguments.is_empty()
+	{
+		let command = cli::parseArguments(&arguments)?;
+		initializeDatabase().await;
+		return cli::run(command).await;
+	}
+	
 	initializeDatabase().await;
 	initializeLogger()?;
 	
@@ -57,7 +67,10 @@ async fn main() -> Result<()>
 	let serverToken = cancelToken.clone();
 	tracker.spawn(async move {
 		let server = WebSocketServer::default();
-		let _ = server.start(serverToken).await;
+		if let Err(e) = server.start(serverToken).await
+		{
+			error!("Server stopped with an error: {:?}", e);
+		}
 	});
 	tracker.close();
 	
@@ -0,0 +1,137 @@
+use ::anyhow::{Error, Result};
+use ::argon2::Argon2;
+use ::argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use ::password_hash::rand_core::OsRng;
+use ::serde_repr::{Deserialize_repr, Serialize_repr};
+
+/// Maximum number of characters in a username.
+pub const MaxUsernameLength: usize = 32;
+
+/**
+The reason sent in the `reason` field of an `AuthenticateFail` command.
+*/
+#[derive(Clone, Copy, Debug, Deserialize_repr, PartialEq, Eq, Serialize_repr)]
+#[repr(i32)]
+pub enum AuthenticationFailure
+{
+	/// The `name` or `password` field was missing or empty.
+	MissingCredentials = 1,
+	/// The username and password did not match a known account.
+	InvalidCredentials,
+	/// The account predates password authentication and its password must be set from the command line.
+	PasswordNotSet,
+	/// `AuthenticateCreate` was sent for a username which already exists.
+	UserExists,
+	/// The server failed to process the request.
+	ServerError,
+	/// `AuthenticateResume` was sent with an unknown or expired session token.
+	InvalidSession,
+	/// `AuthenticateCreate` was sent with a username which is too long or has characters which are not allowed.
+	InvalidUsername,
+	/// An `AuthenticateCreate`, `AuthenticateResume` or `AuthenticateSend` came from an authenticated client.
+	AlreadyAuthenticated,
+}
+
+/**
+Hash `password` with Argon2 using a freshly generated salt.
+
+The result is a PHC formatted string containing the algorithm parameters and
+salt, suitable for storing in `users.passwordHash`.
+*/
+pub fn hashPassword(password: &str) -> Result<String>
+{
+	let salt = SaltString::generate(&mut OsRng);
+	return match Argon2::default().hash_password(password.as_bytes(), &salt)
+	{
+		Ok(hash) => Ok(hash.to_string()),
+		Err(e) => Err(Error::msg(format!("Failed to hash password: {}", e))),
+	};
+}
+
+/**
+Check whether `name` may be used as the username of a new account.
+
+Usernames are shown in chat, so they must be at most `MaxUsernameLength`
+printable characters, without surrounding whitespace, invisible formatting
+characters such as bidirectional controls, or the square brackets of BBCode.
+*/
+pub fn isValidUsername(name: &str) -> bool
+{
+	return name.trim() == name
+		&& name.chars().count() <= MaxUsernameLength
+		&& !name.chars().any(|c| c.is_control() || isFormatting(c) || c == '[' || c == ']');
+}
+
+/**
+Check `password` against a PHC formatted `hash` produced by `hashPassword`.
+*/
+pub fn verifyPassword(password: &str, hash: &str) -> bool
+{
+	return match PasswordHash::new(hash)
+	{
+		Err(_) => false,
+		Ok(parsed) => Argon2::default()
+			.verify_password(password.as_bytes(), &parsed)
+			.is_ok(),
+	};
+}
+
+/**
+Whether `c` is an invisible character which changes how the text around it is
+shown: zero width characters, bidirectional marks, embeddings, overrides and
+isolates, and the byte order mark.
+*/
+fn isFormatting(c: char) -> bool
+{
+	return matches!(c,
+		'\u{061C}' | '\u{200B}'..='\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2060}'..='\u{2069}' | '\u{FEFF}'
+	);
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	
+	#[test]
+	fn usernames()
+	{
+		assert!(isValidUsername("alice"));
+		assert!(isValidUsername("Zoë the Bold"));
+		assert!(isValidUsername(&"a".repeat(MaxUsernameLength)));
+		
+		assert!(!isValidUsername(&"a".repeat(MaxUsernameLength + 1)));
+		assert!(!isValidUsername(" alice"));
+		assert!(!isValidUsername("al\nice"));
+		assert!(!isValidUsername("evil\u{202E}gm"));
+		assert!(!isValidUsername("gm\u{200B}"));
+		assert!(!isValidUsername("[b]alice[/b]"));
+	}
+	
+	#[test]
+	fn hashAndVerify()
+	{
+		let password = "correct horse battery staple";
+		
+		let hash = hashPassword(password).expect("Error hashing password");
+		assert_ne!(hash, password);
+		assert!(hash.starts_with("$argon2"));
+		
+		assert!(verifyPassword(password, &hash));
+		assert!(!verifyPassword("incorrect horse battery staple", &hash));
+		assert!(!verifyPassword(password, "not a hash"));
+	}
+	
+	#[test]
+	fn saltIsUnique()
+	{
+		let password = "hunter2";
+		
+		let first = hashPassword(password).expect("Error hashing password");
+		let second = hashPassword(password).expect("Error hashing password");
+		assert_ne!(first, second);
+		
+		assert!(verifyPassword(password, &first));
+		assert!(verifyPassword(password, &second));
+	}
+}
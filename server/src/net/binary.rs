@@ -0,0 +1,114 @@
+use ::anyhow::Result;
+#[cfg(test)]
+use ::anyhow::Error;
+
+const IdLength: usize = 8;
+const KeyLengthLength: usize = 2;
+const HeaderLength: usize = IdLength + KeyLengthLength;
+
+/**
+A single binary WebSocket frame carrying one `BinaryData` entry of a command.
+
+Layout, with integers in network byte order:
+
+| Bytes | Content |
+|-------|---------|
+| 8 | Binary id, matching the command's `BinaryId` field |
+| 2 | Length of the key in bytes |
+| n | UTF-8 key, matching a key of the command's `BinaryData` |
+| * | Raw data |
+
+All frames for a command are sent before the text frame containing the
+command itself, so the receiver already holds the data when the command
+arrives.
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BinaryFrame
+{
+	pub id: u64,
+	pub key: String,
+	pub data: Vec<u8>,
+}
+
+impl BinaryFrame
+{
+	#[cfg(test)]
+	pub fn decode(bytes: &[u8]) -> Result<Self>
+	{
+		if bytes.len() < HeaderLength
+		{
+			return Err(Error::msg("Binary frame is too short to contain a header"));
+		}
+		
+		let (idBytes, rest) = bytes.split_at(IdLength);
+		let (keyLengthBytes, rest) = rest.split_at(KeyLengthLength);
+		
+		let id = u64::from_be_bytes(idBytes.try_into()?);
+		let keyLength = u16::from_be_bytes(keyLengthBytes.try_into()?) as usize;
+		
+		if rest.len() < keyLength
+		{
+			return Err(Error::msg("Binary frame key length exceeds frame length"));
+		}
+		
+		let (keyBytes, data) = rest.split_at(keyLength);
+		
+		return Ok(Self
+		{
+			id,
+			key: String::from_utf8(keyBytes.to_vec())?,
+			data: data.to_vec(),
+		});
+	}
+	
+	pub fn encode(&self) -> Result<Vec<u8>>
+	{
+		let keyLength = u16::try_from(self.key.len())?;
+		
+		let mut bytes = Vec::with_capacity(HeaderLength + self.key.len() + self.data.len());
+		bytes.extend_from_slice(&self.id.to_be_bytes());
+		bytes.extend_from_slice(&keyLength.to_be_bytes());
+		bytes.extend_from_slice(self.key.as_bytes());
+		bytes.extend_from_slice(&self.data);
+		
+		return Ok(bytes);
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	
+	#[test]
+	fn roundTrip()
+	{
+		let frame = BinaryFrame
+		{
+			id: 258,
+			key: "background".into(),
+			data: vec![0, 1, 2, 255],
+		};
+		
+		let bytes = frame.encode().expect("Error encoding frame");
+		assert_eq!(&bytes[..IdLength], &[0, 0, 0, 0, 0, 0, 1, 2]);
+		assert_eq!(&bytes[IdLength..HeaderLength], &[0, 10]);
+		assert_eq!(bytes.len(), HeaderLength + 10 + 4);
+		
+		let decoded = BinaryFrame::decode(&bytes).expect("Error decoding frame");
+		assert_eq!(decoded, frame);
+	}
+	
+	#[test]
+	fn malformed()
+	{
+		assert!(BinaryFrame::decode(&[0, 0, 0]).is_err());
+		
+		//Claims a 5 byte key but only contains 2 bytes after the header
+		let bytes = [0, 0, 0, 0, 0, 0, 0, 1, 0, 5, b'a', b'b'];
+		assert!(BinaryFrame::decode(&bytes).is_err());
+		
+		let invalidUtf8 = [0, 0, 0, 0, 0, 0, 0, 1, 0, 1, 0xff];
+		assert!(BinaryFrame::decode(&invalidUtf8).is_err());
+	}
+}
@@ -0,0 +1,448 @@
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+use ::anyhow::Result;
+use crate::chat::dice::{DiceExpression, RandomRoller, RollResult};
+use crate::data::{MessageKind, Role};
+use super::error::RequestError;
+
+pub fn getChatCommands() -> &'static ChatCommands
+{
+	static ChatCommandsLock: OnceLock<ChatCommands> = OnceLock::new();
+	return ChatCommandsLock.get_or_init(ChatCommands::builtIn);
+}
+
+/**
+A single argument in the grammar of a chat command.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Argument
+{
+	/// A single word, such as a username.
+	Word(&'static str),
+	/// The rest of the text, which must not be empty.
+	Text(&'static str),
+	/// The rest of the text, which may be empty.
+	OptionalText(&'static str),
+}
+
+/**
+Runs a chat command with the arguments parsed according to its grammar, one
+per `Argument`.
+*/
+pub type ChatHandler = fn(&ChatContext, &[&str]) -> Result<ChatOutput>;
+
+/**
+A slash command which may be sent as chat text, such as `/me waves`.
+*/
+pub struct ChatCommand
+{
+	/// The name, including the leading `/`.
+	pub name: &'static str,
+	pub arguments: &'static [Argument],
+	pub description: &'static str,
+	/// The minimum role which may use the command.
+	pub role: Role,
+	pub handler: ChatHandler,
+}
+
+impl ChatCommand
+{
+	/**
+	How to use the command, such as `/w <user> <text>`.
+	*/
+	pub fn usage(&self) -> String
+	{
+		let mut usage = self.name.to_string();
+		for argument in self.arguments
+		{
+			let text = match argument
+			{
+				Argument::Word(name) | Argument::Text(name) => format!(" <{}>", name),
+				Argument::OptionalText(name) => format!(" [{}]", name),
+			};
+			
+			usage.push_str(&text);
+		}
+		
+		return usage;
+	}
+	
+	/**
+	Split `input` into one value per argument of the command's grammar.
+	*/
+	fn parseArguments<'a>(&self, input: &'a str) -> Result<Vec<&'a str>>
+	{
+		let mut values = vec![];
+		let mut rest = input.trim();
+		
+		for argument in self.arguments
+		{
+			let value = match argument
+			{
+				Argument::Word(_) => {
+					let (word, remaining) = rest.split_once(char::is_whitespace)
+						.unwrap_or((rest, ""));
+					rest = remaining.trim_start();
+					word
+				},
+				Argument::Text(_) | Argument::OptionalText(_) => std::mem::take(&mut rest),
+			};
+			
+			if value.is_empty() && !matches!(argument, Argument::OptionalText(_))
+			{
+				return Err(RequestError::invalid(format!("Usage: {}", self.usage())).into());
+			}
+			
+			values.push(value);
+		}
+		
+		if !rest.is_empty()
+		{
+			return Err(RequestError::invalid(format!("Usage: {}", self.usage())).into());
+		}
+		
+		return Ok(values);
+	}
+}
+
+/**
+The sender of a chat command.
+*/
+pub struct ChatContext<'a>
+{
+	pub commands: &'a ChatCommands,
+	/// The sender's role, within the current campaign if there is one.
+	pub role: Role,
+}
+
+/**
+What a chat command produces.
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChatOutput
+{
+	/**
+	A message saved to the chat log.
+	
+	Whispers with a `recipient` are delivered to that user and the sender,
+	`Secret` messages to the sender and game masters and all others to the
+	sender's chat room.
+	*/
+	Message
+	{
+		kind: MessageKind,
+		/// The username of the only user, besides the sender, who may see the message.
+		recipient: Option<String>,
+		roll: Option<RollResult>,
+		text: String,
+	},
+	
+	/// Text shown only to the sender, which is not saved.
+	Reply(String),
+}
+
+impl ChatOutput
+{
+	/**
+	A message of `kind` with no recipient or roll.
+	*/
+	pub fn message(kind: MessageKind, text: impl Into<String>) -> Self
+	{
+		return Self::Message { kind, recipient: None, roll: None, text: text.into() };
+	}
+}
+
+/**
+The registered chat commands, by name.
+*/
+#[derive(Default)]
+pub struct ChatCommands
+{
+	commands: BTreeMap<&'static str, ChatCommand>,
+}
+
+impl ChatCommands
+{
+	/**
+	A registry holding the built in commands.
+	*/
+	pub fn builtIn() -> Self
+	{
+		let mut commands = Self::default();
+		
+		commands.register(ChatCommand
+		{
+			name: "/gmroll",
+			arguments: &[Argument::Text("dice")],
+			description: "Roll dice which only game masters can see",
+			role: Role::Player,
+			handler: handleGmRoll,
+		});
+		
+		commands.register(ChatCommand
+		{
+			name: "/help",
+			arguments: &[Argument::OptionalText("command")],
+			description: "List the commands you can use, or describe one of them",
+			role: Role::Player,
+			handler: handleHelp,
+		});
+		
+		commands.register(ChatCommand
+		{
+			name: "/me",
+			arguments: &[Argument::Text("action")],
+			description: "Describe what your character does",
+			role: Role::Player,
+			handler: handleMe,
+		});
+		
+		commands.register(ChatCommand
+		{
+			name: "/ooc",
+			arguments: &[Argument::Text("text")],
+			description: "Speak out of character",
+			role: Role::Player,
+			handler: handleOoc,
+		});
+		
+		commands.register(ChatCommand
+		{
+			name: "/roll",
+			arguments: &[Argument::Text("dice")],
+			description: "Roll dice, such as 4d6kh3+2",
+			role: Role::Player,
+			handler: handleRoll,
+		});
+		
+		commands.register(ChatCommand
+		{
+			name: "/w",
+			arguments: &[Argument::Word("user"), Argument::Text("text")],
+			description: "Whisper to a single user",
+			role: Role::Player,
+			handler: handleWhisper,
+		});
+		
+		return commands;
+	}
+	
+	/**
+	Add `command`, replacing any registered command of the same name.
+	*/
+	pub fn register(&mut self, command: ChatCommand)
+	{
+		self.commands.insert(command.name, command);
+	}
+	
+	/**
+	The commands which may be used with `role`, sorted by name.
+	*/
+	pub fn available(&self, role: Role) -> Vec<&ChatCommand>
+	{
+		return self.commands.values()
+			.filter(|command| role >= command.role)
+			.collect();
+	}
+	
+	/**
+	Run the command in `text`, or return `None` if `text` is not a command.
+	*/
+	pub fn run(&self, context: &ChatContext, text: &str) -> Option<Result<ChatOutput>>
+	{
+		if !text.starts_with('/')
+		{
+			return None;
+		}
+		
+		let (name, input) = text.split_once(char::is_whitespace)
+			.unwrap_or((text, ""));
+		
+		let command = match self.commands.get(name)
+		{
+			Some(command) => command,
+			None => return Some(Err(RequestError::invalid(format!("Unknown command {}, see /help", name)).into())),
+		};
+		
+		if context.role < command.role
+		{
+			return Some(Err(RequestError::denied(format!("{} requires the {:?} role", name, command.role)).into()));
+		}
+		
+		return Some(command.parseArguments(input)
+			.and_then(|arguments| (command.handler)(context, &arguments)));
+	}
+}
+
+/**
+Roll the dice expression at the start of `input`, returning the result and the
+text of its message.
+*/
+fn rollDice(input: &str) -> Result<(RollResult, String)>
+{
+	let (expression, label) = DiceExpression::parse(input)
+		.map_err(|e| RequestError::invalid(format!("Invalid dice expression: {}", e)))?;
+	
+	let result = expression.roll(&mut RandomRoller::default())
+		.map_err(|e| RequestError::invalid(e.to_string()))?;
+	
+	let text = match label.is_empty()
+	{
+		true => format!("rolls {}", result),
+		false => format!("rolls {} ({})", result, label),
+	};
+	
+	return Ok((result, text));
+}
+
+fn handleGmRoll(_: &ChatContext, arguments: &[&str]) -> Result<ChatOutput>
+{
+	let (roll, text) = rollDice(arguments[0])?;
+	return Ok(ChatOutput::Message { kind: MessageKind::Secret, recipient: None, roll: Some(roll), text });
+}
+
+fn handleHelp(context: &ChatContext, arguments: &[&str]) -> Result<ChatOutput>
+{
+	let describe = |command: &ChatCommand| format!("{} - {}", command.usage(), command.description);
+	
+	if !arguments[0].is_empty()
+	{
+		let name = format!("/{}", arguments[0].trim_start_matches('/'));
+		return match context.commands.commands.get(name.as_str())
+		{
+			Some(command) => Ok(ChatOutput::Reply(describe(command))),
+			None => Err(RequestError::invalid(format!("Unknown command {}, see /help", name)).into()),
+		};
+	}
+	
+	let lines = context.commands.available(context.role)
+		.into_iter()
+		.map(describe)
+		.collect::<Vec<_>>();
+	
+	return Ok(ChatOutput::Reply(lines.join("\n")));
+}
+
+fn handleMe(_: &ChatContext, arguments: &[&str]) -> Result<ChatOutput>
+{
+	return Ok(ChatOutput::message(MessageKind::Emote, arguments[0]));
+}
+
+fn handleOoc(_: &ChatContext, arguments: &[&str]) -> Result<ChatOutput>
+{
+	return Ok(ChatOutput::message(MessageKind::OutOfCharacter, arguments[0]));
+}
+
+fn handleRoll(_: &ChatContext, arguments: &[&str]) -> Result<ChatOutput>
+{
+	let (roll, text) = rollDice(arguments[0])?;
+	return Ok(ChatOutput::Message { kind: MessageKind::Say, recipient: None, roll: Some(roll), text });
+}
+
+fn handleWhisper(_: &ChatContext, arguments: &[&str]) -> Result<ChatOutput>
+{
+	return Ok(ChatOutput::Message
+	{
+		kind: MessageKind::Say,
+		recipient: Some(arguments[0].to_string()),
+		roll: None,
+		text: arguments[1].to_string(),
+	});
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::net::error::{describeError, ErrorCode};
+	
+	fn run(role: Role, text: &str) -> Option<Result<ChatOutput>>
+	{
+		let commands = ChatCommands::builtIn();
+		let context = ChatContext { commands: &commands, role };
+		return commands.run(&context, text);
+	}
+	
+	fn errorCode(role: Role, text: &str) -> ErrorCode
+	{
+		let error = run(role, text).expect("Not a command").expect_err("Command succeeded");
+		return describeError(&error).0;
+	}
+	
+	#[test]
+	fn plainText()
+	{
+		assert!(run(Role::Player, "hello /me").is_none());
+	}
+	
+	#[test]
+	fn emotes()
+	{
+		let output = run(Role::Player, "/me  waves hello ").expect("Not a command").expect("Error running /me");
+		assert_eq!(output, ChatOutput::message(MessageKind::Emote, "waves hello"));
+		
+		let output = run(Role::Player, "/ooc brb").expect("Not a command").expect("Error running /ooc");
+		assert_eq!(output, ChatOutput::message(MessageKind::OutOfCharacter, "brb"));
+	}
+	
+	#[test]
+	fn whispers()
+	{
+		let output = run(Role::Player, "/w bob meet me  later").expect("Not a command").expect("Error running /w");
+		assert_eq!(output, ChatOutput::Message
+		{
+			kind: MessageKind::Say,
+			recipient: Some("bob".into()),
+			roll: None,
+			text: "meet me  later".into(),
+		});
+		
+		assert_eq!(errorCode(Role::Player, "/w bob"), ErrorCode::InvalidRequest);
+	}
+	
+	#[test]
+	fn rolls()
+	{
+		let output = run(Role::Player, "/gmroll 1d1+2 stealth").expect("Not a command").expect("Error running /gmroll");
+		match output
+		{
+			ChatOutput::Message { kind, roll, text, .. } => {
+				assert_eq!(kind, MessageKind::Secret);
+				assert_eq!(roll.map(|r| r.total), Some(3));
+				assert_eq!(text, "rolls 1d1+2: [1] + 2 = 3 (stealth)");
+			},
+			_ => panic!("Expected a message"),
+		}
+		
+		assert_eq!(errorCode(Role::Player, "/roll dice"), ErrorCode::InvalidRequest);
+	}
+	
+	#[test]
+	fn rejections()
+	{
+		assert_eq!(errorCode(Role::Player, "/dance"), ErrorCode::InvalidRequest);
+		assert_eq!(errorCode(Role::Spectator, "/me waves"), ErrorCode::PermissionDenied);
+		assert_eq!(errorCode(Role::Spectator, "/help"), ErrorCode::PermissionDenied);
+		assert_eq!(errorCode(Role::Player, "/help dance"), ErrorCode::InvalidRequest);
+		assert_eq!(errorCode(Role::Player, "/help me ooc"), ErrorCode::InvalidRequest);
+	}
+	
+	#[test]
+	fn help()
+	{
+		let output = run(Role::Player, "/help help").expect("Not a command").expect("Error running /help");
+		assert_eq!(output, ChatOutput::Reply("/help [command] - List the commands you can use, or describe one of them".into()));
+		
+		let output = run(Role::Player, "/help me").expect("Not a command").expect("Error running /help");
+		assert_eq!(output, ChatOutput::Reply("/me <action> - Describe what your character does".into()));
+		
+		match run(Role::Player, "/help")
+		{
+			Some(Ok(ChatOutput::Reply(text))) => {
+				assert_eq!(text.lines().count(), 6);
+				assert!(text.contains("/w <user> <text> - Whisper to a single user"));
+			},
+			_ => panic!("Expected a reply"),
+		}
+	}
+}
@@ -1,25 +1,97 @@
-use std::collections::HashMap;
-use ::anyhow::Result;
-use ::base64::prelude::*;
-use ::chrono::{NaiveDateTime, Utc};
-use ::fastwebsockets::{FragmentCollector, Frame, Payload, OpCode};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use ::anyhow::{Error, Result};
+use ::chrono::{DateTime, NaiveDateTime, TimeDelta, Utc};
+use ::fastwebsockets::{FragmentCollector, Frame, OpCode, Payload as WsPayload};
 use ::fastwebsockets::upgrade::UpgradeFut;
 use ::hyper::upgrade::Upgraded;
 use ::hyper_util::rt::TokioIo;
 use ::log::{info, error};
+use ::tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use ::tokio::time::{sleep_until, Instant};
 use ::tokio_util::sync::CancellationToken;
+use crate::getConfig;
+use crate::chat::export::{exportLog, logFileName, logTitle};
+use crate::chat::messageText;
+use crate::chat::log::{logEntries, LogEntry};
+use crate::chat::sanitize::{escapeBbcode, sanitize};
 use crate::data::dao;
-use crate::data::{NewMessage, NewUser, User};
-use crate::data::assets::{loadAsset, Asset, Image};
+use crate::data::{Channel, ChannelVisibility, DoorState, FogRegion, ImageAsset, MainChannel, Message, MessageKind,
+	MessageScope, MessageSearch, MessageSearchHit, NewCampaignRole, NewChannel, NewFogRegion, NewImageAsset, NewMessage,
+	NewMessageRevision, NewRoll, NewScene2D, NewToken, NewUser, NewWall, Role, Scene2D, Token, User, Wall, WallKind};
+use crate::data::assets::{loadAsset, relativeAssetPath, Asset, Image};
 use crate::net::user::getUserManager;
-use crate::util::parseDateTime;
+use crate::scene::grid::Grid;
+use crate::scene::sight::{SceneState, Viewer};
+use super::auth::{hashPassword, isValidUsername, verifyPassword, AuthenticationFailure};
+use super::binary::BinaryFrame;
+use super::chatcommands::{getChatCommands, ChatContext, ChatOutput};
 use super::commands::Commands;
-use super::payload::Command;
-use super::queue::getMessageQueue;
+use super::error::{describeError, ErrorCode, RequestError};
+use super::heartbeat::{Heartbeat, HeartbeatAction};
+use super::outbox::{buildCommand, getOutboxes};
+use super::payload::{AuthenticateFailData, AuthenticateSuccessData, BroadcastData, BroadcastGetData, ChannelCreateData,
+	ChannelData, ChannelIdData, ChannelListData, ChannelUpdateData, Command, CredentialsData, DirectMessageData,
+	EditData, EmptyData, ErrorData, ExportData, ExportResultData, FogChangeData, FogData, FogRegionData, FogRequestData,
+	FogResetData, GridData, HelloData, HelloFailData, HelloSuccessData, HistoryData, HistoryMessageData,
+	HistoryRequestData, MessageIdData, Payload, PermissionDeniedData, ResumeData, RevisionData, RevisionsData, RoomData,
+	RoomPresenceData, Scene2DBackgroundData, Scene2DCreateData, Scene2DData, Scene2DGridData, Scene2DIdData,
+	Scene2DListData, Scene2DRenameData, Scene2DRequestData, SearchData, SearchHitData, SearchResultData,
+	TokenCreateData, TokenData, TokenIdData, TokenListData, TokenListRequestData, TokenMoveData, UserRoleData,
+	WallCreateData, WallData, WallDoorData, WallIdData, WallListData, WallListRequestData};
+use super::permissions::{access, effectiveRole, isAllowed, isCampaignMember, Access};
+use super::protocol::{featureNames, negotiate, parseFeatures, serverFeatures,
+	Feature, HelloFailure, MinimumProtocolVersion, ProtocolVersion};
+use super::rooms::{getRooms, Room, RoomChanges, Rooms};
+use super::session::getSessionManager;
+
+/// Close code and reason sent after rejecting a client's hello.
+const ProtocolMismatchClose: (u16, &'static str) = (1002, "Protocol version mismatch");
+/// Number of messages returned by `BroadcastHistoryRequest` when no `limit` is given.
+const DefaultHistoryPageSize: u32 = 50;
+/// Maximum number of messages returned by a single `BroadcastHistoryRequest`.
+const MaxHistoryPageSize: u32 = 200;
+/// Number of hits returned by `BroadcastSearchRequest` when no `limit` is given.
+const DefaultSearchLimit: u32 = 20;
+/// Maximum number of hits returned by a single `BroadcastSearchRequest`.
+const MaxSearchLimit: u32 = 100;
+/// Maximum number of characters in a channel name.
+const MaxChannelNameLength: usize = 32;
+/// Maximum number of characters in a scene name.
+const MaxSceneNameLength: usize = 64;
+/// Maximum number of characters in the name of a grid's unit of distance.
+const MaxGridUnitLength: usize = 16;
+/// Maximum number of characters in a token name.
+const MaxTokenNameLength: usize = 64;
+/// Width and height in pixels of tokens created without a size.
+const DefaultTokenSize: i64 = 100;
+/// Maximum number of corners of a revealed or hidden fog region.
+const MaxFogPoints: usize = 256;
+/// The channels created in each campaign and the lobby, alongside the main chat used for in character chat.
+const DefaultChannels: &'static [(&'static str, ChannelVisibility)] = &[
+	("Out of character", ChannelVisibility::Public),
+	("Game masters", ChannelVisibility::GameMasters),
+];
 
 pub struct WebSocketClient
 {
+	/// The user's role within the current campaign, if any.
+	campaignRole: Option<Role>,
+	/// The close code and reason to send once pending commands are written.
+	closing: Option<(u16, &'static str)>,
+	/// Features negotiated during the hello exchange.
+	features: BTreeSet<Feature>,
+	heartbeat: Heartbeat,
 	id: i64,
+	/// Commands waiting to be written to the socket.
+	inbound: UnboundedReceiver<Command>,
+	/// Sending half of `inbound`, attached to the client id once authenticated.
+	outbound: UnboundedSender<Command>,
+	nextBinaryId: u64,
+	/// Protocol version negotiated during the hello exchange.
+	protocolVersion: Option<u32>,
+	/// Request id of the command being processed, echoed on every response to it.
+	requestId: Option<String>,
+	session: Option<String>,
 	user: Option<User>,
 	socket: FragmentCollector<TokioIo<Upgraded>>,
 }
@@ -32,9 +104,21 @@ impl WebSocketClient
 	pub async fn fromUpgradeFut(future: UpgradeFut) -> Result<Self>
 	{
 		let ws = future.await?;
+		let (outbound, inbound) = unbounded_channel();
+		
 		return Ok(Self
 		{
+			campaignRole: None,
+			closing: None,
+			features: BTreeSet::default(),
+			heartbeat: Heartbeat::new(&getConfig().network.heartbeat, Instant::now()),
 			id: -1,
+			inbound,
+			outbound,
+			nextBinaryId: 0,
+			protocolVersion: None,
+			requestId: None,
+			session: None,
 			user: None,
 			socket: FragmentCollector::new(ws),
 		});
@@ -42,8 +126,14 @@ impl WebSocketClient
 	
 	pub async fn start(&mut self, token: CancellationToken) -> Result<()>
 	{
-		self.queueCommandSimple(self.id, Commands::AuthenticateRequest)?;
-		self.sendQueuedMessages().await?;
+		let hello = HelloData
+		{
+			features: featureNames(&serverFeatures()),
+			minimumVersion: Some(MinimumProtocolVersion),
+			version: ProtocolVersion,
+		};
+		
+		self.queueCommand(Payload::HelloRequest(hello), None)?;
 		
 		loop
 		{
@@ -54,20 +144,99 @@ impl WebSocketClient
 				},
 				
 				result = self.socket.read_frame() => {
-					if let Ok(frame) = result
+					match result
 					{
-						if self.poll(frame).await?
-						{
+						Ok(frame) => {
+							self.heartbeat.received(Instant::now(), frame.opcode == OpCode::Pong);
+							
+							if self.poll(frame).await?
+							{
+								break;
+							}
+						},
+						
+						//The connection dropped without a Close frame
+						Err(e) => {
+							info!("Client {} read failed: {:?}", self.id, e);
+							self.disconnect()?;
 							break;
-						}
+						},
 					}
-				}
+				},
+				
+				Some(command) = self.inbound.recv() => {
+					if let Err(e) = self.sendCommands(command).await
+					{
+						info!("Client {} write failed: {:?}", self.id, e);
+						self.disconnect()?;
+						break;
+					}
+				},
+				
+				_ = sleepUntil(self.heartbeat.nextDeadline()) => {
+					if self.checkHeartbeat().await?
+					{
+						break;
+					}
+				},
 			}
 		}
 		
 		return Ok(());
 	}
 	
+	/**
+	Send a ping when one is due, or disconnect the client if it has stopped
+	responding or has been idle for too long.
+	
+	Returns `true` when the connection has been closed.
+	*/
+	async fn checkHeartbeat(&mut self) -> Result<bool>
+	{
+		match self.heartbeat.poll(Instant::now())
+		{
+			HeartbeatAction::None => {},
+			
+			HeartbeatAction::Ping => {
+				let ping = Frame::new(true, OpCode::Ping, None, WsPayload::Borrowed(&[]));
+				if let Err(e) = self.socket.write_frame(ping).await
+				{
+					info!("Client {} ping failed: {:?}", self.id, e);
+					self.disconnect()?;
+					return Ok(true);
+				}
+			},
+			
+			HeartbeatAction::PongTimedOut => {
+				info!("Client {} did not answer a ping in time", self.id);
+				self.disconnect()?;
+				return Ok(true);
+			},
+			
+			HeartbeatAction::IdleTimedOut => {
+				info!("Client {} was idle for too long", self.id);
+				//The client is likely still listening, so tell it why it is being dropped
+				_ = self.close(1001, "Idle timeout").await;
+				self.disconnect()?;
+				return Ok(true);
+			},
+		}
+		
+		return Ok(false);
+	}
+	
+	/**
+	The user's role, within the current campaign if there is one.
+	*/
+	fn role(&self) -> Role
+	{
+		return match &self.user
+		{
+			Some(u) => effectiveRole(u.role(), self.campaignRole),
+			None => Role::Spectator,
+		};
+	}
+	
 	fn username(&self) -> String
 	{
 		return match &self.user
@@ -82,318 +251,3423 @@ impl WebSocketClient
 		match frame.opcode
 		{
 			OpCode::Close => {
-				self.queueRemoveId();
-				
-				let name = match &self.user
-				{
-					None => String::default(),
-					Some(u) => u.name.to_owned(),
-				};
-				
-				info!("{} ({}) disconnected!", name, self.id);
-				self.queueBroadcast(format!("{} ({}) disconnected!", name, self.id))?;
-				
+				self.disconnect()?;
 				return Ok(true);
 			},
 			
 			OpCode::Text => self.processText(frame).await?,
-			
-			/*
-			OpCode::Binary => {
-				self.socket.write_frame(frame).await?;
-			},
-			*/
+			OpCode::Binary => self.processBinary()?,
 			
 			_ => {},
 		}
 		
-		self.sendQueuedMessages().await?;
+		if let Some((code, reason)) = self.closing
+		{
+			self.close(code, reason).await?;
+			return Ok(true);
+		}
 		
 		return Ok(false);
 	}
 	
+	/**
+	Answer a binary frame from the client with an `Error` command, as binary
+	data is only sent from the server to clients.
+	*/
+	fn processBinary(&self) -> Result<()>
+	{
+		return self.queueError(ErrorCode::InvalidRequest, "Binary frames are only sent by the server".into());
+	}
+	
+	/**
+	Parse and handle a command sent by the client.
+	
+	A command which cannot be parsed or handled is answered with an `Error`
+	command, leaving the connection open.
+	*/
 	async fn processText(&mut self, frame: Frame<'_>) -> Result<()>
 	{
-		match std::str::from_utf8(frame.payload.as_ref())
+		let json = match std::str::from_utf8(frame.payload.as_ref())
+		{
+			Ok(json) => json,
+			Err(e) => return self.queueError(ErrorCode::MalformedCommand, format!("Text frames must be UTF-8: {}", e)),
+		};
+		
+		let result = match Command::parse(json)
 		{
-			Ok(json) => {
-				let command: Command = serde_json::from_str(json)?;
-				match command.Type
+			Ok(command) => {
+				self.requestId = command.RequestId.to_owned();
+				self.processCommand(command).await
+			},
+			
+			Err(e) => {
+				self.requestId = readRequestId(json);
+				
+				//A hello which cannot be parsed is answered as a failed hello, so that older or newer clients learn why
+				if self.protocolVersion.is_none() && isHelloSend(json)
 				{
-					Commands::AuthenticateSend => self.handleAuthenticateSend(command).await?,
-					Commands::BroadcastGetRequest => self.handleBroadcastGetRequest(command).await?,
-					Commands::BroadcastRequest => self.handleBroadcastSend(command).await?,
-					Commands::Scene2DRequest => self.handleScene2dRequest(command).await?,
-					_ => {},
+					self.closing = Some(ProtocolMismatchClose);
+					self.queueHelloFail(HelloFailure::MissingVersion)
+				}
+				else
+				{
+					Err(RequestError { code: ErrorCode::MalformedCommand, message: format!("Failed to parse command: {}", e) }.into())
 				}
 			},
-			
-			Err(e) => error!("Failed to parse text packet: {:?}", e),
+		};
+		
+		if let Err(e) = &result
+		{
+			if e.downcast_ref::<RequestError>().is_none()
+			{
+				error!("Error processing command from client {}: {:?}", self.id, e);
+			}
 		}
 		
-		return Ok(());
+		let reported = match result
+		{
+			Ok(()) => Ok(()),
+			Err(e) => {
+				let (code, message) = describeError(&e);
+				self.queueError(code, message)
+			},
+		};
+		
+		self.requestId = None;
+		return reported;
 	}
 	
-	// -----
-	
-	async fn handleAuthenticateSend(&mut self, command: Command) -> Result<()>
+	async fn processCommand(&mut self, command: Command) -> Result<()>
 	{
-		println!("Authentication data received!");
+		let commandType = command.commandType();
 		
-		match command.Data.get("name")
+		if self.protocolVersion.is_none() && commandType != Commands::HelloSend
 		{
-			None => self.queueCommand(self.id, Commands::AuthenticateFail, None, None)?,
-			
-			Some(username) => {
-				self.user = self.userFindOrCreate(username).await;
-				
-				match &self.user
-				{
-					Some(user) => {
-						if let Some(newId) = self.userGetClientId(&user.name)
-						{
-							Some(id) => {
-								if let Some(newId) = self.userGetClientId(&id.to_string())
-								{
-									self.id = newId;
-									
-									let data: HashMap<String, String> = vec![
-										("clientId".to_string(), self.id.to_string()),
-										("username".to_string(), user.name.to_owned()),
-									].into_iter().collect();
-									
-									self.queueCommand(
-										self.id,
-										Commands::AuthenticateSuccess,
-										Some(data),
-										None
-									)?;
-									
-									self.queueBroadcast(format!("{} ({}) connected!", user.name, self.id))?;
-								}
-							},
-							
-							None => self.queueCommandSimple(self.id, Commands::AuthenticateFail)?,
-						}
-					},
-					
-					None => self.queueCommandSimple(self.id, Commands::AuthenticateFail)?,
-				}
-			}
+			return self.queueHelloFail(HelloFailure::HelloRequired);
+		}
+		
+		if !self.isAuthorized(commandType).await?
+		{
+			return self.queuePermissionDenied(commandType);
+		}
+		
+		match command.Payload
+		{
+			Payload::AuthenticateCreate(data) => self.handleAuthenticateCreate(data).await?,
+			Payload::AuthenticateResume(data) => self.handleAuthenticateResume(data).await?,
+			Payload::AuthenticateSend(data) => self.handleAuthenticateSend(data).await?,
+			Payload::BroadcastDeleteRequest(data) => self.handleBroadcastDeleteRequest(data).await?,
+			Payload::BroadcastEditRequest(data) => self.handleBroadcastEditRequest(data).await?,
+			Payload::BroadcastExportRequest(data) => self.handleBroadcastExportRequest(data).await?,
+			Payload::BroadcastGetRequest(data) => self.handleBroadcastGetRequest(data).await?,
+			Payload::BroadcastRequest(data) => self.handleBroadcastSend(data).await?,
+			Payload::BroadcastHistoryRequest(data) => self.handleBroadcastHistoryRequest(data).await?,
+			Payload::BroadcastRevisionsRequest(data) => self.handleBroadcastRevisionsRequest(data).await?,
+			Payload::BroadcastSearchRequest(data) => self.handleBroadcastSearchRequest(data).await?,
+			Payload::ChannelCreate(data) => self.handleChannelCreate(data).await?,
+			Payload::ChannelListRequest(_) => self.handleChannelListRequest().await?,
+			Payload::ChannelUpdate(data) => self.handleChannelUpdate(data).await?,
+			Payload::DirectMessageSend(data) => self.handleDirectMessage(data).await?,
+			Payload::FogHide(data) => self.changeFog(data, false).await?,
+			Payload::FogRequest(data) => self.handleFogRequest(data).await?,
+			Payload::FogReset(data) => self.handleFogReset(data).await?,
+			Payload::FogReveal(data) => self.changeFog(data, true).await?,
+			Payload::HelloSend(data) => self.handleHelloSend(data)?,
+			Payload::RoomJoin(data) => self.handleRoomJoin(data).await?,
+			Payload::RoomLeave(data) => self.handleRoomLeave(data).await?,
+			Payload::Scene2DBackgroundUpdate(data) => self.handleScene2dBackgroundUpdate(data).await?,
+			Payload::Scene2DCreate(data) => self.handleScene2dCreate(data).await?,
+			Payload::Scene2DDelete(data) => self.handleScene2dDelete(data).await?,
+			Payload::Scene2DGridUpdate(data) => self.handleScene2dGridUpdate(data).await?,
+			Payload::Scene2DListRequest(_) => self.handleScene2dListRequest().await?,
+			Payload::Scene2DRename(data) => self.handleScene2dRename(data).await?,
+			Payload::Scene2DRequest(data) => self.handleScene2dRequest(data).await?,
+			Payload::TokenCreate(data) => self.handleTokenCreate(data).await?,
+			Payload::TokenDelete(data) => self.handleTokenDelete(data).await?,
+			Payload::TokenListRequest(data) => self.handleTokenListRequest(data).await?,
+			Payload::TokenMove(data) => self.handleTokenMove(data).await?,
+			Payload::UserRoleUpdate(data) => self.handleUserRoleUpdate(data).await?,
+			Payload::WallCreate(data) => self.handleWallCreate(data).await?,
+			Payload::WallDelete(data) => self.handleWallDelete(data).await?,
+			Payload::WallDoorUpdate(data) => self.handleWallDoorUpdate(data).await?,
+			Payload::WallListRequest(data) => self.handleWallListRequest(data).await?,
+			_ => {},
 		}
 		
 		return Ok(());
 	}
 	
-	async fn handleBroadcastGetRequest(&self, command: Command) -> Result<()>
+	/**
+	Check whether this client may send `command`.
+	
+	The user is reloaded before checking commands which require a role so that
+	role changes take effect immediately.
+	*/
+	async fn isAuthorized(&mut self, command: Commands) -> Result<bool>
 	{
-		if let Some(start) = parseDateTime(command.Data.get("start"))
+		if let Access::Minimum(_) = access(command)
 		{
-			if let Some(end) = parseDateTime(command.Data.get("end"))
+			if let Some(user) = &self.user
 			{
-				self.queueExistingMessages(start.naive_utc(), end.naive_utc()).await?;
+				self.user = dao::userGet(user.id).await?;
+				self.campaignRole = self.loadCampaignRole().await?;
 			}
 		}
 		
-		return Ok(());
+		let role = self.user.as_ref()
+			.map(|u| effectiveRole(u.role(), self.campaignRole));
+		
+		return Ok(isAllowed(role, command));
 	}
 	
-	async fn handleBroadcastSend(&self, command: Command) -> Result<()>
+	// -----
+	
+	async fn handleAuthenticateCreate(&mut self, credentials: CredentialsData) -> Result<()>
 	{
-		if let Some(text) = command.Data.get("text")
+		if self.user.is_some()
 		{
-			//TODO: Implement input sanitation
-			if !text.is_empty()
-			{
-				if let Some(user) = &self.user
-				{
-					self.queueBroadcast(format!("{}: {}", self.username(), text))?;
-					
-					let newMessage = NewMessage
-					{
-						text: text.to_owned(),
-						timestamp: Utc::now().naive_utc(),
-						userId: Some(user.id),
-						..Default::default()
-					};
-					
-					_ = dao::messageCreate(newMessage).await?;
-				}
-			}
+			return self.queueAuthenticateFail(AuthenticationFailure::AlreadyAuthenticated);
 		}
 		
-		return Ok(());
+		let (username, password) = match readCredentials(credentials)
+		{
+			Some(credentials) => credentials,
+			None => return self.queueAuthenticateFail(AuthenticationFailure::MissingCredentials),
+		};
+		
+		if !isValidUsername(&username)
+		{
+			return self.queueAuthenticateFail(AuthenticationFailure::InvalidUsername);
+		}
+		
+		let existing = match dao::userFind(username.to_owned()).await
+		{
+			Ok(opt) => opt,
+			Err(e) => {
+				error!("Error searching the db for username '{}': {:?}", username, e);
+				return self.queueAuthenticateFail(AuthenticationFailure::ServerError);
+			},
+		};
+		
+		if existing.is_some()
+		{
+			return self.queueAuthenticateFail(AuthenticationFailure::UserExists);
+		}
+		
+		let passwordHash = match hashPasswordBlocking(password).await
+		{
+			Ok(hash) => hash,
+			Err(e) => {
+				error!("Error hashing password for username '{}': {:?}", username, e);
+				return self.queueAuthenticateFail(AuthenticationFailure::ServerError);
+			},
+		};
+		
+		//The first account on a new server belongs to its GM
+		let role = match dao::userCount().await?
+		{
+			0 => Role::GameMaster,
+			_ => Role::Player,
+		};
+		
+		let newUser = NewUser
+		{
+			name: username.to_owned(),
+			passwordHash: Some(passwordHash),
+			role: role.into(),
+			..Default::default()
+		};
+		
+		return match dao::userCreate(newUser).await
+		{
+			Ok(Some(user)) => self.completeAuthentication(user),
+			Ok(None) => self.queueAuthenticateFail(AuthenticationFailure::ServerError),
+			Err(e) => {
+				error!("Error saving user with username '{}': {:?}", username, e);
+				self.queueAuthenticateFail(AuthenticationFailure::ServerError)
+			},
+		};
 	}
 	
-	async fn handleScene2dRequest(&self, _command: Command) -> Result<()>
+	async fn handleAuthenticateResume(&mut self, data: ResumeData) -> Result<()>
 	{
-		let image: Image = loadAsset("BackgroundPlaceholder.png".into())?;
+		if self.user.is_some()
+		{
+			return self.queueAuthenticateFail(AuthenticationFailure::AlreadyAuthenticated);
+		}
 		
-		let data: HashMap<String, String> = vec![
-			("height".into(), "600".into()),
-			("width".into(), "900".into()),
-		].into_iter().collect();
+		let token = data.sessionToken;
+		if token.is_empty()
+		{
+			return self.queueAuthenticateFail(AuthenticationFailure::MissingCredentials);
+		}
 		
-		let binaryData: HashMap<String, String> = vec![
-			("background".into(), BASE64_STANDARD.encode(image.bytes()?)),
-		].into_iter().collect();
+		purgeExpiredSessions();
 		
-		self.queueCommand(
-			self.id,
-			Commands::Scene2DResponse,
-			Some(data),
-			Some(binaryData)
-		)?;
+		let resumed = match getSessionManager().lock()
+		{
+			Ok(manager) => manager.resume(&token, gracePeriod(), Utc::now()),
+			Err(e) => {
+				error!("Error resuming session: {:?}", e);
+				None
+			},
+		};
+		
+		let (newToken, session) = match resumed
+		{
+			Some(resumed) => resumed,
+			None => return self.queueAuthenticateFail(AuthenticationFailure::InvalidSession),
+		};
+		
+		let user = match dao::userGet(session.userId).await
+		{
+			Ok(Some(user)) => user,
+			Ok(None) => return self.queueAuthenticateFail(AuthenticationFailure::InvalidSession),
+			Err(e) => {
+				error!("Error retrieving user id '{}' for session: {:?}", session.userId, e);
+				return self.queueAuthenticateFail(AuthenticationFailure::ServerError);
+			},
+		};
+		
+		self.id = session.clientId;
+		self.session = Some(newToken.to_owned());
+		self.queueAuthenticateSuccess(&user.name, newToken)?;
+		self.attachOutbox();
+		
+		let username = user.name.to_owned();
+		self.user = Some(user);
+		
+		//Rooms are kept while the session can be resumed
+		if self.chatRoom().is_none()
+		{
+			let id = self.id;
+			self.changeRooms(|rooms| rooms.join(id, Room::Lobby))?;
+		}
+		
+		self.campaignRole = self.loadCampaignRole().await?;
+		self.queueBroadcast(format!("{} ({}) reconnected!", username, self.id))?;
 		
 		return Ok(());
 	}
 	
-	// -----
-	
-	fn queueBroadcast(&self, text: String) -> Result<()>
+	async fn handleAuthenticateSend(&mut self, credentials: CredentialsData) -> Result<()>
 	{
-		if let Ok(queue) = getMessageQueue().lock()
+		if self.user.is_some()
 		{
-			queue.queueBroadcast(text)?;
+			return self.queueAuthenticateFail(AuthenticationFailure::AlreadyAuthenticated);
 		}
 		
-		return Ok(());
+		let (username, password) = match readCredentials(credentials)
+		{
+			Some(credentials) => credentials,
+			None => return self.queueAuthenticateFail(AuthenticationFailure::MissingCredentials),
+		};
+		
+		let user = match dao::userFind(username.to_owned()).await
+		{
+			Ok(Some(user)) => user,
+			Ok(None) => return self.queueAuthenticateFail(AuthenticationFailure::InvalidCredentials),
+			Err(e) => {
+				error!("Error searching the db for username '{}': {:?}", username, e);
+				return self.queueAuthenticateFail(AuthenticationFailure::ServerError);
+			},
+		};
+		
+		let passwordHash = match &user.passwordHash
+		{
+			Some(hash) => hash.to_owned(),
+			None => return self.queueAuthenticateFail(AuthenticationFailure::PasswordNotSet),
+		};
+		
+		return match verifyPasswordBlocking(password, passwordHash).await
+		{
+			true => self.completeAuthentication(user),
+			false => self.queueAuthenticateFail(AuthenticationFailure::InvalidCredentials),
+		};
 	}
 	
-	fn queueCommandSimple(&self, id: i64, command: Commands) -> Result<()>
+	/**
+	Delete a message sent by this client's user, or any message if they are a
+	game master. The text is kept as a revision.
+	*/
+	async fn handleBroadcastDeleteRequest(&self, data: MessageIdData) -> Result<()>
 	{
-		return self.queueCommand(id, command, None, None);
+		let message = self.editableMessage(data.id).await?;
+		return self.reviseMessage(message, String::new(), true).await;
 	}
 	
-	fn queueCommand(&self,
-		id: i64,
-		command: Commands,
-		data: Option<HashMap<String, String>>,
-		binaryData: Option<HashMap<String, String>>
-	) -> Result<()>
+	/**
+	Replace the text of a message sent by this client's user, or of any message
+	if they are a game master. The previous text is kept as a revision.
+	*/
+	async fn handleBroadcastEditRequest(&self, data: EditData) -> Result<()>
 	{
-		if let Ok(queue) = getMessageQueue().lock()
+		let text = sanitizeChat(&data.text)?;
+		let message = self.editableMessage(data.id).await?;
+		
+		if !dao::rollFindByMessages(vec![message.id]).await?.is_empty()
 		{
-			queue.queueCommand(id, command, data, binaryData)?;
+			return Err(RequestError::invalid("Messages reporting dice rolls cannot be edited").into());
 		}
 		
-		return Ok(());
+		return self.reviseMessage(message, text, false).await;
 	}
 	
-	async fn queueExistingMessages(&self, start: NaiveDateTime, end: NaiveDateTime) -> Result<()>
+	async fn handleBroadcastExportRequest(&self, data: ExportData) -> Result<()>
 	{
-		let messages = dao::messageFindByDateRange(start, end).await?;
-		if !messages.is_empty()
+		let start = data.start.unwrap_or(DateTime::UNIX_EPOCH).naive_utc();
+		let end = data.end.unwrap_or(Utc::now()).naive_utc();
+		
+		let messages = dao::messageFindByDateRange(self.messageScope(data.channel).await?, start, end).await?;
+		let entries = logEntries(messages).await?;
+		
+		let campaignId = self.currentCampaign();
+		let exported = Utc::now();
+		let export = ExportResultData
 		{
-			let users = dao::userGetAll().await?;
-			
-			for m in messages
-			{
-				let userId = match m.userId
-				{
-					None => String::default(),
-					Some(userId) => match users.iter()
-						.find(|u| u.id == userId)
-					{
-						None => String::default(),
-						Some(user) => user.name.to_owned(),
-					},
-				};
-				
-				let data: HashMap<String, String> = vec![
-					(
-						"text".to_string(),
-						match userId.is_empty()
-						{
-							true => m.text.to_owned(),
-							false => format!("{}: {}", userId, m.text),
-						}
-					),
-				].into_iter().collect();
-				
-				self.queueCommand(
-					self.id.to_owned(),
-					Commands::BroadcastResponse,
-					Some(data),
-					None
-				)?;
-			}
-		}
+			content: exportLog(&logTitle(campaignId), &entries, data.format, exported)?,
+			fileName: logFileName(campaignId, data.format, exported),
+			format: data.format,
+		};
 		
-		return Ok(());
+		return self.queueCommand(Payload::BroadcastExportResponse(export), None);
+	}
+	
+	async fn handleBroadcastGetRequest(&self, data: BroadcastGetData) -> Result<()>
+	{
+		return self.queueExistingMessages(data.start.naive_utc(), data.end.naive_utc(), data.channel).await;
 	}
 	
-	fn queueRemoveId(&self)
+	async fn handleBroadcastHistoryRequest(&self, data: HistoryRequestData) -> Result<()>
 	{
-		if let Ok(messager) = getMessageQueue().lock()
+		let limit = data.limit.unwrap_or(DefaultHistoryPageSize);
+		if limit == 0
+		{
+			return Err(RequestError::invalid("limit must be greater than 0").into());
+		}
+		
+		let limit = limit.min(MaxHistoryPageSize);
+		
+		//Fetch one extra message to learn whether any remain beyond this page
+		let scope = self.messageScope(data.channel).await?;
+		let mut messages = dao::messageFindPage(scope, data.before, limit as i64 + 1).await?;
+		let more = messages.len() > limit as usize;
+		if more
 		{
-			let _ = messager.removeId(self.id);
+			messages.remove(0);
 		}
+		
+		let history = HistoryData
+		{
+			messages: historyMessages(messages).await?,
+			more,
+		};
+		
+		return self.queueCommand(Payload::BroadcastHistoryResponse(history), None);
+	}
+	
+	async fn handleBroadcastRevisionsRequest(&self, data: MessageIdData) -> Result<()>
+	{
+		let message = dao::messageFind(self.messageScope(None).await?, data.id).await?
+			.ok_or_else(|| RequestError::notFound(format!("No message with id {}", data.id)))?;
+		
+		let users = dao::userGetAll().await?;
+		let revisions = dao::messageRevisionFindByMessage(message.id).await?
+			.into_iter()
+			.map(|revision| RevisionData
+			{
+				text: displayText(&revision.text),
+				timestamp: revision.timestamp.and_utc(),
+				user: users.iter()
+					.find(|u| Some(u.id) == revision.userId)
+					.map(|u| u.name.to_owned()),
+			})
+			.collect();
+		
+		let message = match historyMessages(vec![message]).await?.pop()
+		{
+			Some(message) => message,
+			None => return Ok(()),
+		};
+		
+		return self.queueCommand(Payload::BroadcastRevisionsResponse(RevisionsData { message, revisions }), None);
 	}
 	
-	async fn sendQueuedMessages(&mut self) -> Result<()>
+	async fn handleBroadcastSearchRequest(&self, data: SearchData) -> Result<()>
 	{
-		let messages = match getMessageQueue().lock()
+		if data.terms.trim().is_empty()
 		{
-			Ok(queue) => queue.readMessages(self.id),
-			Err(e) => {
-				error!("Error reading messages for client id {}: {:?}", self.id, e);
-				vec![]
+			return Err(RequestError::invalid("terms must not be empty").into());
+		}
+		
+		let limit = data.limit.unwrap_or(DefaultSearchLimit);
+		if limit == 0
+		{
+			return Err(RequestError::invalid("limit must be greater than 0").into());
+		}
+		
+		let userId = match data.user
+		{
+			None => None,
+			Some(name) => match dao::userFind(name.to_owned()).await?
+			{
+				Some(user) => Some(user.id),
+				None => return Err(RequestError::notFound(format!("No user named {}", name)).into()),
 			},
 		};
 		
-		let json = serde_json::to_string(&messages)?;
-		self.socket.write_frame(
-			Frame::text(
-				Payload::Owned(json.into_bytes())
-			)
-		).await?;
+		let search = MessageSearch
+		{
+			end: data.end.map(|end| end.naive_utc()),
+			limit: limit.min(MaxSearchLimit) as i64,
+			start: data.start.map(|start| start.naive_utc()),
+			terms: data.terms,
+			userId,
+		};
 		
-		return Ok(());
+		let scope = self.messageScope(data.channel).await?;
+		let (messages, snippets): (Vec<_>, Vec<_>) = dao::messageSearch(scope, search).await?
+			.into_iter()
+			.map(|hit| {
+				let snippet = displaySnippet(&hit);
+				(hit.message, snippet)
+			})
+			.unzip();
+		
+		let hits = historyMessages(messages).await?
+			.into_iter()
+			.zip(snippets)
+			.map(|(message, snippet)| SearchHitData { message, snippet })
+			.collect();
+		
+		return self.queueCommand(Payload::BroadcastSearchResponse(SearchResultData { hits }), None);
 	}
 	
-	fn userGetClientId(&self, username: &String) -> Option<i64>
+	async fn handleBroadcastSend(&self, data: BroadcastData) -> Result<()>
 	{
-		let mut clientId = None;
-		if let Ok(userManager) = getUserManager().lock()
+		let text = sanitizeChat(&data.text)?;
+		let channel = self.sendableChannel(data.channel).await?;
+		let commands = getChatCommands();
+		let context = ChatContext
 		{
-			clientId = userManager.getClientId(username);
-		}
+			commands,
+			role: self.role(),
+		};
 		
-		return clientId;
+		let output = match commands.run(&context, &text)
+		{
+			Some(result) => result?,
+			None => ChatOutput::message(MessageKind::Say, text),
+		};
+		
+		return self.sendChat(output, channel).await;
 	}
 	
-	async fn userFindOrCreate(&self, username: &String) -> Option<User>
+	/**
+	Create a channel in this client's chat room, and announce it to everyone
+	who may see it.
+	*/
+	async fn handleChannelCreate(&self, data: ChannelCreateData) -> Result<()>
 	{
-		return match dao::userFind(username.clone()).await
+		let name = validName(&data.name, MaxChannelNameLength)?;
+		let existing = self.roomChannels().await?;
+		if existing.iter().any(|c| c.name.eq_ignore_ascii_case(&name))
 		{
-			Err(e) => {
-				error!("Error searching the db for username '{}': {:?}", username, e);
-				None
-			},
-			
-			Ok(opt) => match opt
-			{
-				None => {
-					let newUser = NewUser
+			return Err(RequestError::invalid(format!("A channel named {} already exists", name)).into());
+		}
+		
+		let newChannel = NewChannel
+		{
+			campaignId: self.currentCampaign(),
+			name,
+			visibility: data.visibility.into(),
+		};
+		
+		let memberIds = userIds(&data.members).await?;
+		if let Some(channel) = dao::channelCreate(newChannel, memberIds).await?
+		{
+			let audience = self.channelAudience(&channel).await?;
+			self.queueChannelUpdated(channel, audience, BTreeSet::default()).await?;
+		}
+		
+		return Ok(());
+	}
+	
+	async fn handleChannelListRequest(&self) -> Result<()>
+	{
+		let channels = self.visibleChannels().await?;
+		let data = ChannelListData { channels: channelData(channels).await? };
+		return self.queueCommand(Payload::ChannelListResponse(data), None);
+	}
+	
+	/**
+	Change the name, visibility or members of a channel in this client's chat
+	room. Clients which may no longer see the channel are told it was removed.
+	*/
+	async fn handleChannelUpdate(&self, data: ChannelUpdateData) -> Result<()>
+	{
+		let channels = self.roomChannels().await?;
+		let mut channel = channels.iter()
+			.find(|c| c.id == data.id)
+			.cloned()
+			.ok_or_else(|| RequestError::notFound(format!("No channel with id {}", data.id)))?;
+		
+		let previous = self.channelAudience(&channel).await?;
+		if let Some(name) = data.name
+		{
+			let name = validName(&name, MaxChannelNameLength)?;
+			if channels.iter().any(|c| c.id != channel.id && c.name.eq_ignore_ascii_case(&name))
+			{
+				return Err(RequestError::invalid(format!("A channel named {} already exists", name)).into());
+			}
+			
+			channel.name = name;
+		}
+		
+		if let Some(visibility) = data.visibility
+		{
+			channel.visibility = visibility.into();
+		}
+		
+		let memberIds = match data.members
+		{
+			Some(members) => Some(userIds(&members).await?),
+			None => None,
+		};
+		
+		if let Some(channel) = dao::channelUpdate(channel, memberIds).await?
+		{
+			let audience = self.channelAudience(&channel).await?;
+			self.queueChannelUpdated(channel, audience, previous).await?;
+		}
+		
+		return Ok(());
+	}
+	
+	/**
+	Whisper to a single user, delivering the message only to them and to this
+	client.
+	*/
+	async fn handleDirectMessage(&self, data: DirectMessageData) -> Result<()>
+	{
+		if data.recipient.is_empty()
+		{
+			return Err(RequestError::invalid("recipient must not be empty").into());
+		}
+		
+		let text = sanitizeChat(&data.text)?;
+		return self.sendChat(ChatOutput::Message
+		{
+			kind: MessageKind::Say,
+			recipient: Some(data.recipient),
+			roll: None,
+			text,
+		}, None).await;
+	}
+	
+	/**
+	Send the fog covering a scene as this client sees it, replacing any fog it
+	was sent before.
+	*/
+	async fn handleFogRequest(&self, data: FogRequestData) -> Result<()>
+	{
+		let id = data.scene.or(self.currentScene())
+			.ok_or(RequestError::invalid("scene is required when not viewing a scene"))?;
+		
+		let scene = self.roomScene(id).await?;
+		let viewer = self.viewer();
+		let regions = dao::fogRegionFindByScene(scene.id).await?
+			.into_iter()
+			.filter(|region| viewer.receives(region))
+			.collect();
+		
+		let fog = FogData
+		{
+			enabled: scene.fogEnabled,
+			regions: fogRegionData(regions).await?,
+			reset: true,
+			scene: scene.id,
+		};
+		
+		return self.queueCommand(Payload::FogUpdated(fog), None);
+	}
+	
+	/**
+	Remove every revealed and hidden area of a scene, leaving it covered in fog
+	or entirely revealed, and show the result to the scene's viewers.
+	*/
+	async fn handleFogReset(&self, data: FogResetData) -> Result<()>
+	{
+		let scene = self.roomScene(data.scene).await?;
+		let before = sceneState(&scene).await?;
+		let reset = match dao::scene2dResetFog(scene.id, data.enabled.unwrap_or(true)).await?
+		{
+			Some(reset) => reset,
+			None => return Ok(()),
+		};
+		
+		let viewers = self.sceneViewers(scene.id).await?;
+		for viewer in &viewers
+		{
+			let fog = FogData { enabled: reset.fogEnabled, regions: vec![], reset: true, scene: reset.id };
+			self.queueClient(viewer.clientId, Payload::FogUpdated(fog), None)?;
+		}
+		
+		let after = SceneState { fogEnabled: reset.fogEnabled, regions: vec![], ..before.clone() };
+		return self.queueSceneChanges(&viewers, &before, &after).await;
+	}
+	
+	/**
+	Agree on a protocol version and features with the client, then request
+	authentication. Incompatible clients are sent the reason and disconnected.
+	*/
+	fn handleHelloSend(&mut self, hello: HelloData) -> Result<()>
+	{
+		if self.protocolVersion.is_some()
+		{
+			return Ok(());
+		}
+		
+		let features = parseFeatures(&hello.features);
+		
+		match negotiate(hello.version, hello.minimumVersion, &features)
+		{
+			Err(reason) => {
+				info!("Rejecting client with protocol version {}", hello.version);
+				self.closing = Some(ProtocolMismatchClose);
+				self.queueHelloFail(reason)?;
+			},
+			
+			Ok(negotiated) => {
+				let success = HelloSuccessData
+				{
+					features: featureNames(&negotiated.features),
+					version: negotiated.version,
+				};
+				
+				self.protocolVersion = Some(negotiated.version);
+				self.features = negotiated.features;
+				
+				self.queueCommand(Payload::HelloSuccess(success), None)?;
+				self.queueCommand(Payload::AuthenticateRequest(EmptyData::default()), None)?;
+			},
+		}
+		
+		return Ok(());
+	}
+	
+	async fn handleRoomJoin(&mut self, data: RoomData) -> Result<()>
+	{
+		let room = data.room();
+		if let Room::Campaign(id) | Room::Scene(id) = room
+		{
+			if id <= 0
+			{
+				return Err(RequestError::invalid("id must be a positive number").into());
+			}
+		}
+		
+		match room
+		{
+			Room::Campaign(id) => self.requireCampaignMember(id).await?,
+			
+			Room::Scene(id) => {
+				if let Some(campaignId) = self.roomScene(id).await?.campaignId
+				{
+					self.requireCampaignMember(campaignId).await?;
+				}
+			},
+			
+			Room::Lobby => {},
+		}
+		
+		let id = self.id;
+		let changes = self.changeRooms(|rooms| rooms.join(id, room))?;
+		
+		//Already a member, so only the sender needs to hear about it
+		if changes.joined.is_empty()
+		{
+			let members = self.roomMembers(room);
+			self.queueCommand(Payload::RoomJoined(self.presence(room, &members)), None)?;
+		}
+		
+		self.campaignRole = self.loadCampaignRole().await?;
+		return Ok(());
+	}
+	
+	async fn handleRoomLeave(&mut self, data: RoomData) -> Result<()>
+	{
+		let room = data.room();
+		let id = self.id;
+		let changes = self.changeRooms(|rooms| rooms.leave(id, room))?;
+		
+		if changes.left.is_empty()
+		{
+			return Err(RequestError::invalid("Not a member of that room, or the room cannot be left").into());
+		}
+		
+		self.campaignRole = self.loadCampaignRole().await?;
+		return Ok(());
+	}
+	
+	async fn handleScene2dBackgroundUpdate(&self, data: Scene2DBackgroundData) -> Result<()>
+	{
+		let scene = self.roomScene(data.id).await?;
+		let background = registerImage(&data.background).await?;
+		
+		if let Some(scene) = dao::scene2dUpdateBackground(scene.id, background.id).await?
+		{
+			self.queueSceneUpdated(scene).await?;
+		}
+		
+		return Ok(());
+	}
+	
+	/**
+	Create a scene in this client's chat room, drawn over the image at the
+	given path within the assets directory.
+	*/
+	async fn handleScene2dCreate(&self, data: Scene2DCreateData) -> Result<()>
+	{
+		let name = validName(&data.name, MaxSceneNameLength)?;
+		let grid = validGrid(data.grid.unwrap_or_default())?;
+		let background = registerImage(&data.background).await?;
+		
+		let newScene = NewScene2D
+		{
+			name,
+			backgroundId: background.id,
+			campaignId: self.currentCampaign(),
+			fogEnabled: false,
+			gridCellSize: grid.cellSize,
+			gridDistance: grid.distance,
+			gridOriginX: grid.originX,
+			gridOriginY: grid.originY,
+			gridType: grid.kind.into(),
+			gridUnit: grid.unit,
+		};
+		
+		if let Some(scene) = dao::scene2dCreate(newScene).await?
+		{
+			self.queueSceneUpdated(scene).await?;
+		}
+		
+		return Ok(());
+	}
+	
+	/**
+	Delete a scene in this client's chat room. Its viewers are removed from the
+	scene's room.
+	*/
+	async fn handleScene2dDelete(&self, data: Scene2DIdData) -> Result<()>
+	{
+		let scene = self.roomScene(data.id).await?;
+		let audience = self.sceneAudience(scene.id).await?;
+		dao::scene2dDelete(scene.id).await?;
+		
+		if let Ok(mut rooms) = getRooms().lock()
+		{
+			for viewer in rooms.members(Room::Scene(scene.id))
+			{
+				rooms.leave(viewer, Room::Scene(scene.id));
+			}
+		}
+		
+		return self.queueClients(audience, Payload::Scene2DRemoved(Scene2DIdData { id: scene.id }), None);
+	}
+	
+	/**
+	Replace the grid of a scene in this client's chat room. Tokens already on
+	the scene stay where they are until they are next moved.
+	*/
+	async fn handleScene2dGridUpdate(&self, data: Scene2DGridData) -> Result<()>
+	{
+		let mut scene = self.roomScene(data.id).await?;
+		let grid = validGrid(data.grid)?;
+		
+		scene.gridCellSize = grid.cellSize;
+		scene.gridDistance = grid.distance;
+		scene.gridOriginX = grid.originX;
+		scene.gridOriginY = grid.originY;
+		scene.gridType = grid.kind.into();
+		scene.gridUnit = grid.unit;
+		
+		if let Some(scene) = dao::scene2dUpdateGrid(scene).await?
+		{
+			self.queueSceneUpdated(scene).await?;
+		}
+		
+		return Ok(());
+	}
+	
+	async fn handleScene2dListRequest(&self) -> Result<()>
+	{
+		let mut scenes = vec![];
+		for scene in dao::scene2dFindByCampaign(self.currentCampaign()).await?
+		{
+			let background = dao::imageAssetGet(scene.backgroundId).await?;
+			scenes.push(sceneData(scene, background.as_ref()));
+		}
+		
+		return self.queueCommand(Payload::Scene2DListResponse(Scene2DListData { scenes }), None);
+	}
+	
+	async fn handleScene2dRename(&self, data: Scene2DRenameData) -> Result<()>
+	{
+		let scene = self.roomScene(data.id).await?;
+		let name = validName(&data.name, MaxSceneNameLength)?;
+		
+		if let Some(scene) = dao::scene2dRename(scene.id, name).await?
+		{
+			self.queueSceneUpdated(scene).await?;
+		}
+		
+		return Ok(());
+	}
+	
+	/**
+	Send a scene along with its background image. Without an `id`, the scene
+	this client is viewing is sent, or else the first scene of its chat room.
+	*/
+	async fn handleScene2dRequest(&self, data: Scene2DRequestData) -> Result<()>
+	{
+		let scene = match data.id.or(self.currentScene())
+		{
+			Some(id) => self.roomScene(id).await?,
+			None => dao::scene2dFindByCampaign(self.currentCampaign()).await?
+				.into_iter()
+				.next()
+				.ok_or(RequestError::notFound("No scenes have been created"))?,
+		};
+		
+		let background = dao::imageAssetGet(scene.backgroundId).await?
+			.ok_or_else(|| RequestError::notFound(format!("Scene {} has no background image", scene.id)))?;
+		
+		let image: Image = loadAsset(background.path.to_owned())?;
+		
+		let binaryData: HashMap<String, Vec<u8>> = vec![
+			("background".into(), image.bytes()?),
+		].into_iter().collect();
+		
+		self.queueCommand(
+			Payload::Scene2DResponse(sceneData(scene, Some(&background))),
+			Some(binaryData)
+		)?;
+		
+		return Ok(());
+	}
+	
+	/**
+	Place a token on a scene in this client's chat room, and show it to the
+	scene's viewers.
+	*/
+	async fn handleTokenCreate(&self, data: TokenCreateData) -> Result<()>
+	{
+		let scene = self.roomScene(data.scene).await?;
+		let name = validName(&data.name, MaxTokenNameLength)?;
+		let width = validTokenSize(data.width)?;
+		let height = validTokenSize(data.height)?;
+		let (x, y) = tokenPosition(&scene, data.x, data.y).await?;
+		
+		let imageId = match data.image
+		{
+			Some(path) => Some(registerImage(&path).await?.id),
+			None => None,
+		};
+		
+		let ownerId = match data.owner
+		{
+			Some(owner) => Some(dao::userFind(owner.to_owned()).await?
+				.ok_or_else(|| RequestError::notFound(format!("No user named {}", owner)))?
+				.id),
+			None => None,
+		};
+		
+		let before = sceneState(&scene).await?;
+		let newToken = NewToken
+		{
+			height,
+			hidden: data.hidden,
+			imageId,
+			name,
+			ownerId,
+			sceneId: scene.id,
+			width,
+			x,
+			y,
+		};
+		
+		if let Some(token) = dao::tokenCreate(newToken).await?
+		{
+			let mut after = before.clone();
+			after.tokens.push(token);
+			
+			let viewers = self.sceneViewers(scene.id).await?;
+			self.queueSceneChanges(&viewers, &before, &after).await?;
+		}
+		
+		return Ok(());
+	}
+	
+	async fn handleTokenDelete(&self, data: TokenIdData) -> Result<()>
+	{
+		let token = self.sceneToken(data.id).await?;
+		let scene = self.roomScene(token.sceneId).await?;
+		let before = sceneState(&scene).await?;
+		dao::tokenDelete(token.id).await?;
+		
+		let mut after = before.clone();
+		after.tokens.retain(|t| t.id != token.id);
+		
+		let viewers = self.sceneViewers(scene.id).await?;
+		return self.queueSceneChanges(&viewers, &before, &after).await;
+	}
+	
+	/**
+	Send the tokens this client may see on a scene through its fog and past its
+	walls, with their images attached as binary data under their paths.
+	*/
+	async fn handleTokenListRequest(&self, data: TokenListRequestData) -> Result<()>
+	{
+		let id = data.scene.or(self.currentScene())
+			.ok_or(RequestError::invalid("scene is required when not viewing a scene"))?;
+		
+		let scene = self.roomScene(id).await?;
+		let state = sceneState(&scene).await?;
+		let sight = self.viewer().sight(&state);
+		
+		let visible = state.tokens.into_iter()
+			.filter(|t| sight.sees(t))
+			.collect();
+		
+		let tokens = tokenData(visible).await?;
+		let images = tokenImages(&tokens)?;
+		return self.queueCommand(Payload::TokenListResponse(TokenListData { scene: scene.id, tokens }), Some(images));
+	}
+	
+	/**
+	Move a token after checking that this client may move it and that its new
+	position lies on the scene, then show the move to the scene's viewers. The
+	token is snapped to the centre of the grid cell it is dropped in.
+	*/
+	async fn handleTokenMove(&self, data: TokenMoveData) -> Result<()>
+	{
+		let token = self.sceneToken(data.id).await?;
+		let viewer = self.viewer();
+		
+		if !token.isMovableBy(viewer.gameMaster, viewer.userId)
+		{
+			return Err(RequestError::invalid("Only the token's owner or a game master may move it").into());
+		}
+		
+		let scene = self.roomScene(token.sceneId).await?;
+		let (x, y) = tokenPosition(&scene, data.x, data.y).await?;
+		let before = sceneState(&scene).await?;
+		
+		if let Some(moved) = dao::tokenMove(token.id, x, y).await?
+		{
+			let mut after = before.clone();
+			for t in after.tokens.iter_mut().filter(|t| t.id == moved.id)
+			{
+				*t = moved.clone();
+			}
+			
+			let viewers = self.sceneViewers(scene.id).await?;
+			self.queueSceneChanges(&viewers, &before, &after).await?;
+		}
+		
+		return Ok(());
+	}
+	
+	/**
+	Change a user's server wide role, or their role within a campaign, telling
+	both the sender and the user.
+	
+	Server wide roles may only be changed by server wide game masters, and
+	campaign roles by game masters of that campaign, regardless of the campaign
+	the sender is in.
+	
+	The user's own client picks the new role up with its next command, as
+	`isAuthorized` reloads the user from the database.
+	*/
+	async fn handleUserRoleUpdate(&self, data: UserRoleData) -> Result<()>
+	{
+		let sender = match &self.user
+		{
+			Some(user) => user,
+			None => return Ok(()),
+		};
+		
+		if data.campaign.is_some_and(|id| id <= 0)
+		{
+			return Err(RequestError::invalid("campaign must be a positive number").into());
+		}
+		
+		//The sender's role in their current campaign grants no authority elsewhere
+		let campaignRole = match data.campaign
+		{
+			Some(campaignId) => dao::campaignRoleFind(campaignId, sender.id).await?.map(|r| r.role()),
+			None => None,
+		};
+		
+		if effectiveRole(sender.role(), campaignRole) != Role::GameMaster
+		{
+			let message = match data.campaign
+			{
+				Some(campaignId) => format!("Only game masters of campaign {} may change its roles", campaignId),
+				None => "Only server game masters may change server wide roles".to_string(),
+			};
+			
+			return Err(RequestError::denied(message).into());
+		}
+		
+		let user = dao::userFind(data.name.to_owned()).await?
+			.ok_or(RequestError::notFound(format!("No user named '{}'", data.name)))?;
+		
+		let role = match data.campaign
+		{
+			Some(campaignId) => {
+				let newCampaignRole = NewCampaignRole
+				{
+					campaignId,
+					role: data.role.into(),
+					userId: user.id,
+				};
+				
+				dao::campaignRoleSet(newCampaignRole).await?.map(|r| r.role())
+			},
+			
+			None => dao::userUpdateRole(user.id, data.role).await?.map(|u| u.role()),
+		};
+		
+		if let Some(role) = role
+		{
+			let target = match getUserManager().lock()
+			{
+				Ok(manager) => manager.getClientId(&user.name),
+				Err(_) => None,
+			};
+			
+			let payload = Payload::UserRoleUpdated(UserRoleData
+			{
+				campaign: data.campaign,
+				name: user.name,
+				role,
+			});
+			
+			self.queueCommand(payload.clone(), None)?;
+			if let Some(clientId) = target.filter(|id| *id != self.id)
+			{
+				self.queueClient(clientId, payload, None)?;
+			}
+		}
+		
+		return Ok(());
+	}
+	
+	/**
+	Add a wall to a scene in this client's chat room, and show it to the
+	scene's viewers along with what it now hides from them.
+	*/
+	async fn handleWallCreate(&self, data: WallCreateData) -> Result<()>
+	{
+		let scene = self.roomScene(data.scene).await?;
+		validWallEnds(&scene, data.start, data.end).await?;
+		
+		let doorState = match data.kind
+		{
+			WallKind::Door => data.door.unwrap_or_default(),
+			_ => DoorState::default(),
+		};
+		
+		let newWall = NewWall
+		{
+			doorState: doorState.into(),
+			endX: data.end[0],
+			endY: data.end[1],
+			kind: data.kind.into(),
+			sceneId: scene.id,
+			startX: data.start[0],
+			startY: data.start[1],
+		};
+		
+		let before = sceneState(&scene).await?;
+		if let Some(wall) = dao::wallCreate(newWall).await?
+		{
+			let mut after = before.clone();
+			after.walls.push(wall);
+			
+			self.queueSceneChanges(&self.sceneViewers(scene.id).await?, &before, &after).await?;
+		}
+		
+		return Ok(());
+	}
+	
+	async fn handleWallDelete(&self, data: WallIdData) -> Result<()>
+	{
+		let wall = self.sceneWall(data.id).await?;
+		let scene = self.roomScene(wall.sceneId).await?;
+		let before = sceneState(&scene).await?;
+		dao::wallDelete(wall.id).await?;
+		
+		let mut after = before.clone();
+		after.walls.retain(|w| w.id != wall.id);
+		
+		return self.queueSceneChanges(&self.sceneViewers(scene.id).await?, &before, &after).await;
+	}
+	
+	/**
+	Open, close or lock a door, and show it to the scene's viewers along with
+	what it now hides or reveals. Players may open and close doors which are not
+	locked, but only game masters may lock and unlock them.
+	*/
+	async fn handleWallDoorUpdate(&self, data: WallDoorData) -> Result<()>
+	{
+		let wall = self.sceneWall(data.id).await?;
+		if wall.kind() != WallKind::Door
+		{
+			return Err(RequestError::invalid(format!("Wall {} is not a door", wall.id)).into());
+		}
+		
+		let locking = wall.doorState() == DoorState::Locked || data.state == DoorState::Locked;
+		if locking && !self.viewer().gameMaster
+		{
+			return Err(RequestError::invalid("Only a game master may lock or unlock a door").into());
+		}
+		
+		let scene = self.roomScene(wall.sceneId).await?;
+		let before = sceneState(&scene).await?;
+		
+		if let Some(updated) = dao::wallUpdateDoor(wall.id, data.state).await?
+		{
+			let mut after = before.clone();
+			for w in after.walls.iter_mut().filter(|w| w.id == updated.id)
+			{
+				*w = updated.clone();
+			}
+			
+			self.queueSceneChanges(&self.sceneViewers(scene.id).await?, &before, &after).await?;
+		}
+		
+		return Ok(());
+	}
+	
+	/**
+	Send the walls of a scene which this client may see, including its doors.
+	*/
+	async fn handleWallListRequest(&self, data: WallListRequestData) -> Result<()>
+	{
+		let id = data.scene.or(self.currentScene())
+			.ok_or(RequestError::invalid("scene is required when not viewing a scene"))?;
+		
+		let scene = self.roomScene(id).await?;
+		let state = sceneState(&scene).await?;
+		let sight = self.viewer().sight(&state);
+		
+		let walls = state.walls.into_iter()
+			.filter(|w| sight.seesWall(w))
+			.map(wallData)
+			.collect();
+		
+		return self.queueCommand(Payload::WallListResponse(WallListData { scene: scene.id, walls }), None);
+	}
+	
+	// -----
+	
+	/**
+	Assign the client id for a successfully authenticated `user` and notify
+	everyone of the new connection.
+	*/
+	fn completeAuthentication(&mut self, user: User) -> Result<()>
+	{
+		match self.userGetClientId(&user.name)
+		{
+			None => self.queueAuthenticateFail(AuthenticationFailure::ServerError)?,
+			
+			Some(newId) => {
+				self.id = newId;
+				
+				purgeExpiredSessions();
+				let token = match getSessionManager().lock()
+				{
+					Ok(manager) => manager.create(self.id, user.id),
+					Err(e) => {
+						error!("Error creating session for client id {}: {:?}", self.id, e);
+						return self.queueAuthenticateFail(AuthenticationFailure::ServerError);
+					},
+				};
+				
+				self.session = Some(token.to_owned());
+				self.queueAuthenticateSuccess(&user.name, token)?;
+				self.attachOutbox();
+				
+				let username = user.name.to_owned();
+				self.user = Some(user);
+				self.campaignRole = None;
+				
+				//A new session starts in the lobby, leaving any rooms held by an earlier session
+				let id = self.id;
+				self.changeRooms(|rooms| {
+					let left = rooms.leaveAll(id);
+					let mut changes = rooms.join(id, Room::Lobby);
+					changes.left = left;
+					changes
+				})?;
+				
+				self.queueBroadcast(format!("{} ({}) connected!", username, self.id))?;
+			},
+		}
+		
+		return Ok(());
+	}
+	
+	/**
+	Route commands addressed to this client's id to this connection.
+	*/
+	fn attachOutbox(&self)
+	{
+		if let Ok(mut outboxes) = getOutboxes().lock()
+		{
+			outboxes.attach(self.id, self.outbound.clone());
+		}
+	}
+	
+	/**
+	Apply a change of room membership to this client and notify the members of
+	each room joined or left.
+	*/
+	fn changeRooms(&self, change: impl FnOnce(&mut Rooms) -> RoomChanges) -> Result<RoomChanges>
+	{
+		let changes = match getRooms().lock()
+		{
+			Ok(mut rooms) => change(&mut rooms),
+			Err(e) => {
+				error!("Error changing rooms for client id {}: {:?}", self.id, e);
+				return Err(Error::msg("Rooms are unavailable"));
+			},
+		};
+		
+		for room in changes.left.iter()
+		{
+			let members = self.roomMembers(*room);
+			let payload = Payload::RoomLeft(self.presence(*room, &members));
+			
+			//The client is no longer a member, but still needs to hear that it left
+			self.queueCommand(payload.clone(), None)?;
+			self.queueRoom(*room, payload)?;
+		}
+		
+		for room in changes.joined.iter()
+		{
+			let members = self.roomMembers(*room);
+			self.queueRoom(*room, Payload::RoomJoined(self.presence(*room, &members)))?;
+		}
+		
+		return Ok(changes);
+	}
+	
+	/**
+	The chat room this client's broadcasts are sent to.
+	*/
+	fn chatRoom(&self) -> Option<Room>
+	{
+		return getRooms().lock().ok()?.chatRoom(self.id);
+	}
+	
+	/**
+	The campaign this client has joined, if any.
+	*/
+	fn currentCampaign(&self) -> Option<i32>
+	{
+		return match self.chatRoom()
+		{
+			Some(Room::Campaign(id)) => Some(id),
+			_ => None,
+		};
+	}
+	
+	/**
+	The scene this client is viewing, if any.
+	*/
+	fn currentScene(&self) -> Option<i32>
+	{
+		return getRooms().lock().ok()?
+			.roomsOf(self.id)
+			.into_iter()
+			.find_map(|room| match room
+			{
+				Room::Scene(id) => Some(id),
+				_ => None,
+			});
+	}
+	
+	/**
+	Read the user's role within the current campaign.
+	*/
+	async fn loadCampaignRole(&self) -> Result<Option<Role>>
+	{
+		let (user, campaignId) = match (&self.user, self.currentCampaign())
+		{
+			(Some(user), Some(campaignId)) => (user, campaignId),
+			_ => return Ok(None),
+		};
+		
+		let campaignRole = dao::campaignRoleFind(campaignId, user.id).await?;
+		return Ok(campaignRole.map(|r| r.role()));
+	}
+	
+	fn presence(&self, room: Room, members: &BTreeSet<i64>) -> RoomPresenceData
+	{
+		let data = RoomData::from(room);
+		
+		return RoomPresenceData
+		{
+			clientId: self.id,
+			id: data.id,
+			kind: data.kind,
+			members: memberNames(members),
+			username: self.username(),
+		};
+	}
+	
+	fn roomMembers(&self, room: Room) -> BTreeSet<i64>
+	{
+		return match getRooms().lock()
+		{
+			Ok(rooms) => rooms.members(room),
+			Err(_) => BTreeSet::default(),
+		};
+	}
+	
+	/**
+	Release this client's connection state after its socket closes.
+	
+	An authenticated client's session and queued messages are retained for the
+	configured grace period so that it may resume.
+	*/
+	fn disconnect(&mut self) -> Result<()>
+	{
+		if let Some(token) = &self.session
+		{
+			match getSessionManager().lock()
+			{
+				Ok(manager) => manager.disconnect(token, Utc::now()),
+				Err(e) => error!("Error disconnecting session for client id {}: {:?}", self.id, e),
+			}
+			
+			let mut unsent = vec![];
+			while let Ok(command) = self.inbound.try_recv()
+			{
+				unsent.push(command);
+			}
+			
+			if let Ok(mut outboxes) = getOutboxes().lock()
+			{
+				outboxes.detach(self.id, &self.outbound, unsent);
+			}
+		}
+		
+		let name = match &self.user
+		{
+			None => String::default(),
+			Some(u) => u.name.to_owned(),
+		};
+		
+		info!("{} ({}) disconnected!", name, self.id);
+		self.queueBroadcast(format!("{} ({}) disconnected!", name, self.id))?;
+		
+		return Ok(());
+	}
+	
+	fn queueAuthenticateFail(&self, reason: AuthenticationFailure) -> Result<()>
+	{
+		let payload = Payload::AuthenticateFail(AuthenticateFailData { reason });
+		return self.queueCommand(payload, None);
+	}
+	
+	fn queueAuthenticateSuccess(&self, username: &String, sessionToken: String) -> Result<()>
+	{
+		let data = AuthenticateSuccessData
+		{
+			clientId: self.id,
+			sessionToken,
+			username: username.to_owned(),
+		};
+		
+		return self.queueCommand(
+			Payload::AuthenticateSuccess(data),
+			None
+		);
+	}
+	
+	fn queueError(&self, code: ErrorCode, message: String) -> Result<()>
+	{
+		let payload = Payload::Error(ErrorData { code, message });
+		return self.queueCommand(payload, None);
+	}
+	
+	fn queueHelloFail(&self, reason: HelloFailure) -> Result<()>
+	{
+		let message = match reason
+		{
+			HelloFailure::MissingVersion => "HelloSend must include a numeric protocol version".to_string(),
+			HelloFailure::HelloRequired => "HelloSend must be sent before any other command".to_string(),
+			HelloFailure::IncompatibleVersion => format!(
+				"This server supports protocol versions {} through {}",
+				MinimumProtocolVersion,
+				ProtocolVersion
+			),
+		};
+		
+		let data = HelloFailData
+		{
+			message,
+			minimumVersion: MinimumProtocolVersion,
+			reason,
+			version: ProtocolVersion,
+		};
+		
+		return self.queueCommand(Payload::HelloFail(data), None);
+	}
+	
+	fn queuePermissionDenied(&self, command: Commands) -> Result<()>
+	{
+		let role = match access(command)
+		{
+			Access::Minimum(role) => Some(role),
+			_ => None,
+		};
+		
+		let payload = Payload::PermissionDenied(PermissionDeniedData { command, role });
+		return self.queueCommand(payload, None);
+	}
+	
+	/**
+	Save the message in `output` to `channel`, or the main chat when `None`, and
+	deliver it to its audience, or show a reply to this client alone. Whispers
+	are always sent to the main chat.
+	*/
+	async fn sendChat(&self, output: ChatOutput, channel: Option<Channel>) -> Result<()>
+	{
+		let (kind, recipient, roll, text) = match output
+		{
+			ChatOutput::Message { kind, recipient, roll, text } => (kind, recipient, roll, text),
+			ChatOutput::Reply(text) => {
+				let data = BroadcastData { text, ..Default::default() };
+				return self.queueCommand(Payload::BroadcastResponse(data), None);
+			},
+		};
+		
+		let sender = match &self.user
+		{
+			Some(user) => user,
+			None => return Ok(()),
+		};
+		
+		let recipient = match recipient
+		{
+			None => None,
+			Some(name) => Some(dao::userFind(name.to_owned()).await?
+				.ok_or_else(|| RequestError::notFound(format!("No user named {}", name)))?),
+		};
+		
+		let channel = match recipient
+		{
+			Some(_) => None,
+			None => channel,
+		};
+		
+		let newMessage = NewMessage
+		{
+			campaignId: self.currentCampaign(),
+			channelId: channel.as_ref().map(|c| c.id),
+			kind: kind.into(),
+			recipientId: recipient.as_ref().map(|u| u.id),
+			text,
+			timestamp: Utc::now().naive_utc(),
+			userId: Some(sender.id),
+		};
+		
+		let message = match &roll
+		{
+			None => dao::messageCreate(newMessage).await?,
+			Some(roll) => {
+				let newRoll = NewRoll
+				{
+					expression: roll.expression.to_owned(),
+					messageId: 0,
+					result: serde_json::to_string(roll)?,
+					total: roll.total,
+				};
+				
+				dao::messageCreateWithRoll(newMessage, newRoll).await?
+					.map(|(message, _)| message)
+			},
+		};
+		
+		let message = match message
+		{
+			Some(message) => message,
+			None => return Ok(()),
+		};
+		
+		let recipientName = recipient.map(|u| u.name);
+		let payload = Payload::BroadcastResponse(BroadcastData
+		{
+			channel: message.channelId,
+			id: Some(message.id),
+			text: displayText(&messageText(kind, &sender.name, recipientName.as_deref(), &message.text)),
+			recipient: recipientName.to_owned(),
+			roll,
+			..Default::default()
+		});
+		
+		return self.queueChat(kind, None, recipientName.as_deref(), channel.as_ref(), payload).await;
+	}
+	
+	/**
+	Queue `payload`, about a message of `kind` sent to `channel`, for everyone
+	who may see that message, including this client.
+	
+	A whisper is seen only by its `sender` and `recipient`, and a `Secret`
+	message by its `sender` and the game masters. Messages sent to a channel are
+	seen by those who may see the channel, and others by the whole chat room.
+	`sender` may be `None` when it is this client's user.
+	*/
+	async fn queueChat(&self,
+		kind: MessageKind,
+		sender: Option<&str>,
+		recipient: Option<&str>,
+		channel: Option<&Channel>,
+		payload: Payload
+	) -> Result<()>
+	{
+		let mut audience = match (recipient, kind, channel)
+		{
+			(Some(_), _, _) => BTreeSet::default(),
+			(None, MessageKind::Secret, _) => self.gameMasters().await?,
+			(None, _, Some(channel)) => self.channelAudience(channel).await?,
+			(None, _, None) => return match self.chatRoom()
+			{
+				Some(room) => self.queueRoom(room, payload),
+				None => Ok(()),
+			},
+		};
+		
+		if let Ok(manager) = getUserManager().lock()
+		{
+			for name in sender.into_iter().chain(recipient)
+			{
+				audience.extend(manager.getClientId(&name.to_string()));
+			}
+		}
+		
+		audience.remove(&self.id);
+		self.queueCommand(payload.clone(), None)?;
+		
+		if let Ok(mut outboxes) = getOutboxes().lock()
+		{
+			outboxes.queueMany(&audience, payload);
+		}
+		
+		return Ok(());
+	}
+	
+	/**
+	Find the message `id` in this client's chat room, provided it has not been
+	deleted and this client may change it.
+	*/
+	async fn editableMessage(&self, id: i32) -> Result<Message>
+	{
+		let message = dao::messageFind(self.messageScope(None).await?, id).await?
+			.filter(|m| !m.deleted)
+			.ok_or_else(|| RequestError::notFound(format!("No message with id {}", id)))?;
+		
+		let isAuthor = self.user.as_ref()
+			.is_some_and(|user| message.userId == Some(user.id));
+		
+		if !isAuthor && self.role() < Role::GameMaster
+		{
+			return Err(RequestError::invalid("Only the sender or a game master may change a message").into());
+		}
+		
+		return Ok(message);
+	}
+	
+	/**
+	Replace the text of `message`, keeping the current text as a revision, and
+	send the result to everyone who may see the message.
+	*/
+	async fn reviseMessage(&self, message: Message, text: String, deleted: bool) -> Result<()>
+	{
+		let revision = NewMessageRevision
+		{
+			messageId: message.id,
+			text: message.text,
+			timestamp: Utc::now().naive_utc(),
+			userId: self.user.as_ref().map(|u| u.id),
+		};
+		
+		let revised = match dao::messageRevise(text, deleted, revision).await?
+		{
+			Some(message) => message,
+			None => return Ok(()),
+		};
+		
+		let entry = match logEntries(vec![revised]).await?.pop()
+		{
+			Some(entry) => entry,
+			None => return Ok(()),
+		};
+		
+		let channel = match entry.channelId
+		{
+			Some(id) => dao::channelGet(id).await?,
+			None => None,
+		};
+		
+		let (kind, sender, recipient) = (entry.kind, entry.sender.to_owned(), entry.recipient.to_owned());
+		let payload = Payload::BroadcastUpdated(historyMessage(entry));
+		return self.queueChat(kind, sender.as_deref(), recipient.as_deref(), channel.as_ref(), payload).await;
+	}
+	
+	/**
+	The channels of this client's campaign or lobby. The default channels are
+	created the first time they are needed.
+	*/
+	async fn roomChannels(&self) -> Result<Vec<Channel>>
+	{
+		let campaignId = self.currentCampaign();
+		let channels = dao::channelFindByCampaign(campaignId).await?;
+		if !channels.is_empty()
+		{
+			return Ok(channels);
+		}
+		
+		for (name, visibility) in DefaultChannels
+		{
+			let newChannel = NewChannel
+			{
+				campaignId,
+				name: name.to_string(),
+				visibility: (*visibility).into(),
+			};
+			
+			dao::channelCreate(newChannel, vec![]).await?;
+		}
+		
+		return dao::channelFindByCampaign(campaignId).await;
+	}
+	
+	/**
+	The channels of this client's campaign or lobby which it may see.
+	*/
+	async fn visibleChannels(&self) -> Result<Vec<Channel>>
+	{
+		let channels = self.roomChannels().await?;
+		let userId = self.user.as_ref().map(|u| u.id);
+		let members = dao::channelMemberFindByChannels(channels.iter().map(|c| c.id).collect()).await?;
+		let gameMaster = self.role() >= Role::GameMaster;
+		
+		return Ok(channels.into_iter()
+			.filter(|channel| {
+				let member = members.iter()
+					.any(|m| m.channelId == channel.id && Some(m.userId) == userId);
+				
+				channel.isVisible(gameMaster, member)
+			})
+			.collect());
+	}
+	
+	/**
+	Find the channel `id` for this client to send a message to, or `None` for
+	the main chat.
+	*/
+	async fn sendableChannel(&self, id: Option<i32>) -> Result<Option<Channel>>
+	{
+		let id = match id
+		{
+			None | Some(MainChannel) => return Ok(None),
+			Some(id) => id,
+		};
+		
+		return match self.visibleChannels().await?.into_iter().find(|c| c.id == id)
+		{
+			Some(channel) => Ok(Some(channel)),
+			None => Err(RequestError::notFound(format!("No channel with id {}", id)).into()),
+		};
+	}
+	
+	/**
+	The client ids of everyone in this client's chat room who may see `channel`.
+	*/
+	async fn channelAudience(&self, channel: &Channel) -> Result<BTreeSet<i64>>
+	{
+		let room = match self.chatRoom()
+		{
+			Some(room) => room,
+			None => return Ok(BTreeSet::default()),
+		};
+		
+		return Ok(match channel.visibility()
+		{
+			ChannelVisibility::Public => self.roomMembers(room),
+			ChannelVisibility::GameMasters => self.gameMasters().await?,
+			ChannelVisibility::Members => {
+				let mut audience = self.gameMasters().await?;
+				let members = dao::channelMemberFindByChannels(vec![channel.id]).await?;
+				let roomMembers = self.roomMembers(room);
+				
+				for member in members
+				{
+					let name = match dao::userGet(member.userId).await?
 					{
-						name: username.to_owned(),
-						..Default::default()
+						Some(user) => user.name,
+						None => continue,
 					};
 					
-					match dao::userCreate(newUser).await
+					if let Ok(manager) = getUserManager().lock()
+					{
+						audience.extend(manager.getClientId(&name)
+							.filter(|id| roomMembers.contains(id)));
+					}
+				}
+				
+				audience
+			},
+		});
+	}
+	
+	/**
+	Send `channel` as `ChannelUpdated` to this client and `audience`, and tell
+	the clients in `previous` which are no longer in `audience` that it was
+	removed.
+	*/
+	async fn queueChannelUpdated(&self, channel: Channel, mut audience: BTreeSet<i64>, previous: BTreeSet<i64>) -> Result<()>
+	{
+		let removed = previous.difference(&audience)
+			.copied()
+			.filter(|id| *id != self.id)
+			.collect::<BTreeSet<_>>();
+		
+		let id = channel.id;
+		let payload = match channelData(vec![channel]).await?.pop()
+		{
+			Some(data) => Payload::ChannelUpdated(data),
+			None => return Ok(()),
+		};
+		
+		audience.remove(&self.id);
+		self.queueCommand(payload.clone(), None)?;
+		
+		if let Ok(mut outboxes) = getOutboxes().lock()
+		{
+			outboxes.queueMany(&audience, payload);
+			outboxes.queueMany(&removed, Payload::ChannelRemoved(ChannelIdData { id }));
+		}
+		
+		return Ok(());
+	}
+	
+	/**
+	Refuse unless this client's user is a member of the campaign `campaignId`.
+	*/
+	async fn requireCampaignMember(&self, campaignId: i32) -> Result<()>
+	{
+		let user = match &self.user
+		{
+			Some(user) => user,
+			None => return Err(RequestError::denied("Not logged in").into()),
+		};
+		
+		let campaignRole = dao::campaignRoleFind(campaignId, user.id).await?
+			.map(|r| r.role());
+		
+		if !isCampaignMember(user.role(), campaignRole)
+		{
+			return Err(RequestError::denied(format!("Not a member of campaign {}", campaignId)).into());
+		}
+		
+		return Ok(());
+	}
+	
+	/**
+	Find the scene `id`, which must belong to this client's chat room.
+	*/
+	async fn roomScene(&self, id: i32) -> Result<Scene2D>
+	{
+		return match dao::scene2dGet(id).await?
+		{
+			Some(scene) if scene.campaignId == self.currentCampaign() => Ok(scene),
+			_ => Err(RequestError::notFound(format!("No scene with id {}", id)).into()),
+		};
+	}
+	
+	/**
+	The client ids of everyone told about changes to the scene `id`: the game
+	masters in this client's chat room and the scene's viewers.
+	*/
+	async fn sceneAudience(&self, id: i32) -> Result<BTreeSet<i64>>
+	{
+		let mut audience = self.gameMasters().await?;
+		audience.extend(self.roomMembers(Room::Scene(id)));
+		return Ok(audience);
+	}
+	
+	/**
+	Send `scene` as `Scene2DUpdated` to this client and everyone in its
+	audience. Viewers request the scene again to receive a new background.
+	*/
+	async fn queueSceneUpdated(&self, scene: Scene2D) -> Result<()>
+	{
+		let audience = self.sceneAudience(scene.id).await?;
+		let background = dao::imageAssetGet(scene.backgroundId).await?;
+		return self.queueClients(audience, Payload::Scene2DUpdated(sceneData(scene, background.as_ref())), None);
+	}
+	
+	/**
+	This client as a viewer of scenes, for checking which tokens it may see and
+	move.
+	*/
+	fn viewer(&self) -> Viewer
+	{
+		let userId = self.user.as_ref()
+			.map(|u| u.id)
+			.unwrap_or_default();
+		
+		return Viewer { clientId: self.id, gameMaster: self.role() >= Role::GameMaster, userId };
+	}
+	
+	/**
+	This client and the other viewers of the scene `id`.
+	*/
+	async fn sceneViewers(&self, id: i32) -> Result<Vec<Viewer>>
+	{
+		let mut viewers = vec![self.viewer()];
+		for clientId in self.roomMembers(Room::Scene(id))
+		{
+			if clientId == self.id
+			{
+				continue;
+			}
+			
+			if let Some((user, role)) = self.clientRole(clientId).await?
+			{
+				viewers.push(Viewer { clientId, gameMaster: role >= Role::GameMaster, userId: user.id });
+			}
+		}
+		
+		return Ok(viewers);
+	}
+	
+	/**
+	Find the token `id`, which must be on a scene in this client's chat room
+	and visible to this client.
+	*/
+	async fn sceneToken(&self, id: i32) -> Result<Token>
+	{
+		let notFound = || RequestError::notFound(format!("No token with id {}", id));
+		let token = dao::tokenGet(id).await?
+			.ok_or_else(notFound)?;
+		
+		let viewer = self.viewer();
+		if !token.isVisibleTo(viewer.gameMaster, viewer.userId) || self.roomScene(token.sceneId).await.is_err()
+		{
+			return Err(notFound().into());
+		}
+		
+		return Ok(token);
+	}
+	
+	/**
+	Reveal, or hide again when not `revealed`, a polygon of a scene for every
+	player or for the players named in `data`. Each of the scene's viewers is
+	sent the new regions which apply to it and the tokens it now sees or no
+	longer sees.
+	*/
+	async fn changeFog(&self, data: FogChangeData, revealed: bool) -> Result<()>
+	{
+		let scene = self.roomScene(data.scene).await?;
+		if !(3..=MaxFogPoints).contains(&data.points.len())
+		{
+			let message = format!("A fog region must have between 3 and {} points", MaxFogPoints);
+			return Err(RequestError::invalid(message).into());
+		}
+		
+		let users = match data.users.is_empty()
+		{
+			true => vec![None],
+			false => userIds(&data.users).await?.into_iter().map(Some).collect(),
+		};
+		
+		let points = serde_json::to_string(&data.points)?;
+		let newRegions = users.into_iter()
+			.map(|userId| NewFogRegion { points: points.to_owned(), revealed, sceneId: scene.id, userId })
+			.collect();
+		
+		let before = sceneState(&scene).await?;
+		let created = dao::fogRegionCreate(newRegions).await?;
+		let mut after = before.clone();
+		after.regions.extend(created.iter().cloned());
+		
+		let viewers = self.sceneViewers(scene.id).await?;
+		for viewer in &viewers
+		{
+			let regions: Vec<FogRegion> = created.iter()
+				.filter(|region| viewer.receives(region))
+				.cloned()
+				.collect();
+			
+			if !regions.is_empty()
+			{
+				let fog = FogData
+				{
+					enabled: scene.fogEnabled,
+					regions: fogRegionData(regions).await?,
+					reset: false,
+					scene: scene.id,
+				};
+				
+				self.queueClient(viewer.clientId, Payload::FogUpdated(fog), None)?;
+			}
+		}
+		
+		return self.queueSceneChanges(&viewers, &before, &after).await;
+	}
+	
+	/**
+	Find the wall `id`, which must be on a scene in this client's chat room
+	and visible to this client.
+	*/
+	async fn sceneWall(&self, id: i32) -> Result<Wall>
+	{
+		let notFound = || RequestError::notFound(format!("No wall with id {}", id));
+		let wall = dao::wallGet(id).await?
+			.ok_or_else(notFound)?;
+		
+		let scene = match self.roomScene(wall.sceneId).await
+		{
+			Ok(scene) => scene,
+			Err(_) => return Err(notFound().into()),
+		};
+		
+		let viewer = self.viewer();
+		if !viewer.gameMaster && !viewer.sight(&sceneState(&scene).await?).seesWall(&wall)
+		{
+			return Err(notFound().into());
+		}
+		
+		return Ok(wall);
+	}
+	
+	/**
+	Tell each of `viewers` about the tokens and walls it sees differently after
+	a scene changed from `before` to `after`. Tokens which came into sight, or
+	moved while in sight, are sent as `TokenUpdated`, with their images when
+	they were not seen before, and tokens which went out of sight as
+	`TokenRemoved`. Walls are sent the same way as `WallUpdated` and
+	`WallRemoved`.
+	*/
+	async fn queueSceneChanges(&self, viewers: &[Viewer], before: &SceneState, after: &SceneState) -> Result<()>
+	{
+		for viewer in viewers
+		{
+			let sightBefore = viewer.sight(before);
+			let sightAfter = viewer.sight(after);
+			
+			let mut seen: BTreeMap<i32, &Token> = before.tokens.iter()
+				.filter(|t| sightBefore.sees(t))
+				.map(|t| (t.id, t))
+				.collect();
+			
+			let mut shown = vec![];
+			let mut newlySeen = BTreeSet::new();
+			for token in after.tokens.iter().filter(|t| sightAfter.sees(t))
+			{
+				match seen.remove(&token.id)
+				{
+					Some(previous) if previous == token => {},
+					Some(_) => shown.push(token.clone()),
+					None => {
+						newlySeen.insert(token.id);
+						shown.push(token.clone());
+					},
+				}
+			}
+			
+			for id in seen.into_keys()
+			{
+				self.queueClient(viewer.clientId, Payload::TokenRemoved(TokenIdData { id }), None)?;
+			}
+			
+			for data in tokenData(shown).await?
+			{
+				let images = match newlySeen.contains(&data.id)
+				{
+					true => Some(tokenImages(std::slice::from_ref(&data))?),
+					false => None,
+				};
+				
+				self.queueClient(viewer.clientId, Payload::TokenUpdated(data), images)?;
+			}
+			
+			let mut seenWalls: BTreeMap<i32, &Wall> = before.walls.iter()
+				.filter(|w| sightBefore.seesWall(w))
+				.map(|w| (w.id, w))
+				.collect();
+			
+			for wall in after.walls.iter().filter(|w| sightAfter.seesWall(w))
+			{
+				if seenWalls.remove(&wall.id).is_none_or(|previous| previous != wall)
+				{
+					self.queueClient(viewer.clientId, Payload::WallUpdated(wallData(wall.clone())), None)?;
+				}
+			}
+			
+			for id in seenWalls.into_keys()
+			{
+				self.queueClient(viewer.clientId, Payload::WallRemoved(WallIdData { id }), None)?;
+			}
+		}
+		
+		return Ok(());
+	}
+	
+	/**
+	The client ids of the game masters in this client's chat room.
+	*/
+	async fn gameMasters(&self) -> Result<BTreeSet<i64>>
+	{
+		let mut gameMasters = BTreeSet::default();
+		let room = match self.chatRoom()
+		{
+			Some(room) => room,
+			None => return Ok(gameMasters),
+		};
+		
+		for id in self.roomMembers(room)
+		{
+			if let Some((_, role)) = self.clientRole(id).await?
+			{
+				if role >= Role::GameMaster
+				{
+					gameMasters.insert(id);
+				}
+			}
+		}
+		
+		return Ok(gameMasters);
+	}
+	
+	/**
+	The user connected as the client `clientId`, and their role within this
+	client's campaign.
+	*/
+	async fn clientRole(&self, clientId: i64) -> Result<Option<(User, Role)>>
+	{
+		let username = match getUserManager().lock()
+		{
+			Ok(manager) => manager.getUserId(clientId),
+			Err(_) => None,
+		};
+		
+		let user = match username
+		{
+			Some(name) => dao::userFind(name).await?,
+			None => None,
+		};
+		
+		let user = match user
+		{
+			Some(user) => user,
+			None => return Ok(None),
+		};
+		
+		let campaignRole = match self.currentCampaign()
+		{
+			Some(campaignId) => dao::campaignRoleFind(campaignId, user.id).await?
+				.map(|r| r.role()),
+			None => None,
+		};
+		
+		let role = effectiveRole(user.role(), campaignRole);
+		return Ok(Some((user, role)));
+	}
+	
+	/**
+	Queue a chat message for every member of this client's chat room.
+	*/
+	fn queueBroadcast(&self, text: String) -> Result<()>
+	{
+		if let Some(room) = self.chatRoom()
+		{
+			self.queueRoom(room, Payload::BroadcastResponse(BroadcastData { text, ..Default::default() }))?;
+		}
+		
+		return Ok(());
+	}
+	
+	/**
+	Queue a command for every member of `room`. This client's own copy is
+	tagged with the request id of the command being processed.
+	*/
+	fn queueRoom(&self, room: Room, payload: Payload) -> Result<()>
+	{
+		let mut members = self.roomMembers(room);
+		
+		if members.remove(&self.id)
+		{
+			self.queueCommand(payload.clone(), None)?;
+		}
+		
+		if let Ok(mut outboxes) = getOutboxes().lock()
+		{
+			outboxes.queueMany(&members, payload);
+		}
+		
+		return Ok(());
+	}
+	
+	/**
+	Queue a command for this client and every client in `audience`. This
+	client's own copy is tagged with the request id of the command being
+	processed.
+	*/
+	fn queueClients(&self,
+		mut audience: BTreeSet<i64>,
+		payload: Payload,
+		binaryData: Option<HashMap<String, Vec<u8>>>
+	) -> Result<()>
+	{
+		audience.remove(&self.id);
+		self.queueCommand(payload.clone(), binaryData.clone())?;
+		
+		if let Ok(mut outboxes) = getOutboxes().lock()
+		{
+			for id in audience
+			{
+				outboxes.queueCommand(id, payload.clone(), binaryData.clone());
+			}
+		}
+		
+		return Ok(());
+	}
+	
+	/**
+	Queue a command for the client `clientId`. When that is this client, the
+	command is tagged with the request id of the command being processed.
+	*/
+	fn queueClient(&self,
+		clientId: i64,
+		payload: Payload,
+		binaryData: Option<HashMap<String, Vec<u8>>>
+	) -> Result<()>
+	{
+		if clientId == self.id
+		{
+			return self.queueCommand(payload, binaryData);
+		}
+		
+		if let Ok(mut outboxes) = getOutboxes().lock()
+		{
+			outboxes.queueCommand(clientId, payload, binaryData);
+		}
+		
+		return Ok(());
+	}
+	
+	/**
+	Queue a command for transmission to this client, tagged with the request id
+	of the command being processed.
+	*/
+	fn queueCommand(&self,
+		payload: Payload,
+		binaryData: Option<HashMap<String, Vec<u8>>>
+	) -> Result<()>
+	{
+		let mut command = buildCommand(self.id, payload, binaryData);
+		command.RequestId = self.requestId.to_owned();
+		
+		self.outbound.send(command)?;
+		return Ok(());
+	}
+	
+	async fn queueExistingMessages(&self, start: NaiveDateTime, end: NaiveDateTime, channel: Option<i32>) -> Result<()>
+	{
+		let messages = dao::messageFindByDateRange(self.messageScope(channel).await?, start, end).await?;
+		
+		for m in historyMessages(messages).await?
+		{
+			let data = BroadcastData
+			{
+				channel: m.channel,
+				deleted: m.deleted,
+				edited: m.edited,
+				id: Some(m.id),
+				recipient: m.recipient,
+				roll: m.roll,
+				text: m.text,
+			};
+			
+			self.queueCommand(
+				Payload::BroadcastResponse(data),
+				None
+			)?;
+		}
+		
+		return Ok(());
+	}
+	
+	/**
+	The messages this client may read, in its current chat room, narrowed to
+	`channel` when it is given.
+	*/
+	async fn messageScope(&self, channel: Option<i32>) -> Result<MessageScope>
+	{
+		let scope = MessageScope
+		{
+			campaignId: self.currentCampaign(),
+			channels: self.visibleChannels().await?
+				.into_iter()
+				.map(|c| c.id)
+				.collect(),
+			gameMaster: self.role() >= Role::GameMaster,
+			mainChat: true,
+			userId: self.user.as_ref().map(|u| u.id),
+		};
+		
+		return Ok(match channel
+		{
+			Some(channel) => scope.onlyChannel(channel),
+			None => scope,
+		});
+	}
+	
+	/**
+	Write any commands still waiting for this client, then close the socket
+	with `code` and `reason`.
+	*/
+	async fn close(&mut self, code: u16, reason: &str) -> Result<()>
+	{
+		if let Ok(command) = self.inbound.try_recv()
+		{
+			self.sendCommands(command).await?;
+		}
+		
+		self.socket.write_frame(Frame::close(code, reason.as_bytes())).await?;
+		return Ok(());
+	}
+	
+	/**
+	Write `command`, along with any other commands already waiting, to the
+	socket as a single JSON array.
+	
+	Binary data is written first as binary frames if the client accepts them,
+	otherwise it is base64 encoded into each command's `BinaryData`.
+	*/
+	async fn sendCommands(&mut self, command: Command) -> Result<()>
+	{
+		let mut commands = vec![command];
+		while let Ok(next) = self.inbound.try_recv()
+		{
+			commands.push(next);
+		}
+		
+		let binaryFrames = self.features.contains(&Feature::BinaryFrames);
+		let typedPayloads = self.features.contains(&Feature::TypedPayloads);
+		
+		let mut wireCommands = Vec::with_capacity(commands.len());
+		for command in commands
+		{
+			let mut binaryId = None;
+			
+			if binaryFrames && !command.Binary.is_empty()
+			{
+				self.nextBinaryId += 1;
+				let id = self.nextBinaryId;
+				
+				for (key, data) in command.Binary.iter()
+				{
+					let bytes = BinaryFrame { id, key: key.to_owned(), data: data.to_owned() }.encode()?;
+					self.socket.write_frame(
+						Frame::binary(
+							WsPayload::Owned(bytes)
+						)
+					).await?;
+				}
+				
+				binaryId = Some(id);
+			}
+			
+			wireCommands.push(command.toWire(typedPayloads, binaryId)?);
+		}
+		
+		let json = serde_json::to_string(&wireCommands)?;
+		self.socket.write_frame(
+			Frame::text(
+				WsPayload::Owned(json.into_bytes())
+			)
+		).await?;
+		
+		return Ok(());
+	}
+	
+	fn userGetClientId(&self, username: &String) -> Option<i64>
+	{
+		let mut clientId = None;
+		if let Ok(userManager) = getUserManager().lock()
+		{
+			clientId = userManager.getClientId(username);
+		}
+		
+		return clientId;
+	}
+}
+
+/**
+Wait until `deadline`, or forever if there is none.
+*/
+async fn sleepUntil(deadline: Option<Instant>)
+{
+	match deadline
+	{
+		Some(deadline) => sleep_until(deadline).await,
+		None => std::future::pending().await,
+	}
+}
+
+fn gracePeriod() -> TimeDelta
+{
+	return TimeDelta::seconds(getConfig().session.gracePeriod as i64);
+}
+
+/**
+Sanitize chat text sent by a client, reporting any violation back to it.
+*/
+fn sanitizeChat(text: &str) -> Result<String>
+{
+	return sanitize(text, &getConfig().chat)
+		.map_err(|e| RequestError::invalid(e.to_string()).into());
+}
+
+/**
+Discard expired sessions along with any messages queued for their clients and
+their room memberships.
+
+Called periodically by the server, and whenever a client authenticates.
+*/
+pub fn purgeExpiredSessions()
+{
+	let expired = match getSessionManager().lock()
+	{
+		Ok(manager) => manager.purgeExpired(gracePeriod(), Utc::now()),
+		Err(e) => {
+			error!("Error purging expired sessions: {:?}", e);
+			vec![]
+		},
+	};
+	
+	if let Ok(mut outboxes) = getOutboxes().lock()
+	{
+		for id in expired.iter()
+		{
+			outboxes.remove(*id);
+		}
+	}
+	
+	if let Ok(mut rooms) = getRooms().lock()
+	{
+		for id in expired
+		{
+			rooms.leaveAll(id);
+		}
+	}
+}
+
+/**
+The usernames of the given client ids.
+*/
+fn memberNames(ids: &BTreeSet<i64>) -> Vec<String>
+{
+	return match getUserManager().lock()
+	{
+		Ok(manager) => ids.iter()
+			.filter_map(|id| manager.getUserId(*id))
+			.collect(),
+		Err(_) => vec![],
+	};
+}
+
+/**
+Convert messages for sending to clients, with the text shown for each and any
+dice rolled for them.
+*/
+async fn historyMessages(messages: Vec<Message>) -> Result<Vec<HistoryMessageData>>
+{
+	let history = logEntries(messages).await?
+		.into_iter()
+		.map(historyMessage)
+		.collect();
+	
+	return Ok(history);
+}
+
+/**
+Describe `channels`, with the usernames of their members, for sending to
+clients.
+*/
+async fn channelData(channels: Vec<Channel>) -> Result<Vec<ChannelData>>
+{
+	let users = dao::userGetAll().await?;
+	let members = dao::channelMemberFindByChannels(channels.iter().map(|c| c.id).collect()).await?;
+	
+	return Ok(channels.into_iter()
+		.map(|channel| ChannelData
+		{
+			id: channel.id,
+			members: members.iter()
+				.filter(|m| m.channelId == channel.id)
+				.filter_map(|m| users.iter().find(|u| u.id == m.userId))
+				.map(|u| u.name.to_owned())
+				.collect(),
+			visibility: channel.visibility(),
+			name: channel.name,
+		})
+		.collect());
+}
+
+/**
+Describe `scene` for sending to clients, with the path and size of its
+`background` image.
+*/
+fn sceneData(scene: Scene2D, background: Option<&ImageAsset>) -> Scene2DData
+{
+	return Scene2DData
+	{
+		background: background.map(|b| b.path.to_owned()).unwrap_or_default(),
+		grid: GridData
+		{
+			cellSize: scene.gridCellSize,
+			distance: scene.gridDistance,
+			kind: scene.gridType(),
+			originX: scene.gridOriginX,
+			originY: scene.gridOriginY,
+			unit: scene.gridUnit,
+		},
+		height: background.map(|b| b.height).unwrap_or_default(),
+		id: scene.id,
+		name: scene.name,
+		width: background.map(|b| b.width).unwrap_or_default(),
+	};
+}
+
+/**
+Describe `tokens`, with the paths of their images and the usernames of their
+owners, for sending to clients.
+*/
+async fn tokenData(tokens: Vec<Token>) -> Result<Vec<TokenData>>
+{
+	let mut data = vec![];
+	for token in tokens
+	{
+		let image = match token.imageId
+		{
+			Some(id) => dao::imageAssetGet(id).await?.map(|i| i.path),
+			None => None,
+		};
+		
+		let owner = match token.ownerId
+		{
+			Some(id) => dao::userGet(id).await?.map(|u| u.name),
+			None => None,
+		};
+		
+		data.push(TokenData
+		{
+			height: token.height,
+			hidden: token.hidden,
+			id: token.id,
+			image,
+			name: token.name,
+			owner,
+			scene: token.sceneId,
+			width: token.width,
+			x: token.x,
+			y: token.y,
+		});
+	}
+	
+	return Ok(data);
+}
+
+/**
+Load everything on `scene` which decides what its viewers may see.
+*/
+async fn sceneState(scene: &Scene2D) -> Result<SceneState>
+{
+	let background = dao::imageAssetGet(scene.backgroundId).await?;
+	
+	return Ok(SceneState
+	{
+		fogEnabled: scene.fogEnabled,
+		height: background.as_ref().map(|b| b.height).unwrap_or_default(),
+		regions: dao::fogRegionFindByScene(scene.id).await?,
+		tokens: dao::tokenFindByScene(scene.id).await?,
+		walls: dao::wallFindByScene(scene.id).await?,
+		width: background.as_ref().map(|b| b.width).unwrap_or_default(),
+	});
+}
+
+/**
+Describe fog `regions`, with the usernames of the players they apply to, for
+sending to clients.
+*/
+async fn fogRegionData(regions: Vec<FogRegion>) -> Result<Vec<FogRegionData>>
+{
+	let mut data = vec![];
+	for region in regions
+	{
+		let user = match region.userId
+		{
+			Some(id) => dao::userGet(id).await?.map(|u| u.name),
+			None => None,
+		};
+		
+		data.push(FogRegionData
+		{
+			id: region.id,
+			points: region.points(),
+			revealed: region.revealed,
+			user,
+		});
+	}
+	
+	return Ok(data);
+}
+
+/**
+Load the images of `tokens`, keyed by their paths, for attaching as binary
+data.
+*/
+fn tokenImages(tokens: &[TokenData]) -> Result<HashMap<String, Vec<u8>>>
+{
+	let mut images = HashMap::new();
+	for path in tokens.iter().filter_map(|t| t.image.as_ref())
+	{
+		if !images.contains_key(path)
+		{
+			let image: Image = loadAsset(path.to_owned())?;
+			images.insert(path.to_owned(), image.bytes()?);
+		}
+	}
+	
+	return Ok(images);
+}
+
+/**
+Check a token's width or height, using the default size when `None`.
+*/
+fn validTokenSize(size: Option<i64>) -> Result<i64>
+{
+	return match size.unwrap_or(DefaultTokenSize)
+	{
+		size if size > 0 => Ok(size),
+		_ => Err(RequestError::invalid("Token width and height must be positive").into()),
+	};
+}
+
+/**
+Snap the pixel `(x, y)` to the grid of `scene`, checking that the result lies
+within the scene's background.
+*/
+async fn tokenPosition(scene: &Scene2D, x: i64, y: i64) -> Result<(i64, i64)>
+{
+	let (x, y) = Grid::from(scene).snap(x, y);
+	let background = dao::imageAssetGet(scene.backgroundId).await?;
+	let inside = background.is_some_and(|b| (0..=b.width).contains(&x) && (0..=b.height).contains(&y));
+	
+	return match inside
+	{
+		true => Ok((x, y)),
+		false => Err(RequestError::invalid(format!("({}, {}) is outside the scene", x, y)).into()),
+	};
+}
+
+/**
+Check the settings of a scene's grid, trimming its unit.
+*/
+fn validGrid(mut grid: GridData) -> Result<GridData>
+{
+	if grid.cellSize <= 0
+	{
+		return Err(RequestError::invalid("cellSize must be positive").into());
+	}
+	
+	if !grid.distance.is_finite() || grid.distance <= 0.0
+	{
+		return Err(RequestError::invalid("distance must be positive").into());
+	}
+	
+	grid.unit = validName(&grid.unit, MaxGridUnitLength)?;
+	return Ok(grid);
+}
+
+/**
+Register the image at `path`, relative to the assets directory, as an image
+asset, reading its dimensions from the file. An image registered before is
+reused, with its dimensions refreshed in case the file was replaced.
+*/
+async fn registerImage(path: &str) -> Result<ImageAsset>
+{
+	let path = relativeAssetPath(path)
+		.ok_or(RequestError::invalid("background must be a path within the assets directory"))?;
+	
+	let image: Image = loadAsset(path.to_owned())
+		.map_err(|_| RequestError::notFound(format!("No image at {}", path)))?;
+	
+	let (width, height) = image.dimensions()
+		.map_err(|_| RequestError::invalid(format!("{} is not a supported image", path)))?;
+	
+	let asset = match dao::imageAssetFindByPath(path.to_owned()).await?
+	{
+		Some(asset) if asset.width == width && asset.height == height => Some(asset),
+		Some(asset) => dao::imageAssetUpdateSize(asset.id, width, height).await?,
+		None => dao::imageAssetCreate(NewImageAsset { height, path, width }).await?,
+	};
+	
+	return asset.ok_or_else(|| Error::msg("Failed to register image asset"));
+}
+
+/**
+Check that a wall from `start` to `end` has some length and lies within the
+background of `scene`.
+*/
+async fn validWallEnds(scene: &Scene2D, start: [i64; 2], end: [i64; 2]) -> Result<()>
+{
+	if start == end
+	{
+		return Err(RequestError::invalid("A wall's start and end must differ").into());
+	}
+	
+	let background = dao::imageAssetGet(scene.backgroundId).await?;
+	let inside = |[x, y]: [i64; 2]| background.as_ref()
+		.is_some_and(|b| (0..=b.width).contains(&x) && (0..=b.height).contains(&y));
+	
+	return match inside(start) && inside(end)
+	{
+		true => Ok(()),
+		false => Err(RequestError::invalid("A wall must lie within the scene").into()),
+	};
+}
+
+/**
+Describe `wall` for sending to clients.
+*/
+fn wallData(wall: Wall) -> WallData
+{
+	let kind = wall.kind();
+	let door = match kind
+	{
+		WallKind::Door => Some(wall.doorState()),
+		_ => None,
+	};
+	
+	return WallData
+	{
+		door,
+		end: [wall.endX, wall.endY],
+		id: wall.id,
+		kind,
+		scene: wall.sceneId,
+		start: [wall.startX, wall.startY],
+	};
+}
+
+/**
+Find the ids of the users named `names`.
+*/
+async fn userIds(names: &[String]) -> Result<Vec<i32>>
+{
+	let mut ids = vec![];
+	for name in names
+	{
+		let user = dao::userFind(name.to_owned()).await?
+			.ok_or_else(|| RequestError::notFound(format!("No user named {}", name)))?;
+		
+		ids.push(user.id);
+	}
+	
+	return Ok(ids);
+}
+
+/**
+Trim the name of a channel or scene, checking that it is neither empty nor
+longer than `maxLength` characters and has no control characters.
+*/
+fn validName(name: &str, maxLength: usize) -> Result<String>
+{
+	let name = name.trim();
+	if name.is_empty()
+	{
+		return Err(RequestError::invalid("name must not be empty").into());
+	}
+	
+	if name.chars().count() > maxLength || name.chars().any(char::is_control)
+	{
+		return Err(RequestError::invalid(format!("name must be at most {} printable characters", maxLength)).into());
+	}
+	
+	return Ok(name.to_string());
+}
+
+/**
+Prepare chat text for a client's `RichTextLabel`, escaping its BBCode unless
+`escapeBbcode` is turned off in the `[chat]` section of `config.toml`.
+*/
+fn displayText(text: &str) -> String
+{
+	return match getConfig().chat.escapeBbcode
+	{
+		true => escapeBbcode(text),
+		false => text.to_string(),
+	};
+}
+
+/**
+Prepare the snippet of a search hit for display like `displayText`, marking
+each matched word in bold.
+*/
+fn displaySnippet(hit: &MessageSearchHit) -> String
+{
+	let mut display = String::new();
+	let mut end = 0;
+	
+	for range in hit.matches()
+	{
+		display.push_str(&displayText(&hit.snippet[end..range.start]));
+		display.push_str(&format!("[b]{}[/b]", displayText(&hit.snippet[range.clone()])));
+		end = range.end;
+	}
+	
+	display.push_str(&displayText(&hit.snippet[end..]));
+	return display;
+}
+
+/**
+Convert a log entry for sending to clients. Deleted messages have no text.
+*/
+fn historyMessage(entry: LogEntry) -> HistoryMessageData
+{
+	return HistoryMessageData
+	{
+		id: entry.id,
+		channel: entry.channelId,
+		deleted: entry.deleted,
+		edited: entry.edited,
+		text: match entry.deleted
+		{
+			true => String::new(),
+			false => displayText(&entry.displayText()),
+		},
+		recipient: entry.recipient,
+		roll: entry.roll,
+		senderId: entry.senderId,
+		timestamp: entry.timestamp,
+	};
+}
+
+/**
+Read the `RequestId` from a command which could not otherwise be parsed.
+*/
+fn readRequestId(json: &str) -> Option<String>
+{
+	let value = serde_json::from_str::<serde_json::Value>(json).ok()?;
+	return value.get("RequestId")?
+		.as_str()
+		.map(|id| id.to_string());
+}
+
+/**
+Check whether a command which could not otherwise be parsed is a `HelloSend`.
+*/
+fn isHelloSend(json: &str) -> bool
+{
+	return serde_json::from_str::<serde_json::Value>(json).ok()
+		.and_then(|value| value.get("Type")?.as_i64())
+		.is_some_and(|value| value == Commands::HelloSend as i64);
+}
+
+/**
+Read the credentials from an authentication command, provided neither is empty.
+*/
+fn readCredentials(credentials: CredentialsData) -> Option<(String, String)>
+{
+	return match credentials.name.is_empty() || credentials.password.is_empty()
+	{
+		true => None,
+		false => Some((credentials.name, credentials.password)),
+	};
+}
+
+/**
+Hash a password off of the async runtime, as Argon2 is deliberately slow.
+*/
+async fn hashPasswordBlocking(password: String) -> Result<String>
+{
+	return tokio::task::spawn_blocking(move || hashPassword(&password)).await?;
+}
+
+/**
+Verify a password off of the async runtime, as Argon2 is deliberately slow.
+*/
+async fn verifyPasswordBlocking(password: String, hash: String) -> bool
+{
+	return tokio::task::spawn_blocking(move || verifyPassword(&password, &hash))
+		.await
+		.unwrap_or(false);
+}
+
+#[cfg(test)]
+mod tests
+{
+	use std::future::Future;
+	use std::sync::{Arc, Mutex};
+	use ::fastwebsockets::handshake;
+	use ::fastwebsockets::upgrade::upgrade;
+	use ::fastwebsockets::WebSocket;
+	use ::http_body_util::Empty;
+	use ::hyper::{Request, StatusCode};
+	use ::hyper::body::{Bytes, Incoming};
+	use ::hyper::server::conn::http1;
+	use ::hyper::service::service_fn;
+	use ::tokio::io::duplex;
+	use ::tokio::sync::oneshot;
+	use crate::data::resetTestDatabase;
+	use crate::net::protocol::ProtocolVersion;
+	use super::*;
+	
+	/**
+	Runs the futures hyper spawns for the client side of a test connection.
+	*/
+	struct SpawnExecutor;
+	
+	impl<F> hyper::rt::Executor<F> for SpawnExecutor
+		where F: Future + Send + 'static, F::Output: Send + 'static
+	{
+		fn execute(&self, future: F)
+		{
+			tokio::spawn(future);
+		}
+	}
+	
+	/**
+	Connect a client for `user` over an in memory socket, as if it had completed
+	the hello exchange and authenticated.
+	
+	The other end of the socket is returned alongside the client and must be
+	kept until the test ends.
+	*/
+	async fn testClient(user: User) -> (WebSocketClient, WebSocket<TokioIo<Upgraded>>)
+	{
+		let (serverStream, clientStream) = duplex(64 * 1024);
+		let (sender, receiver) = oneshot::channel();
+		let sender = Arc::new(Mutex::new(Some(sender)));
+		
+		tokio::spawn(http1::Builder::new()
+			.serve_connection(TokioIo::new(serverStream), service_fn(move |mut request: Request<Incoming>| {
+				let sender = sender.clone();
+				async move {
+					let (response, future) = upgrade(&mut request)?;
+					if let Some(sender) = sender.lock().ok().and_then(|mut s| s.take())
 					{
-						Ok(opt) => opt,
-						
-						Err(e) => {
-							error!("Error creating new user with username '{}': {:?}", username, e);
-							None
-						},
+						let _ = sender.send(future);
 					}
-				},
-				
-				Some(u) => Some(u),
-			},
+					
+					return Result::<_, Error>::Ok(response);
+				}
+			}))
+			.with_upgrades());
+		
+		let request = Request::builder()
+			.uri("ws://localhost/")
+			.header("Host", "localhost")
+			.header("Upgrade", "websocket")
+			.header("Connection", "upgrade")
+			.header("Sec-WebSocket-Key", handshake::generate_key())
+			.header("Sec-WebSocket-Version", "13")
+			.body(Empty::<Bytes>::new())
+			.expect("Failed to build upgrade request");
+		
+		let (socket, response) = handshake::client(&SpawnExecutor, request, clientStream).await
+			.expect("Failed to connect test client");
+		assert_eq!(response.status(), StatusCode::SWITCHING_PROTOCOLS);
+		
+		let future = receiver.await.expect("Connection was not upgraded");
+		let mut client = WebSocketClient::fromUpgradeFut(future).await.expect("Failed to create test client");
+		
+		client.id = client.userGetClientId(&user.name).expect("Failed to assign a client id");
+		client.protocolVersion = Some(ProtocolVersion);
+		client.user = Some(user);
+		
+		return (client, socket);
+	}
+	
+	async fn createUser(name: &str, role: Role) -> User
+	{
+		let newUser = NewUser
+		{
+			name: name.into(),
+			role: role.into(),
+			..Default::default()
+		};
+		
+		return dao::userCreate(newUser).await
+			.expect("Error creating test user")
+			.expect("No database connection");
+	}
+	
+	async fn setCampaignRole(campaignId: i32, user: &User, role: Role)
+	{
+		let newCampaignRole = NewCampaignRole
+		{
+			campaignId,
+			role: role.into(),
+			userId: user.id,
+		};
+		
+		dao::campaignRoleSet(newCampaignRole).await.expect("Error setting campaign role");
+	}
+	
+	async fn campaignRole(campaignId: i32, user: &User) -> Option<Role>
+	{
+		return dao::campaignRoleFind(campaignId, user.id).await
+			.expect("Error finding campaign role")
+			.map(|r| r.role());
+	}
+	
+	async fn serverRole(user: &User) -> Role
+	{
+		return dao::userGet(user.id).await
+			.expect("Error getting user")
+			.expect("User does not exist")
+			.role();
+	}
+	
+	/**
+	Process `payload` as though `client` sent it, returning the code of the
+	error it was answered with, if any.
+	*/
+	async fn send(client: &mut WebSocketClient, payload: Payload) -> Option<ErrorCode>
+	{
+		let command = buildCommand(client.id, payload, None);
+		return client.processCommand(command).await
+			.err()
+			.map(|e| describeError(&e).0);
+	}
+	
+	/**
+	The payloads of the commands queued for `client` so far.
+	*/
+	fn received(client: &mut WebSocketClient) -> Vec<Payload>
+	{
+		let mut payloads = vec![];
+		while let Ok(command) = client.inbound.try_recv()
+		{
+			payloads.push(command.Payload);
+		}
+		
+		return payloads;
+	}
+	
+	fn roleUpdate(user: &User, role: Role, campaign: Option<i32>) -> Payload
+	{
+		return Payload::UserRoleUpdate(UserRoleData
+		{
+			campaign,
+			name: user.name.to_owned(),
+			role,
+		});
+	}
+	
+	/**
+	The text of the messages `client` may read in its current chat room, oldest
+	first.
+	*/
+	async fn readableTexts(client: &WebSocketClient) -> Vec<String>
+	{
+		let scope = client.messageScope(None).await.expect("Error reading message scope");
+		return dao::messageFindPage(scope, None, 100).await
+			.expect("Error finding messages")
+			.into_iter()
+			.map(|m| m.text)
+			.collect();
+	}
+	
+	#[tokio::test]
+	async fn channelsAreReadByTheirAudience()
+	{
+		let _database = resetTestDatabase().await;
+		
+		let gameMaster = createUser("channelGameMaster", Role::GameMaster).await;
+		let member = createUser("channelMember", Role::Player).await;
+		let player = createUser("channelPlayer", Role::Player).await;
+		
+		let channels = [
+			("public", ChannelVisibility::Public, vec![]),
+			("gm", ChannelVisibility::GameMasters, vec![member.id]),
+			("members", ChannelVisibility::Members, vec![member.id]),
+		];
+		
+		for (name, visibility, members) in channels
+		{
+			let newChannel = NewChannel { name: name.into(), visibility: visibility.into(), ..Default::default() };
+			let channel = dao::channelCreate(newChannel, members).await
+				.expect("Error creating channel")
+				.expect("No database connection");
+			
+			let newMessage = NewMessage
+			{
+				channelId: Some(channel.id),
+				text: name.into(),
+				userId: Some(gameMaster.id),
+				..Default::default()
+			};
+			
+			dao::messageCreate(newMessage).await.expect("Error creating message");
+		}
+		
+		let (gameMaster, _gameMasterSocket) = testClient(gameMaster).await;
+		let (member, _memberSocket) = testClient(member).await;
+		let (player, _playerSocket) = testClient(player).await;
+		
+		//Listing a user as a member of a game master channel does not let them read it
+		assert_eq!(readableTexts(&gameMaster).await, vec!["public", "gm", "members"]);
+		assert_eq!(readableTexts(&member).await, vec!["public", "members"]);
+		assert_eq!(readableTexts(&player).await, vec!["public"]);
+	}
+	
+	#[tokio::test]
+	async fn historyPagesAreCapped()
+	{
+		let _database = resetTestDatabase().await;
+		
+		let user = createUser("historyReader", Role::Player).await;
+		for i in 0..=MaxHistoryPageSize
+		{
+			let newMessage = NewMessage
+			{
+				text: i.to_string(),
+				userId: Some(user.id),
+				..Default::default()
+			};
+			
+			dao::messageCreate(newMessage).await.expect("Error creating message");
+		}
+		
+		let (mut client, _socket) = testClient(user).await;
+		let history = |before: Option<i32>| {
+			let request = HistoryRequestData { before, channel: None, limit: Some(MaxHistoryPageSize * 2) };
+			return Payload::BroadcastHistoryRequest(request);
+		};
+		
+		assert_eq!(send(&mut client, history(None)).await, None);
+		let page = match received(&mut client).pop()
+		{
+			Some(Payload::BroadcastHistoryResponse(page)) => page,
+			other => panic!("Expected a history response, found {:?}", other),
+		};
+		
+		assert_eq!(page.messages.len(), MaxHistoryPageSize as usize);
+		assert!(page.messages.windows(2).all(|pair| pair[0].id < pair[1].id));
+		assert_eq!(page.messages[0].text, "historyReader: 1");
+		assert!(page.more);
+		
+		assert_eq!(send(&mut client, history(Some(page.messages[0].id))).await, None);
+		let page = match received(&mut client).pop()
+		{
+			Some(Payload::BroadcastHistoryResponse(page)) => page,
+			other => panic!("Expected a history response, found {:?}", other),
+		};
+		
+		assert_eq!(page.messages.len(), 1);
+		assert_eq!(page.messages[0].text, "historyReader: 0");
+		assert!(!page.more);
+	}
+	
+	#[tokio::test]
+	async fn typedMarkersDoNotHighlightSearches()
+	{
+		let _database = resetTestDatabase().await;
+		
+		let user = createUser("searcher", Role::Player).await;
+		let newMessage = NewMessage
+		{
+			text: "\u{2}fake\u{3} [i]amulet[/i]".into(),
+			userId: Some(user.id),
+			..Default::default()
+		};
+		
+		dao::messageCreate(newMessage).await.expect("Error creating message");
+		
+		let (mut client, _socket) = testClient(user).await;
+		let request = SearchData
+		{
+			channel: None,
+			end: None,
+			limit: None,
+			start: None,
+			terms: "amulet".into(),
+			user: None,
+		};
+		
+		assert_eq!(send(&mut client, Payload::BroadcastSearchRequest(request)).await, None);
+		let hits = match received(&mut client).pop()
+		{
+			Some(Payload::BroadcastSearchResponse(result)) => result.hits,
+			other => panic!("Expected a search response, found {:?}", other),
+		};
+		
+		assert_eq!(hits.len(), 1);
+		assert_eq!(hits[0].snippet, "\u{2}fake\u{3} [lb]i[rb][b]amulet[/b][lb]/i[rb]");
+	}
+	
+	#[tokio::test]
+	async fn editsAndDeletesKeepRevisions()
+	{
+		let _database = resetTestDatabase().await;
+		
+		let user = createUser("reviser", Role::Player).await;
+		let newMessage = NewMessage
+		{
+			text: "first".into(),
+			timestamp: Utc::now().naive_utc(),
+			userId: Some(user.id),
+			..Default::default()
+		};
+		
+		let message = dao::messageCreate(newMessage).await
+			.expect("Error creating message")
+			.expect("No database connection");
+		
+		let (mut client, _socket) = testClient(user.clone()).await;
+		let edit = EditData { id: message.id, text: "second".into() };
+		assert_eq!(send(&mut client, Payload::BroadcastEditRequest(edit)).await, None);
+		assert_eq!(send(&mut client, Payload::BroadcastDeleteRequest(MessageIdData { id: message.id })).await, None);
+		
+		let revisions = dao::messageRevisionFindByMessage(message.id).await.expect("Error finding revisions");
+		assert_eq!(revisions.iter().map(|r| r.text.as_str()).collect::<Vec<_>>(), vec!["first", "second"]);
+		assert!(revisions.iter().all(|r| r.userId == Some(user.id)));
+		
+		let scope = client.messageScope(None).await.expect("Error reading message scope");
+		let deleted = dao::messageFind(scope, message.id).await
+			.expect("Error finding message")
+			.expect("Deleted messages are kept");
+		assert!(deleted.deleted && deleted.text.is_empty());
+	}
+	
+	#[tokio::test]
+	async fn tokenMovesStayOnTheScene()
+	{
+		let _database = resetTestDatabase().await;
+		
+		let owner = createUser("tokenOwner", Role::Player).await;
+		let player = createUser("tokenPlayer", Role::Player).await;
+		
+		let background = dao::imageAssetCreate(NewImageAsset { height: 500, path: "map.png".into(), width: 1000 }).await
+			.expect("Error creating image")
+			.expect("No database connection");
+		
+		let newScene = NewScene2D
+		{
+			backgroundId: background.id,
+			gridCellSize: 100,
+			name: "map".into(),
+			..Default::default()
+		};
+		
+		let scene = dao::scene2dCreate(newScene).await
+			.expect("Error creating scene")
+			.expect("No database connection");
+		
+		let newToken = NewToken
+		{
+			name: "hero".into(),
+			ownerId: Some(owner.id),
+			sceneId: scene.id,
+			x: 50,
+			y: 50,
+			..Default::default()
+		};
+		
+		let token = dao::tokenCreate(newToken).await
+			.expect("Error creating token")
+			.expect("No database connection");
+		
+		let position = || async {
+			let token = dao::tokenGet(token.id).await
+				.expect("Error getting token")
+				.expect("Token was removed");
+			return (token.x, token.y);
+		};
+		
+		let (mut client, _socket) = testClient(owner).await;
+		let moveTo = |x: i64, y: i64| Payload::TokenMove(TokenMoveData { id: token.id, x, y });
+		
+		//Moves snap to the centre of a grid cell
+		assert_eq!(send(&mut client, moveTo(130, 40)).await, None);
+		assert_eq!(position().await, (150, 50));
+		
+		//Moves outside the scene are rejected
+		assert_eq!(send(&mut client, moveTo(1200, 40)).await, Some(ErrorCode::InvalidRequest));
+		assert_eq!(send(&mut client, moveTo(130, -40)).await, Some(ErrorCode::InvalidRequest));
+		assert_eq!(position().await, (150, 50));
+		
+		//Only the owner may move the token
+		let (mut other, _otherSocket) = testClient(player).await;
+		assert_eq!(send(&mut other, moveTo(350, 50)).await, Some(ErrorCode::InvalidRequest));
+		assert_eq!(position().await, (150, 50));
+		
+		//Tokens on scenes outside the client's chat room cannot be found
+		let id = client.id;
+		client.changeRooms(|rooms| rooms.join(id, Room::Campaign(1))).expect("Failed to join campaign");
+		assert_eq!(send(&mut client, moveTo(350, 50)).await, Some(ErrorCode::NotFound));
+		assert_eq!(position().await, (150, 50));
+	}
+	
+	/**
+	The reason given by the last `AuthenticateFail` queued for `client`, or
+	`None` if it last succeeded.
+	*/
+	fn authenticationResult(client: &mut WebSocketClient) -> Option<AuthenticationFailure>
+	{
+		return received(client).into_iter()
+			.rev()
+			.find_map(|payload| match payload
+			{
+				Payload::AuthenticateFail(data) => Some(Some(data.reason)),
+				Payload::AuthenticateSuccess(_) => Some(None),
+				_ => None,
+			})
+			.expect("No authentication result was sent");
+	}
+	
+	#[tokio::test]
+	async fn authenticatedClientsCannotLogInAgain()
+	{
+		let _database = resetTestDatabase().await;
+		
+		let newUser = NewUser
+		{
+			name: "loginSecond".into(),
+			passwordHash: Some(hashPassword("password").expect("Failed to hash password")),
+			..Default::default()
+		};
+		
+		dao::userCreate(newUser).await.expect("Error creating user");
+		
+		let credentials = |name: &str| CredentialsData { name: name.into(), password: "password".into() };
+		let login = |name: &str| Payload::AuthenticateSend(credentials(name));
+		
+		let first = createUser("loginFirst", Role::Player).await;
+		let (mut client, _socket) = testClient(first.clone()).await;
+		let id = client.id;
+		
+		assert_eq!(send(&mut client, login("loginSecond")).await, None);
+		assert_eq!(authenticationResult(&mut client), Some(AuthenticationFailure::AlreadyAuthenticated));
+		
+		assert_eq!(send(&mut client, Payload::AuthenticateCreate(credentials("loginThird"))).await, None);
+		assert_eq!(authenticationResult(&mut client), Some(AuthenticationFailure::AlreadyAuthenticated));
+		assert!(dao::userFind("loginThird".into()).await.expect("Error finding user").is_none());
+		
+		assert_eq!(client.id, id);
+		assert_eq!(client.user.as_ref().map(|u| u.id), Some(first.id));
+		
+		//A client which has not logged in yet may
+		client.user = None;
+		assert_eq!(send(&mut client, login("loginSecond")).await, None);
+		assert_eq!(authenticationResult(&mut client), None);
+		assert!(client.user.as_ref().is_some_and(|u| u.name == "loginSecond"));
+	}
+	
+	#[tokio::test]
+	async fn authenticatedClientsCannotResumeSessions()
+	{
+		let _database = resetTestDatabase().await;
+		
+		let first = createUser("resumeFirst", Role::Player).await;
+		let second = createUser("resumeSecond", Role::Player).await;
+		let (mut client, _socket) = testClient(first.clone()).await;
+		let id = client.id;
+		
+		let secondId = client.userGetClientId(&second.name).expect("Failed to assign a client id");
+		let token = {
+			let manager = getSessionManager().lock().expect("Sessions are unavailable");
+			let token = manager.create(secondId, second.id);
+			manager.disconnect(&token, Utc::now());
+			token
 		};
+		
+		let resume = Payload::AuthenticateResume(ResumeData { sessionToken: token.to_owned() });
+		assert_eq!(send(&mut client, resume).await, None);
+		assert_eq!(authenticationResult(&mut client), Some(AuthenticationFailure::AlreadyAuthenticated));
+		assert_eq!(client.id, id);
+		assert_eq!(client.user.as_ref().map(|u| u.id), Some(first.id));
+		
+		//The session is left for its own client to resume
+		let resumed = getSessionManager().lock()
+			.expect("Sessions are unavailable")
+			.resume(&token, gracePeriod(), Utc::now());
+		assert!(resumed.is_some_and(|(_, session)| session.clientId == secondId));
+	}
+	
+	#[tokio::test]
+	async fn campaignGameMastersOnlyChangeTheirCampaign()
+	{
+		let _database = resetTestDatabase().await;
+		
+		let sender = createUser("campaignGameMaster", Role::Player).await;
+		let target = createUser("campaignTarget", Role::Player).await;
+		setCampaignRole(1, &sender, Role::GameMaster).await;
+		
+		let (mut client, _socket) = testClient(sender.clone()).await;
+		let denied = Some(ErrorCode::PermissionDenied);
+		let id = client.id;
+		client.changeRooms(|rooms| rooms.join(id, Room::Campaign(1))).expect("Failed to join campaign");
+		
+		//Being a game master of campaign 1 grants nothing server wide
+		assert_eq!(send(&mut client, roleUpdate(&sender, Role::GameMaster, None)).await, denied);
+		assert_eq!(send(&mut client, roleUpdate(&target, Role::GameMaster, None)).await, denied);
+		assert_eq!(serverRole(&sender).await, Role::Player);
+		assert_eq!(serverRole(&target).await, Role::Player);
+		
+		//Nor in other campaigns
+		assert_eq!(send(&mut client, roleUpdate(&sender, Role::GameMaster, Some(2))).await, denied);
+		assert_eq!(campaignRole(2, &sender).await, None);
+		
+		assert_eq!(send(&mut client, roleUpdate(&target, Role::GameMaster, Some(1))).await, None);
+		assert_eq!(campaignRole(1, &target).await, Some(Role::GameMaster));
+	}
+	
+	#[tokio::test]
+	async fn serverGameMastersChangeRoles()
+	{
+		let _database = resetTestDatabase().await;
+		
+		let sender = createUser("serverGameMaster", Role::GameMaster).await;
+		let target = createUser("serverTarget", Role::Player).await;
+		setCampaignRole(2, &sender, Role::Player).await;
+		
+		let (mut client, _socket) = testClient(sender.clone()).await;
+		let denied = Some(ErrorCode::PermissionDenied);
+		
+		assert_eq!(send(&mut client, roleUpdate(&target, Role::Spectator, None)).await, None);
+		assert_eq!(serverRole(&target).await, Role::Spectator);
+		
+		assert_eq!(send(&mut client, roleUpdate(&target, Role::GameMaster, Some(1))).await, None);
+		assert_eq!(campaignRole(1, &target).await, Some(Role::GameMaster));
+		
+		//A server game master who only plays in a campaign cannot change its roles
+		assert_eq!(send(&mut client, roleUpdate(&target, Role::GameMaster, Some(2))).await, denied);
+		assert_eq!(campaignRole(2, &target).await, None);
 	}
 }
@@ -5,18 +5,85 @@ use ::serde_repr::{Deserialize_repr, Serialize_repr};
 pub enum Commands
 {
 	None,
+	PermissionDenied,
+	Error,
+	
+	HelloRequest = 10,
+	HelloSend,
+	HelloFail,
+	HelloSuccess,
 	
 	AuthenticateRequest = 100,
 	AuthenticateSend,
 	AuthenticateFail,
 	AuthenticateSuccess,
+	AuthenticateCreate,
+	AuthenticateResume,
 	
 	BroadcastRequest = 200,
 	BroadcastResponse,
 	BroadcastGetRequest,
+	DirectMessageSend,
+	BroadcastHistoryRequest,
+	BroadcastHistoryResponse,
+	BroadcastSearchRequest,
+	BroadcastSearchResponse,
+	BroadcastExportRequest,
+	BroadcastExportResponse,
+	BroadcastEditRequest,
+	BroadcastDeleteRequest,
+	BroadcastUpdated,
+	BroadcastRevisionsRequest,
+	BroadcastRevisionsResponse,
 	
 	Scene2DRequest = 300,
 	Scene2DResponse,
+	Scene2DListRequest,
+	Scene2DListResponse,
+	Scene2DCreate,
+	Scene2DRename,
+	Scene2DBackgroundUpdate,
+	Scene2DDelete,
+	Scene2DUpdated,
+	Scene2DRemoved,
+	Scene2DGridUpdate,
+	
+	UserRoleUpdate = 400,
+	UserRoleUpdated,
+	
+	RoomJoin = 500,
+	RoomLeave,
+	RoomJoined,
+	RoomLeft,
+	
+	ChannelListRequest = 600,
+	ChannelListResponse,
+	ChannelCreate,
+	ChannelUpdate,
+	ChannelUpdated,
+	ChannelRemoved,
+	
+	TokenListRequest = 700,
+	TokenListResponse,
+	TokenCreate,
+	TokenMove,
+	TokenDelete,
+	TokenUpdated,
+	TokenRemoved,
+	
+	FogRequest = 800,
+	FogUpdated,
+	FogReveal,
+	FogHide,
+	FogReset,
+	
+	WallListRequest = 900,
+	WallListResponse,
+	WallCreate,
+	WallDoorUpdate,
+	WallDelete,
+	WallUpdated,
+	WallRemoved,
 }
 
 impl Default for Commands
@@ -0,0 +1,99 @@
+use std::fmt::{Display, Formatter};
+use ::anyhow::Error;
+use ::serde_repr::{Deserialize_repr, Serialize_repr};
+
+/**
+The machine readable `code` sent in the `Data` of an `Error` command.
+*/
+#[derive(Clone, Copy, Debug, Deserialize_repr, PartialEq, Eq, Serialize_repr)]
+#[repr(i32)]
+pub enum ErrorCode
+{
+	/// The command could not be parsed.
+	MalformedCommand = 1,
+	/// The command was parsed but its fields were missing or invalid.
+	InvalidRequest,
+	/// Something the command referred to does not exist.
+	NotFound,
+	/// The server failed to process an otherwise valid command.
+	ServerError,
+	/// The client is not allowed to do what the command asked.
+	PermissionDenied,
+}
+
+/**
+An error caused by the content of a client's request, reported back to the
+client as an `Error` command rather than treated as a server failure.
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequestError
+{
+	pub code: ErrorCode,
+	pub message: String,
+}
+
+impl RequestError
+{
+	pub fn denied(message: impl Into<String>) -> Self
+	{
+		return Self { code: ErrorCode::PermissionDenied, message: message.into() };
+	}
+	
+	pub fn invalid(message: impl Into<String>) -> Self
+	{
+		return Self { code: ErrorCode::InvalidRequest, message: message.into() };
+	}
+	
+	pub fn notFound(message: impl Into<String>) -> Self
+	{
+		return Self { code: ErrorCode::NotFound, message: message.into() };
+	}
+}
+
+impl Display for RequestError
+{
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result
+	{
+		return write!(f, "{}", self.message);
+	}
+}
+
+impl std::error::Error for RequestError {}
+
+/**
+Determine the `code` and `message` to report to the client for a failed command.
+
+Errors which are not a `RequestError` are reported as a `ServerError` without
+their details, which may expose server internals.
+*/
+pub fn describeError(error: &Error) -> (ErrorCode, String)
+{
+	return match error.downcast_ref::<RequestError>()
+	{
+		Some(e) => (e.code, e.message.to_owned()),
+		None => (ErrorCode::ServerError, "The server failed to process the command".to_string()),
+	};
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	
+	#[test]
+	fn requestErrorsAreDescribed()
+	{
+		let error = Error::new(RequestError::invalid("start is required"));
+		assert_eq!(describeError(&error), (ErrorCode::InvalidRequest, "start is required".to_string()));
+	}
+	
+	#[test]
+	fn otherErrorsAreHidden()
+	{
+		let error = Error::msg("database is locked");
+		let (code, message) = describeError(&error);
+		
+		assert_eq!(code, ErrorCode::ServerError);
+		assert!(!message.contains("database"));
+	}
+}
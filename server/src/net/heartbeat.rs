@@ -0,0 +1,211 @@
+use ::tokio::time::{Duration, Instant};
+use crate::config::ConfigHeartbeat;
+
+/**
+What a connection must do when its heartbeat deadline passes.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeartbeatAction
+{
+	/// Nothing is due yet.
+	None,
+	/// Send a ping frame.
+	Ping,
+	/// A ping went unanswered for longer than the pong timeout.
+	PongTimedOut,
+	/// The client has not sent anything other than pongs for longer than the idle timeout.
+	IdleTimedOut,
+}
+
+/**
+Tracks the liveness of a single connection.
+
+A ping is sent every `pingInterval` seconds. Any frame received from the client
+counts as an answer to the outstanding ping, while only frames other than
+pongs count as activity for the idle timeout. A value of `0` disables the
+corresponding check.
+*/
+#[derive(Clone, Debug)]
+pub struct Heartbeat
+{
+	idleTimeout: Option<Duration>,
+	lastActivity: Instant,
+	nextPing: Option<Instant>,
+	pingInterval: Option<Duration>,
+	pingSent: Option<Instant>,
+	pongTimeout: Option<Duration>,
+}
+
+impl Heartbeat
+{
+	pub fn new(config: &ConfigHeartbeat, now: Instant) -> Self
+	{
+		let pingInterval = seconds(config.pingInterval);
+		
+		return Self
+		{
+			idleTimeout: seconds(config.idleTimeout),
+			lastActivity: now,
+			nextPing: pingInterval.map(|interval| now + interval),
+			pingInterval,
+			pingSent: None,
+			pongTimeout: seconds(config.pongTimeout),
+		};
+	}
+	
+	/**
+	Record a frame received from the client.
+	*/
+	pub fn received(&mut self, now: Instant, isPong: bool)
+	{
+		self.pingSent = None;
+		
+		if !isPong
+		{
+			self.lastActivity = now;
+		}
+	}
+	
+	/**
+	The next time at which `poll` may return an action, or `None` if every
+	check is disabled.
+	*/
+	pub fn nextDeadline(&self) -> Option<Instant>
+	{
+		let pongDeadline = self.pingSent.zip(self.pongTimeout)
+			.map(|(sent, timeout)| sent + timeout);
+		let idleDeadline = self.idleTimeout.map(|timeout| self.lastActivity + timeout);
+		
+		return [self.nextPing, pongDeadline, idleDeadline].into_iter()
+			.flatten()
+			.min();
+	}
+	
+	/**
+	Determine what is due at `now`, updating the ping schedule when a ping is due.
+	*/
+	pub fn poll(&mut self, now: Instant) -> HeartbeatAction
+	{
+		if let (Some(sent), Some(timeout)) = (self.pingSent, self.pongTimeout)
+		{
+			if now >= sent + timeout
+			{
+				return HeartbeatAction::PongTimedOut;
+			}
+		}
+		
+		if let Some(timeout) = self.idleTimeout
+		{
+			if now >= self.lastActivity + timeout
+			{
+				return HeartbeatAction::IdleTimedOut;
+			}
+		}
+		
+		if let (Some(next), Some(interval)) = (self.nextPing, self.pingInterval)
+		{
+			if now >= next
+			{
+				self.nextPing = Some(now + interval);
+				
+				//Keep the deadline of a ping which is still unanswered
+				if self.pingSent.is_none()
+				{
+					self.pingSent = Some(now);
+				}
+				
+				return HeartbeatAction::Ping;
+			}
+		}
+		
+		return HeartbeatAction::None;
+	}
+}
+
+fn seconds(value: u64) -> Option<Duration>
+{
+	return match value
+	{
+		0 => None,
+		_ => Some(Duration::from_secs(value)),
+	};
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	
+	fn config(pingInterval: u64, pongTimeout: u64, idleTimeout: u64) -> ConfigHeartbeat
+	{
+		return ConfigHeartbeat { idleTimeout, pingInterval, pongTimeout };
+	}
+	
+	#[test]
+	fn answeredPings()
+	{
+		let start = Instant::now();
+		let mut heartbeat = Heartbeat::new(&config(30, 10, 0), start);
+		
+		assert_eq!(heartbeat.nextDeadline(), Some(start + Duration::from_secs(30)));
+		assert_eq!(heartbeat.poll(start + Duration::from_secs(29)), HeartbeatAction::None);
+		assert_eq!(heartbeat.poll(start + Duration::from_secs(30)), HeartbeatAction::Ping);
+		assert_eq!(heartbeat.nextDeadline(), Some(start + Duration::from_secs(40)));
+		
+		heartbeat.received(start + Duration::from_secs(31), true);
+		assert_eq!(heartbeat.nextDeadline(), Some(start + Duration::from_secs(60)));
+		assert_eq!(heartbeat.poll(start + Duration::from_secs(45)), HeartbeatAction::None);
+		assert_eq!(heartbeat.poll(start + Duration::from_secs(60)), HeartbeatAction::Ping);
+	}
+	
+	#[test]
+	fn unansweredPing()
+	{
+		let start = Instant::now();
+		let mut heartbeat = Heartbeat::new(&config(30, 10, 0), start);
+		
+		assert_eq!(heartbeat.poll(start + Duration::from_secs(30)), HeartbeatAction::Ping);
+		assert_eq!(heartbeat.poll(start + Duration::from_secs(39)), HeartbeatAction::None);
+		assert_eq!(heartbeat.poll(start + Duration::from_secs(40)), HeartbeatAction::PongTimedOut);
+	}
+	
+	#[test]
+	fn pongTimeoutLongerThanInterval()
+	{
+		let start = Instant::now();
+		let mut heartbeat = Heartbeat::new(&config(10, 25, 0), start);
+		
+		assert_eq!(heartbeat.poll(start + Duration::from_secs(10)), HeartbeatAction::Ping);
+		assert_eq!(heartbeat.poll(start + Duration::from_secs(20)), HeartbeatAction::Ping);
+		assert_eq!(heartbeat.poll(start + Duration::from_secs(30)), HeartbeatAction::Ping);
+		assert_eq!(heartbeat.poll(start + Duration::from_secs(35)), HeartbeatAction::PongTimedOut);
+	}
+	
+	#[test]
+	fn idleTimeout()
+	{
+		let start = Instant::now();
+		let mut heartbeat = Heartbeat::new(&config(30, 10, 100), start);
+		
+		heartbeat.received(start + Duration::from_secs(50), false);
+		for second in [60, 90, 120]
+		{
+			let now = start + Duration::from_secs(second);
+			let _ = heartbeat.poll(now);
+			heartbeat.received(now, true);
+		}
+		
+		assert_eq!(heartbeat.nextDeadline(), Some(start + Duration::from_secs(150)));
+		assert_eq!(heartbeat.poll(start + Duration::from_secs(150)), HeartbeatAction::IdleTimedOut);
+	}
+	
+	#[test]
+	fn disabled()
+	{
+		let start = Instant::now();
+		let mut heartbeat = Heartbeat::new(&config(0, 0, 0), start);
+		
+		assert_eq!(heartbeat.nextDeadline(), None);
+		assert_eq!(heartbeat.poll(start + Duration::from_secs(86400)), HeartbeatAction::None);
+	}
+}
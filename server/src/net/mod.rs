@@ -1,8 +1,19 @@
+mod auth;
+mod binary;
+mod chatcommands;
 mod client;
 mod commands;
+mod error;
+mod heartbeat;
+mod outbox;
 mod payload;
-mod queue;
+mod permissions;
+mod protocol;
+mod rooms;
 mod server;
+mod session;
+mod tls;
 mod user;
 
+pub use auth::hashPassword;
 pub use server::WebSocketServer;
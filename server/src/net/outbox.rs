@@ -0,0 +1,296 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Mutex, OnceLock};
+use ::chrono::Utc;
+use ::tokio::sync::mpsc::UnboundedSender;
+use super::payload::{Command, Payload};
+
+/// Maximum number of commands held for a detached client. The oldest are discarded first.
+const MaxPendingCommands: usize = 1000;
+
+pub fn getOutboxes() -> &'static Mutex<Outboxes>
+{
+	static OutboxesLock: OnceLock<Mutex<Outboxes>> = OnceLock::new();
+	return OutboxesLock.get_or_init(|| Mutex::new(Outboxes::default()));
+}
+
+/**
+Build a command ready to be sent to a client.
+*/
+pub fn buildCommand(
+	id: i64,
+	payload: Payload,
+	binaryData: Option<HashMap<String, Vec<u8>>>
+) -> Command
+{
+	return Command
+	{
+		Binary: binaryData.unwrap_or_default(),
+		Id: id,
+		Payload: payload,
+		RequestId: None,
+		Timestamp: Utc::now().timestamp(),
+	};
+}
+
+/**
+The outbound channel of a single client id.
+
+While no socket is attached, commands are held in `pending` and delivered, in
+order, once a socket resumes the client id. At most `MaxPendingCommands` are
+held.
+*/
+#[derive(Default)]
+struct Outbox
+{
+	pending: Vec<Command>,
+	sender: Option<UnboundedSender<Command>>,
+}
+
+impl Outbox
+{
+	fn send(&mut self, command: Command)
+	{
+		let undelivered = match &self.sender
+		{
+			None => Some(command),
+			Some(sender) => sender.send(command).err().map(|e| e.0),
+		};
+		
+		if let Some(command) = undelivered
+		{
+			self.sender = None;
+			self.hold(vec![command]);
+		}
+	}
+	
+	/**
+	Hold `commands` until a socket is attached, discarding the oldest held
+	commands beyond `MaxPendingCommands`.
+	*/
+	fn hold(&mut self, commands: Vec<Command>)
+	{
+		self.pending.extend(commands);
+		
+		let excess = self.pending.len().saturating_sub(MaxPendingCommands);
+		self.pending.drain(..excess);
+	}
+}
+
+/**
+Routes commands to the outbound channel of each authenticated client.
+
+Each connection's `WebSocketClient` owns the receiving end of its channel and
+writes commands to its socket as soon as they arrive.
+*/
+#[derive(Default)]
+pub struct Outboxes
+{
+	outboxes: HashMap<i64, Outbox>,
+}
+
+impl Outboxes
+{
+	/**
+	Attach a connection's channel to the client `id`.
+	
+	Any commands held while the client was detached are sent first, preserving
+	their order.
+	*/
+	pub fn attach(&mut self, id: i64, sender: UnboundedSender<Command>)
+	{
+		let outbox = self.outboxes.entry(id).or_default();
+		outbox.sender = Some(sender);
+		
+		for command in std::mem::take(&mut outbox.pending)
+		{
+			outbox.send(command);
+		}
+	}
+	
+	/**
+	Detach the channel of a closed connection from the client `id`.
+	
+	`unsent` holds the commands which were sent to the channel but never written
+	to the socket. They, and any commands sent to `id` afterward, are held until
+	`id` is attached again or removed. Nothing happens if `id` has since been
+	attached to a different channel.
+	*/
+	pub fn detach(&mut self, id: i64, sender: &UnboundedSender<Command>, unsent: Vec<Command>)
+	{
+		if let Some(outbox) = self.outboxes.get_mut(&id)
+		{
+			if outbox.sender.as_ref().is_some_and(|s| s.same_channel(sender))
+			{
+				outbox.sender = None;
+				outbox.pending.clear();
+				outbox.hold(unsent);
+			}
+		}
+	}
+	
+	/**
+	Queue a command for transmission to each of `ids`.
+	*/
+	pub fn queueMany(&mut self, ids: &BTreeSet<i64>, payload: Payload)
+	{
+		for id in ids
+		{
+			if let Some(outbox) = self.outboxes.get_mut(id)
+			{
+				outbox.send(buildCommand(*id, payload.clone(), None));
+			}
+		}
+	}
+	
+	/**
+	Queue a command for transmission to a given `id`.
+	
+	Commands for an `id` which has never been attached are discarded.
+	*/
+	#[allow(dead_code)]
+	pub fn queueCommand(&mut self,
+		id: i64,
+		payload: Payload,
+		binaryData: Option<HashMap<String, Vec<u8>>>
+	)
+	{
+		if let Some(outbox) = self.outboxes.get_mut(&id)
+		{
+			outbox.send(buildCommand(id, payload, binaryData));
+		}
+	}
+	
+	/**
+	Remove the client `id`, discarding any commands held for it.
+	*/
+	pub fn remove(&mut self, id: i64)
+	{
+		self.outboxes.remove(&id);
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use ::tokio::sync::mpsc::unbounded_channel;
+	use crate::net::payload::BroadcastData;
+	
+	fn broadcast(text: &str) -> Payload
+	{
+		return Payload::BroadcastResponse(BroadcastData { text: text.into(), ..Default::default() });
+	}
+	
+	fn texts(commands: Vec<Command>) -> Vec<String>
+	{
+		return commands.into_iter()
+			.filter_map(|c| match c.Payload
+			{
+				Payload::BroadcastResponse(data) => Some(data.text),
+				_ => None,
+			})
+			.collect();
+	}
+	
+	#[test]
+	fn broadcastIsPushed()
+	{
+		let mut outboxes = Outboxes::default();
+		let (sender1, mut receiver1) = unbounded_channel();
+		let (sender2, mut receiver2) = unbounded_channel();
+		let (sender3, mut receiver3) = unbounded_channel();
+		let ids = BTreeSet::from([1, 2]);
+		
+		outboxes.attach(1, sender1);
+		outboxes.attach(2, sender2);
+		outboxes.attach(3, sender3);
+		outboxes.queueMany(&ids, broadcast("first"));
+		outboxes.queueMany(&ids, broadcast("second"));
+		
+		for receiver in [&mut receiver1, &mut receiver2]
+		{
+			let mut received = vec![];
+			while let Ok(command) = receiver.try_recv()
+			{
+				received.push(command);
+			}
+			
+			assert_eq!(texts(received), vec!["first", "second"]);
+		}
+		
+		//Client 3 is not one of the recipients
+		assert!(receiver3.try_recv().is_err());
+	}
+	
+	#[test]
+	fn detachedCommandsAreHeld()
+	{
+		let mut outboxes = Outboxes::default();
+		let (sender, receiver) = unbounded_channel();
+		let ids = BTreeSet::from([1]);
+		
+		outboxes.attach(1, sender.clone());
+		outboxes.detach(1, &sender, vec![]);
+		drop(receiver);
+		
+		outboxes.queueMany(&ids, broadcast("first"));
+		outboxes.queueMany(&ids, broadcast("second"));
+		
+		let (newSender, mut newReceiver) = unbounded_channel();
+		outboxes.attach(1, newSender);
+		outboxes.queueMany(&ids, broadcast("third"));
+		
+		let mut received = vec![];
+		while let Ok(command) = newReceiver.try_recv()
+		{
+			received.push(command);
+		}
+		
+		assert_eq!(texts(received), vec!["first", "second", "third"]);
+	}
+	
+	#[test]
+	fn heldCommandsAreCapped()
+	{
+		let mut outboxes = Outboxes::default();
+		let (sender, receiver) = unbounded_channel();
+		let ids = BTreeSet::from([1]);
+		
+		outboxes.attach(1, sender.clone());
+		outboxes.detach(1, &sender, vec![]);
+		drop(receiver);
+		
+		for i in 0..MaxPendingCommands + 10
+		{
+			outboxes.queueMany(&ids, broadcast(&i.to_string()));
+		}
+		
+		let (newSender, mut newReceiver) = unbounded_channel();
+		outboxes.attach(1, newSender);
+		
+		let mut received = vec![];
+		while let Ok(command) = newReceiver.try_recv()
+		{
+			received.push(command);
+		}
+		
+		let received = texts(received);
+		assert_eq!(received.len(), MaxPendingCommands);
+		assert_eq!(received.first(), Some(&"10".to_string()));
+	}
+	
+	#[test]
+	fn detachIgnoresReplacedChannel()
+	{
+		let mut outboxes = Outboxes::default();
+		let (oldSender, _oldReceiver) = unbounded_channel();
+		let (newSender, mut newReceiver) = unbounded_channel();
+		
+		outboxes.attach(1, oldSender.clone());
+		outboxes.attach(1, newSender);
+		outboxes.detach(1, &oldSender, vec![]);
+		outboxes.queueCommand(1, broadcast("new"), None);
+		
+		assert!(newReceiver.try_recv().is_ok());
+	}
+}
@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+use ::anyhow::Result;
+use ::base64::prelude::*;
+use ::chrono::Utc;
+use ::serde::{Deserialize, Serialize};
+use ::serde_json::Value;
+use crate::net::commands::Commands;
+use super::data::{EmptyData, Payload};
+use super::legacy::{flattenPayload, LegacyData};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Command
+{
+	/// Raw binary data, sent to clients either as binary frames or base64 encoded into `BinaryData`.
+	pub Binary: HashMap<String, Vec<u8>>,
+	pub Id: i64,
+	pub Payload: Payload,
+	/// Chosen by the client and echoed back on every response to the command.
+	pub RequestId: Option<String>,
+	pub Timestamp: i64,
+}
+
+impl Default for Command
+{
+	fn default() -> Self
+	{
+		return Self
+		{
+			Binary: HashMap::default(),
+			Id: 0,
+			Payload: Payload::None(EmptyData::default()),
+			RequestId: None,
+			Timestamp: Utc::now().timestamp(),
+		};
+	}
+}
+
+impl Command
+{
+	/**
+	Parse a command received from a client.
+	
+	The payload is read from `Payload` if present, otherwise from the legacy
+	`Data` map.
+	*/
+	pub fn parse(json: &str) -> Result<Self>
+	{
+		let wire: WireCommand = serde_json::from_str(json)?;
+		
+		let payload = match wire.Payload
+		{
+			Some(value) => Payload::parse(wire.Type, value)?,
+			None => Payload::parse(wire.Type, LegacyData(&wire.Data))?,
+		};
+		
+		let mut binary = HashMap::default();
+		for (key, value) in wire.BinaryData
+		{
+			binary.insert(key, BASE64_STANDARD.decode(value)?);
+		}
+		
+		let command = Self
+		{
+			Binary: binary,
+			Id: wire.Id,
+			Payload: payload,
+			RequestId: wire.RequestId,
+			Timestamp: wire.Timestamp,
+		};
+		
+		return Ok(command);
+	}
+	
+	pub fn commandType(&self) -> Commands
+	{
+		return self.Payload.command();
+	}
+	
+	/**
+	Prepare this command for transmission.
+	
+	`typedPayloads` selects between sending the payload in `Payload` or as the
+	legacy `Data` map. When `binaryId` is set, `Binary` has been sent as binary
+	frames with that id, otherwise it is base64 encoded into `BinaryData`.
+	*/
+	pub fn toWire(&self, typedPayloads: bool, binaryId: Option<u64>) -> Result<WireCommand>
+	{
+		let payload = serde_json::to_value(&self.Payload)?;
+		let (data, payload) = match typedPayloads
+		{
+			true => (HashMap::default(), Some(payload)),
+			false => (flattenPayload(payload), None),
+		};
+		
+		let binaryData = match binaryId
+		{
+			Some(_) => HashMap::default(),
+			None => self.Binary.iter()
+				.map(|(key, bytes)| (key.to_owned(), BASE64_STANDARD.encode(bytes)))
+				.collect(),
+		};
+		
+		return Ok(WireCommand
+		{
+			BinaryData: binaryData,
+			BinaryId: binaryId,
+			Data: data,
+			Id: self.Id,
+			Payload: payload,
+			RequestId: self.RequestId.to_owned(),
+			Timestamp: self.Timestamp,
+			Type: self.commandType(),
+		});
+	}
+}
+
+/**
+A command as it appears in JSON.
+*/
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct WireCommand
+{
+	/// Binary data must be base64 encoded before transmission to clients
+	#[serde(default)]
+	BinaryData: HashMap<String, String>,
+	/// Id of the binary frames sent ahead of this command.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	BinaryId: Option<u64>,
+	/// The payload as a string map, for clients which do not use typed payloads.
+	#[serde(default)]
+	Data: HashMap<String, String>,
+	#[serde(default)]
+	Id: i64,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	Payload: Option<Value>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	RequestId: Option<String>,
+	#[serde(default)]
+	Timestamp: i64,
+	Type: Commands,
+}
+
+#[cfg(test)]
+mod tests
+{
+	use chrono::DateTime;
+	use crate::data::Role;
+	use crate::net::payload::{BroadcastData, BroadcastGetData, UserRoleData};
+	use super::*;
+	
+	#[test]
+	fn parseLegacy()
+	{
+		let json = r#"{"BinaryData":{},"Data":{"start":"100","end":"200"},"Id":-1,"Timestamp":0,"Type":202}"#;
+		let command = Command::parse(json).expect("Error parsing legacy command");
+		
+		assert_eq!(command.Payload, Payload::BroadcastGetRequest(BroadcastGetData
+		{
+			channel: None,
+			end: DateTime::from_timestamp(200, 0).unwrap(),
+			start: DateTime::from_timestamp(100, 0).unwrap(),
+		}));
+	}
+	
+	#[test]
+	fn parseTyped()
+	{
+		let json = r#"{"Payload":{"name":"gm","role":2},"RequestId":"a1","Type":400}"#;
+		let command = Command::parse(json).expect("Error parsing typed command");
+		
+		assert_eq!(command.RequestId, Some("a1".into()));
+		assert_eq!(command.Payload, Payload::UserRoleUpdate(UserRoleData
+		{
+			campaign: None,
+			name: "gm".into(),
+			role: Role::GameMaster,
+		}));
+	}
+	
+	#[test]
+	fn rejectInvalidPayloads()
+	{
+		let missingField = r#"{"Data":{"start":"100"},"Type":202}"#;
+		assert!(Command::parse(missingField).is_err());
+		
+		let wrongType = r#"{"Payload":{"name":"gm","role":"two"},"Type":400}"#;
+		assert!(Command::parse(wrongType).is_err());
+		
+		let invalidRole = r#"{"Data":{"name":"gm","role":"7"},"Type":400}"#;
+		assert!(Command::parse(invalidRole).is_err());
+	}
+	
+	#[test]
+	fn toWire()
+	{
+		let command = Command
+		{
+			Binary: vec![("image".to_string(), vec![1, 2, 3])].into_iter().collect(),
+			Payload: Payload::BroadcastResponse(BroadcastData { text: "hi".into(), ..Default::default() }),
+			..Default::default()
+		};
+		
+		let legacy = serde_json::to_value(command.toWire(false, None).unwrap()).unwrap();
+		assert_eq!(legacy["Data"]["text"], "hi");
+		assert_eq!(legacy["BinaryData"]["image"], "AQID");
+		assert!(legacy.get("Payload").is_none());
+		
+		let typed = serde_json::to_value(command.toWire(true, Some(4)).unwrap()).unwrap();
+		assert_eq!(typed["Payload"]["text"], "hi");
+		assert_eq!(typed["BinaryId"], 4);
+		assert!(typed["BinaryData"].as_object().unwrap().is_empty());
+	}
+}
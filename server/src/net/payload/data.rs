@@ -0,0 +1,960 @@
+use ::chrono::{DateTime, Utc};
+use ::chrono::serde::{ts_seconds, ts_seconds_option};
+use ::serde::{Deserialize, Deserializer, Serialize};
+use crate::chat::dice::RollResult;
+use crate::chat::export::ExportFormat;
+use crate::data::{ChannelVisibility, DoorState, GridType, Role, WallKind};
+use crate::net::auth::AuthenticationFailure;
+use crate::net::commands::Commands;
+use crate::net::error::ErrorCode;
+use crate::net::protocol::HelloFailure;
+use crate::net::rooms::Room;
+use crate::scene::grid::{DefaultCellSize, DefaultDistance, DefaultUnit};
+
+/**
+The typed content of a command, with one variant for each of `Commands`.
+
+Serialized as the variant's data alone; the variant is identified by the
+command's `Type`.
+*/
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Payload
+{
+	None(EmptyData),
+	PermissionDenied(PermissionDeniedData),
+	Error(ErrorData),
+	
+	HelloRequest(HelloData),
+	HelloSend(HelloData),
+	HelloFail(HelloFailData),
+	HelloSuccess(HelloSuccessData),
+	
+	AuthenticateRequest(EmptyData),
+	AuthenticateSend(CredentialsData),
+	AuthenticateFail(AuthenticateFailData),
+	AuthenticateSuccess(AuthenticateSuccessData),
+	AuthenticateCreate(CredentialsData),
+	AuthenticateResume(ResumeData),
+	
+	BroadcastRequest(BroadcastData),
+	BroadcastResponse(BroadcastData),
+	BroadcastGetRequest(BroadcastGetData),
+	DirectMessageSend(DirectMessageData),
+	BroadcastHistoryRequest(HistoryRequestData),
+	BroadcastHistoryResponse(HistoryData),
+	BroadcastSearchRequest(SearchData),
+	BroadcastSearchResponse(SearchResultData),
+	BroadcastExportRequest(ExportData),
+	BroadcastExportResponse(ExportResultData),
+	BroadcastEditRequest(EditData),
+	BroadcastDeleteRequest(MessageIdData),
+	BroadcastUpdated(HistoryMessageData),
+	BroadcastRevisionsRequest(MessageIdData),
+	BroadcastRevisionsResponse(RevisionsData),
+	
+	Scene2DRequest(Scene2DRequestData),
+	Scene2DResponse(Scene2DData),
+	Scene2DListRequest(EmptyData),
+	Scene2DListResponse(Scene2DListData),
+	Scene2DCreate(Scene2DCreateData),
+	Scene2DRename(Scene2DRenameData),
+	Scene2DBackgroundUpdate(Scene2DBackgroundData),
+	Scene2DDelete(Scene2DIdData),
+	Scene2DUpdated(Scene2DData),
+	Scene2DRemoved(Scene2DIdData),
+	Scene2DGridUpdate(Scene2DGridData),
+	
+	UserRoleUpdate(UserRoleData),
+	UserRoleUpdated(UserRoleData),
+	
+	RoomJoin(RoomData),
+	RoomLeave(RoomData),
+	RoomJoined(RoomPresenceData),
+	RoomLeft(RoomPresenceData),
+	
+	ChannelListRequest(EmptyData),
+	ChannelListResponse(ChannelListData),
+	ChannelCreate(ChannelCreateData),
+	ChannelUpdate(ChannelUpdateData),
+	ChannelUpdated(ChannelData),
+	ChannelRemoved(ChannelIdData),
+	
+	TokenListRequest(TokenListRequestData),
+	TokenListResponse(TokenListData),
+	TokenCreate(TokenCreateData),
+	TokenMove(TokenMoveData),
+	TokenDelete(TokenIdData),
+	TokenUpdated(TokenData),
+	TokenRemoved(TokenIdData),
+	
+	FogRequest(FogRequestData),
+	FogUpdated(FogData),
+	FogReveal(FogChangeData),
+	FogHide(FogChangeData),
+	FogReset(FogResetData),
+	
+	WallListRequest(WallListRequestData),
+	WallListResponse(WallListData),
+	WallCreate(WallCreateData),
+	WallDoorUpdate(WallDoorData),
+	WallDelete(WallIdData),
+	WallUpdated(WallData),
+	WallRemoved(WallIdData),
+}
+
+impl Payload
+{
+	/**
+	Deserialize the payload of a command whose `Type` is `command`.
+	*/
+	pub fn parse<'de, D>(command: Commands, deserializer: D) -> Result<Self, D::Error>
+		where D: Deserializer<'de>
+	{
+		return Ok(match command
+		{
+			Commands::None => Self::None(Deserialize::deserialize(deserializer)?),
+			Commands::PermissionDenied => Self::PermissionDenied(Deserialize::deserialize(deserializer)?),
+			Commands::Error => Self::Error(Deserialize::deserialize(deserializer)?),
+			
+			Commands::HelloRequest => Self::HelloRequest(Deserialize::deserialize(deserializer)?),
+			Commands::HelloSend => Self::HelloSend(Deserialize::deserialize(deserializer)?),
+			Commands::HelloFail => Self::HelloFail(Deserialize::deserialize(deserializer)?),
+			Commands::HelloSuccess => Self::HelloSuccess(Deserialize::deserialize(deserializer)?),
+			
+			Commands::AuthenticateRequest => Self::AuthenticateRequest(Deserialize::deserialize(deserializer)?),
+			Commands::AuthenticateSend => Self::AuthenticateSend(Deserialize::deserialize(deserializer)?),
+			Commands::AuthenticateFail => Self::AuthenticateFail(Deserialize::deserialize(deserializer)?),
+			Commands::AuthenticateSuccess => Self::AuthenticateSuccess(Deserialize::deserialize(deserializer)?),
+			Commands::AuthenticateCreate => Self::AuthenticateCreate(Deserialize::deserialize(deserializer)?),
+			Commands::AuthenticateResume => Self::AuthenticateResume(Deserialize::deserialize(deserializer)?),
+			
+			Commands::BroadcastRequest => Self::BroadcastRequest(Deserialize::deserialize(deserializer)?),
+			Commands::BroadcastResponse => Self::BroadcastResponse(Deserialize::deserialize(deserializer)?),
+			Commands::BroadcastGetRequest => Self::BroadcastGetRequest(Deserialize::deserialize(deserializer)?),
+			Commands::DirectMessageSend => Self::DirectMessageSend(Deserialize::deserialize(deserializer)?),
+			Commands::BroadcastHistoryRequest => Self::BroadcastHistoryRequest(Deserialize::deserialize(deserializer)?),
+			Commands::BroadcastHistoryResponse => Self::BroadcastHistoryResponse(Deserialize::deserialize(deserializer)?),
+			Commands::BroadcastSearchRequest => Self::BroadcastSearchRequest(Deserialize::deserialize(deserializer)?),
+			Commands::BroadcastSearchResponse => Self::BroadcastSearchResponse(Deserialize::deserialize(deserializer)?),
+			Commands::BroadcastExportRequest => Self::BroadcastExportRequest(Deserialize::deserialize(deserializer)?),
+			Commands::BroadcastExportResponse => Self::BroadcastExportResponse(Deserialize::deserialize(deserializer)?),
+			Commands::BroadcastEditRequest => Self::BroadcastEditRequest(Deserialize::deserialize(deserializer)?),
+			Commands::BroadcastDeleteRequest => Self::BroadcastDeleteRequest(Deserialize::deserialize(deserializer)?),
+			Commands::BroadcastUpdated => Self::BroadcastUpdated(Deserialize::deserialize(deserializer)?),
+			Commands::BroadcastRevisionsRequest => Self::BroadcastRevisionsRequest(Deserialize::deserialize(deserializer)?),
+			Commands::BroadcastRevisionsResponse => Self::BroadcastRevisionsResponse(Deserialize::deserialize(deserializer)?),
+			
+			Commands::Scene2DRequest => Self::Scene2DRequest(Deserialize::deserialize(deserializer)?),
+			Commands::Scene2DResponse => Self::Scene2DResponse(Deserialize::deserialize(deserializer)?),
+			Commands::Scene2DListRequest => Self::Scene2DListRequest(Deserialize::deserialize(deserializer)?),
+			Commands::Scene2DListResponse => Self::Scene2DListResponse(Deserialize::deserialize(deserializer)?),
+			Commands::Scene2DCreate => Self::Scene2DCreate(Deserialize::deserialize(deserializer)?),
+			Commands::Scene2DRename => Self::Scene2DRename(Deserialize::deserialize(deserializer)?),
+			Commands::Scene2DBackgroundUpdate => Self::Scene2DBackgroundUpdate(Deserialize::deserialize(deserializer)?),
+			Commands::Scene2DDelete => Self::Scene2DDelete(Deserialize::deserialize(deserializer)?),
+			Commands::Scene2DUpdated => Self::Scene2DUpdated(Deserialize::deserialize(deserializer)?),
+			Commands::Scene2DRemoved => Self::Scene2DRemoved(Deserialize::deserialize(deserializer)?),
+			Commands::Scene2DGridUpdate => Self::Scene2DGridUpdate(Deserialize::deserialize(deserializer)?),
+			
+			Commands::UserRoleUpdate => Self::UserRoleUpdate(Deserialize::deserialize(deserializer)?),
+			Commands::UserRoleUpdated => Self::UserRoleUpdated(Deserialize::deserialize(deserializer)?),
+			
+			Commands::RoomJoin => Self::RoomJoin(Deserialize::deserialize(deserializer)?),
+			Commands::RoomLeave => Self::RoomLeave(Deserialize::deserialize(deserializer)?),
+			Commands::RoomJoined => Self::RoomJoined(Deserialize::deserialize(deserializer)?),
+			Commands::RoomLeft => Self::RoomLeft(Deserialize::deserialize(deserializer)?),
+			
+			Commands::ChannelListRequest => Self::ChannelListRequest(Deserialize::deserialize(deserializer)?),
+			Commands::ChannelListResponse => Self::ChannelListResponse(Deserialize::deserialize(deserializer)?),
+			Commands::ChannelCreate => Self::ChannelCreate(Deserialize::deserialize(deserializer)?),
+			Commands::ChannelUpdate => Self::ChannelUpdate(Deserialize::deserialize(deserializer)?),
+			Commands::ChannelUpdated => Self::ChannelUpdated(Deserialize::deserialize(deserializer)?),
+			Commands::ChannelRemoved => Self::ChannelRemoved(Deserialize::deserialize(deserializer)?),
+			
+			Commands::TokenListRequest => Self::TokenListRequest(Deserialize::deserialize(deserializer)?),
+			Commands::TokenListResponse => Self::TokenListResponse(Deserialize::deserialize(deserializer)?),
+			Commands::TokenCreate => Self::TokenCreate(Deserialize::deserialize(deserializer)?),
+			Commands::TokenMove => Self::TokenMove(Deserialize::deserialize(deserializer)?),
+			Commands::TokenDelete => Self::TokenDelete(Deserialize::deserialize(deserializer)?),
+			Commands::TokenUpdated => Self::TokenUpdated(Deserialize::deserialize(deserializer)?),
+			Commands::TokenRemoved => Self::TokenRemoved(Deserialize::deserialize(deserializer)?),
+			
+			Commands::FogRequest => Self::FogRequest(Deserialize::deserialize(deserializer)?),
+			Commands::FogUpdated => Self::FogUpdated(Deserialize::deserialize(deserializer)?),
+			Commands::FogReveal => Self::FogReveal(Deserialize::deserialize(deserializer)?),
+			Commands::FogHide => Self::FogHide(Deserialize::deserialize(deserializer)?),
+			Commands::FogReset => Self::FogReset(Deserialize::deserialize(deserializer)?),
+			
+			Commands::WallListRequest => Self::WallListRequest(Deserialize::deserialize(deserializer)?),
+			Commands::WallListResponse => Self::WallListResponse(Deserialize::deserialize(deserializer)?),
+			Commands::WallCreate => Self::WallCreate(Deserialize::deserialize(deserializer)?),
+			Commands::WallDoorUpdate => Self::WallDoorUpdate(Deserialize::deserialize(deserializer)?),
+			Commands::WallDelete => Self::WallDelete(Deserialize::deserialize(deserializer)?),
+			Commands::WallUpdated => Self::WallUpdated(Deserialize::deserialize(deserializer)?),
+			Commands::WallRemoved => Self::WallRemoved(Deserialize::deserialize(deserializer)?),
+		});
+	}
+	
+	/**
+	The command type which carries this payload.
+	*/
+	pub fn command(&self) -> Commands
+	{
+		return match self
+		{
+			Self::None(_) => Commands::None,
+			Self::PermissionDenied(_) => Commands::PermissionDenied,
+			Self::Error(_) => Commands::Error,
+			
+			Self::HelloRequest(_) => Commands::HelloRequest,
+			Self::HelloSend(_) => Commands::HelloSend,
+			Self::HelloFail(_) => Commands::HelloFail,
+			Self::HelloSuccess(_) => Commands::HelloSuccess,
+			
+			Self::AuthenticateRequest(_) => Commands::AuthenticateRequest,
+			Self::AuthenticateSend(_) => Commands::AuthenticateSend,
+			Self::AuthenticateFail(_) => Commands::AuthenticateFail,
+			Self::AuthenticateSuccess(_) => Commands::AuthenticateSuccess,
+			Self::AuthenticateCreate(_) => Commands::AuthenticateCreate,
+			Self::AuthenticateResume(_) => Commands::AuthenticateResume,
+			
+			Self::BroadcastRequest(_) => Commands::BroadcastRequest,
+			Self::BroadcastResponse(_) => Commands::BroadcastResponse,
+			Self::BroadcastGetRequest(_) => Commands::BroadcastGetRequest,
+			Self::DirectMessageSend(_) => Commands::DirectMessageSend,
+			Self::BroadcastHistoryRequest(_) => Commands::BroadcastHistoryRequest,
+			Self::BroadcastHistoryResponse(_) => Commands::BroadcastHistoryResponse,
+			Self::BroadcastSearchRequest(_) => Commands::BroadcastSearchRequest,
+			Self::BroadcastSearchResponse(_) => Commands::BroadcastSearchResponse,
+			Self::BroadcastExportRequest(_) => Commands::BroadcastExportRequest,
+			Self::BroadcastExportResponse(_) => Commands::BroadcastExportResponse,
+			Self::BroadcastEditRequest(_) => Commands::BroadcastEditRequest,
+			Self::BroadcastDeleteRequest(_) => Commands::BroadcastDeleteRequest,
+			Self::BroadcastUpdated(_) => Commands::BroadcastUpdated,
+			Self::BroadcastRevisionsRequest(_) => Commands::BroadcastRevisionsRequest,
+			Self::BroadcastRevisionsResponse(_) => Commands::BroadcastRevisionsResponse,
+			
+			Self::Scene2DRequest(_) => Commands::Scene2DRequest,
+			Self::Scene2DResponse(_) => Commands::Scene2DResponse,
+			Self::Scene2DListRequest(_) => Commands::Scene2DListRequest,
+			Self::Scene2DListResponse(_) => Commands::Scene2DListResponse,
+			Self::Scene2DCreate(_) => Commands::Scene2DCreate,
+			Self::Scene2DRename(_) => Commands::Scene2DRename,
+			Self::Scene2DBackgroundUpdate(_) => Commands::Scene2DBackgroundUpdate,
+			Self::Scene2DDelete(_) => Commands::Scene2DDelete,
+			Self::Scene2DUpdated(_) => Commands::Scene2DUpdated,
+			Self::Scene2DRemoved(_) => Commands::Scene2DRemoved,
+			Self::Scene2DGridUpdate(_) => Commands::Scene2DGridUpdate,
+			
+			Self::UserRoleUpdate(_) => Commands::UserRoleUpdate,
+			Self::UserRoleUpdated(_) => Commands::UserRoleUpdated,
+			
+			Self::RoomJoin(_) => Commands::RoomJoin,
+			Self::RoomLeave(_) => Commands::RoomLeave,
+			Self::RoomJoined(_) => Commands::RoomJoined,
+			Self::RoomLeft(_) => Commands::RoomLeft,
+			
+			Self::ChannelListRequest(_) => Commands::ChannelListRequest,
+			Self::ChannelListResponse(_) => Commands::ChannelListResponse,
+			Self::ChannelCreate(_) => Commands::ChannelCreate,
+			Self::ChannelUpdate(_) => Commands::ChannelUpdate,
+			Self::ChannelUpdated(_) => Commands::ChannelUpdated,
+			Self::ChannelRemoved(_) => Commands::ChannelRemoved,
+			
+			Self::TokenListRequest(_) => Commands::TokenListRequest,
+			Self::TokenListResponse(_) => Commands::TokenListResponse,
+			Self::TokenCreate(_) => Commands::TokenCreate,
+			Self::TokenMove(_) => Commands::TokenMove,
+			Self::TokenDelete(_) => Commands::TokenDelete,
+			Self::TokenUpdated(_) => Commands::TokenUpdated,
+			Self::TokenRemoved(_) => Commands::TokenRemoved,
+			
+			Self::FogRequest(_) => Commands::FogRequest,
+			Self::FogUpdated(_) => Commands::FogUpdated,
+			Self::FogReveal(_) => Commands::FogReveal,
+			Self::FogHide(_) => Commands::FogHide,
+			Self::FogReset(_) => Commands::FogReset,
+			
+			Self::WallListRequest(_) => Commands::WallListRequest,
+			Self::WallListResponse(_) => Commands::WallListResponse,
+			Self::WallCreate(_) => Commands::WallCreate,
+			Self::WallDoorUpdate(_) => Commands::WallDoorUpdate,
+			Self::WallDelete(_) => Commands::WallDelete,
+			Self::WallUpdated(_) => Commands::WallUpdated,
+			Self::WallRemoved(_) => Commands::WallRemoved,
+		};
+	}
+}
+
+/// The payload of commands which carry no data.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+pub struct EmptyData {}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct AuthenticateFailData
+{
+	pub reason: AuthenticationFailure,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct AuthenticateSuccessData
+{
+	pub clientId: i64,
+	/// Token which may be sent with `AuthenticateResume` to reclaim this client id.
+	pub sessionToken: String,
+	pub username: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+pub struct BroadcastData
+{
+	/// The channel the message is sent to, or `None` for the main chat.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub channel: Option<i32>,
+	/// Whether the message was deleted, leaving `text` empty.
+	#[serde(default, skip_deserializing, skip_serializing_if = "std::ops::Not::not")]
+	pub deleted: bool,
+	/// When the message was last edited or deleted.
+	#[serde(default, skip_deserializing, with = "ts_seconds_option", skip_serializing_if = "Option::is_none")]
+	pub edited: Option<DateTime<Utc>>,
+	/// The id of the stored message, which `BroadcastUpdated` refers to.
+	#[serde(default, skip_deserializing, skip_serializing_if = "Option::is_none")]
+	pub id: Option<i32>,
+	/// The username a whisper was sent to. `None` for messages sent to the whole room.
+	#[serde(default, skip_deserializing, skip_serializing_if = "Option::is_none")]
+	pub recipient: Option<String>,
+	/// The dice rolled by the server for a `/roll` message. Never accepted from clients.
+	#[serde(default, skip_deserializing, skip_serializing_if = "Option::is_none")]
+	pub roll: Option<RollResult>,
+	pub text: String,
+}
+
+/// Request for the chat messages sent between `start` and `end`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct BroadcastGetData
+{
+	/// Only include messages sent to this channel, or to the main chat when `0`.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub channel: Option<i32>,
+	#[serde(with = "ts_seconds")]
+	pub end: DateTime<Utc>,
+	#[serde(with = "ts_seconds")]
+	pub start: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct CredentialsData
+{
+	pub name: String,
+	pub password: String,
+}
+
+/// Request to create a channel in the current campaign or lobby.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct ChannelCreateData
+{
+	/// The usernames of the channel's members, who alone may see a `members` channel besides game masters.
+	#[serde(default)]
+	pub members: Vec<String>,
+	pub name: String,
+	#[serde(default)]
+	pub visibility: ChannelVisibility,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct ChannelData
+{
+	pub id: i32,
+	pub members: Vec<String>,
+	pub name: String,
+	pub visibility: ChannelVisibility,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct ChannelIdData
+{
+	pub id: i32,
+}
+
+/// The channels of the current campaign or lobby which the client may see, besides its main chat.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct ChannelListData
+{
+	pub channels: Vec<ChannelData>,
+}
+
+/// Request to change the channel `id`. Fields which are `None` are left unchanged.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct ChannelUpdateData
+{
+	pub id: i32,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub members: Option<Vec<String>>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub name: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub visibility: Option<ChannelVisibility>,
+}
+
+/// A whisper sent only to the user named `recipient`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct DirectMessageData
+{
+	pub recipient: String,
+	pub text: String,
+}
+
+/// Request to replace the text of the message `id`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct EditData
+{
+	pub id: i32,
+	pub text: String,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct ErrorData
+{
+	pub code: ErrorCode,
+	pub message: String,
+}
+
+/// The protocol versions and features offered by one side of the hello exchange.
+/// Request to export the chat log of the current campaign or lobby, optionally limited to messages sent between `start` and `end`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct ExportData
+{
+	/// Only include messages sent to this channel, or to the main chat when `0`.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub channel: Option<i32>,
+	#[serde(default, with = "ts_seconds_option", skip_serializing_if = "Option::is_none")]
+	pub end: Option<DateTime<Utc>>,
+	#[serde(default)]
+	pub format: ExportFormat,
+	#[serde(default, with = "ts_seconds_option", skip_serializing_if = "Option::is_none")]
+	pub start: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct ExportResultData
+{
+	/// The exported chat log.
+	pub content: String,
+	/// A suggested name for the file `content` is saved to.
+	pub fileName: String,
+	pub format: ExportFormat,
+}
+
+/// Request to reveal or hide the polygon with corners at the pixels `points` on the scene `scene`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct FogChangeData
+{
+	pub points: Vec<[i64; 2]>,
+	pub scene: i32,
+	/// The usernames of the only players the change applies to, or every player when empty.
+	#[serde(default)]
+	pub users: Vec<String>,
+}
+
+/// The fog of war covering a scene, as the receiving client sees it.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct FogData
+{
+	/// Without fog the whole scene is revealed.
+	pub enabled: bool,
+	/// Areas revealed or hidden again, in the order they apply.
+	pub regions: Vec<FogRegionData>,
+	/// Whether `regions` replace every region received before, rather than adding to them.
+	pub reset: bool,
+	pub scene: i32,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct FogRegionData
+{
+	pub id: i32,
+	pub points: Vec<[i64; 2]>,
+	pub revealed: bool,
+	/// The username of the only player the region applies to.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub user: Option<String>,
+}
+
+/// Request for the fog of the scene `scene`, or of the client's current scene when `None`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+pub struct FogRequestData
+{
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub scene: Option<i32>,
+}
+
+/// Request to remove every revealed and hidden area of the scene `scene`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct FogResetData
+{
+	/// Whether the scene is covered in fog afterwards, rather than fully revealed. Covered when `None`.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub enabled: Option<bool>,
+	pub scene: i32,
+}
+
+/// The layout of a scene's grid. Fields which are missing take their default values.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct GridData
+{
+	/// Distance in pixels between the centres of neighbouring cells.
+	pub cellSize: i64,
+	/// The distance across a cell, in `unit`s.
+	pub distance: f64,
+	pub kind: GridType,
+	/// Pixel position of the top left corner of the bounds of the cell at `(0, 0)`.
+	pub originX: i64,
+	pub originY: i64,
+	pub unit: String,
+}
+
+impl Default for GridData
+{
+	fn default() -> Self
+	{
+		return Self
+		{
+			cellSize: DefaultCellSize,
+			distance: DefaultDistance,
+			kind: GridType::default(),
+			originX: 0,
+			originY: 0,
+			unit: DefaultUnit.to_string(),
+		};
+	}
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct HelloData
+{
+	#[serde(default)]
+	pub features: Vec<String>,
+	/// The oldest version the sender can speak, or only `version` if omitted.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub minimumVersion: Option<u32>,
+	pub version: u32,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct HelloFailData
+{
+	pub message: String,
+	pub minimumVersion: u32,
+	pub reason: HelloFailure,
+	pub version: u32,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct HelloSuccessData
+{
+	pub features: Vec<String>,
+	pub version: u32,
+}
+
+/// A page of chat history, oldest first.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct HistoryData
+{
+	pub messages: Vec<HistoryMessageData>,
+	/// Whether older messages remain before the first of `messages`.
+	pub more: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct HistoryMessageData
+{
+	pub id: i32,
+	/// The channel the message was sent to, or `None` for the main chat.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub channel: Option<i32>,
+	/// Whether the message was deleted, leaving `text` empty.
+	#[serde(default, skip_serializing_if = "std::ops::Not::not")]
+	pub deleted: bool,
+	/// When the message was last edited or deleted.
+	#[serde(default, with = "ts_seconds_option", skip_serializing_if = "Option::is_none")]
+	pub edited: Option<DateTime<Utc>>,
+	/// The username a whisper was sent to. `None` for messages sent to the whole room.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub recipient: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub roll: Option<RollResult>,
+	/// The user id of the sender, if the message was sent by a user.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub senderId: Option<i32>,
+	pub text: String,
+	#[serde(with = "ts_seconds")]
+	pub timestamp: DateTime<Utc>,
+}
+
+/// Request for a page of the most recent chat messages sent before the message `before`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct HistoryRequestData
+{
+	/// The id of the oldest message already received, or `None` for the latest page.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub before: Option<i32>,
+	/// Only include messages sent to this channel, or to the main chat when `0`.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub channel: Option<i32>,
+	/// The maximum number of messages to return.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub limit: Option<u32>,
+}
+
+/// Identifies a single chat message, to delete it or to list its revisions.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct MessageIdData
+{
+	pub id: i32,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct PermissionDeniedData
+{
+	/// The command which was rejected.
+	pub command: Commands,
+	/// The minimum role which may send `command`, if any role may.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub role: Option<Role>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct ResumeData
+{
+	pub sessionToken: String,
+}
+
+/// Identifies a room. `id` is ignored for the lobby.
+/// A version of a message which was replaced when it was edited or deleted.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct RevisionData
+{
+	/// The text the message held before the change.
+	pub text: String,
+	/// When the change was made.
+	#[serde(with = "ts_seconds")]
+	pub timestamp: DateTime<Utc>,
+	/// The username of whoever made the change.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub user: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct RevisionsData
+{
+	/// The message as it is now.
+	pub message: HistoryMessageData,
+	/// Earlier versions of the message, oldest first.
+	pub revisions: Vec<RevisionData>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct RoomData
+{
+	#[serde(default)]
+	pub id: i32,
+	pub kind: RoomKind,
+}
+
+impl RoomData
+{
+	pub fn room(&self) -> Room
+	{
+		return match self.kind
+		{
+			RoomKind::Lobby => Room::Lobby,
+			RoomKind::Campaign => Room::Campaign(self.id),
+			RoomKind::Scene => Room::Scene(self.id),
+		};
+	}
+}
+
+impl From<Room> for RoomData
+{
+	fn from(value: Room) -> Self
+	{
+		return match value
+		{
+			Room::Lobby => Self { id: 0, kind: RoomKind::Lobby },
+			Room::Campaign(id) => Self { id, kind: RoomKind::Campaign },
+			Room::Scene(id) => Self { id, kind: RoomKind::Scene },
+		};
+	}
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RoomKind
+{
+	Lobby,
+	Campaign,
+	Scene,
+}
+
+/// Sent to the members of a room when a client joins or leaves it.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct RoomPresenceData
+{
+	/// The client which joined or left.
+	pub clientId: i64,
+	pub id: i32,
+	pub kind: RoomKind,
+	/// Usernames of the room's members after the change.
+	pub members: Vec<String>,
+	pub username: String,
+}
+
+/// Request to replace the background of the scene `id`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct Scene2DBackgroundData
+{
+	/// Path of the background image, relative to the server's assets directory.
+	pub background: String,
+	pub id: i32,
+}
+
+/// Request to create a scene in the current campaign or lobby.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Scene2DCreateData
+{
+	/// Path of the background image, relative to the server's assets directory.
+	pub background: String,
+	/// A square grid of 100 pixel, 5 ft cells when `None`.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub grid: Option<GridData>,
+	pub name: String,
+}
+
+/// A scene along with the size of its background image in pixels.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Scene2DData
+{
+	/// Path of the background image, relative to the server's assets directory.
+	pub background: String,
+	pub grid: GridData,
+	pub height: i64,
+	pub id: i32,
+	pub name: String,
+	pub width: i64,
+}
+
+/// Request to replace the grid of the scene `id`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Scene2DGridData
+{
+	pub grid: GridData,
+	pub id: i32,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct Scene2DIdData
+{
+	pub id: i32,
+}
+
+/// The scenes of the current campaign or lobby.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Scene2DListData
+{
+	pub scenes: Vec<Scene2DData>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct Scene2DRenameData
+{
+	pub id: i32,
+	pub name: String,
+}
+
+/// Request for the scene `id`, or for the client's current scene when `None`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+pub struct Scene2DRequestData
+{
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub id: Option<i32>,
+}
+
+/// A full text search of the chat log of the current campaign or lobby.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct SearchData
+{
+	/// Only include messages sent to this channel, or to the main chat when `0`.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub channel: Option<i32>,
+	/// Only include messages sent at or before this time.
+	#[serde(default, with = "ts_seconds_option", skip_serializing_if = "Option::is_none")]
+	pub end: Option<DateTime<Utc>>,
+	/// The maximum number of hits to return.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub limit: Option<u32>,
+	/// Only include messages sent at or after this time.
+	#[serde(default, with = "ts_seconds_option", skip_serializing_if = "Option::is_none")]
+	pub start: Option<DateTime<Utc>>,
+	/// Words which must all appear in a message. A trailing `*` matches any word beginning with the rest.
+	pub terms: String,
+	/// Only include messages sent by the user with this username.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub user: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct SearchHitData
+{
+	pub message: HistoryMessageData,
+	/// An excerpt of the message with the matched words wrapped in `[b]` and `[/b]`.
+	pub snippet: String,
+}
+
+/// The messages matching a search, best match first.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct SearchResultData
+{
+	pub hits: Vec<SearchHitData>,
+}
+
+/// Request to place a token on the scene `scene`, centred on the pixel `(x, y)`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct TokenCreateData
+{
+	/// Height in pixels, 100 when `None`.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub height: Option<i64>,
+	#[serde(default)]
+	pub hidden: bool,
+	/// Path of the token's image, relative to the server's assets directory.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub image: Option<String>,
+	pub name: String,
+	/// The username of the player who may move the token.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub owner: Option<String>,
+	pub scene: i32,
+	/// Width in pixels, 100 when `None`.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub width: Option<i64>,
+	pub x: i64,
+	pub y: i64,
+}
+
+/// A token on a scene, centred on the pixel `(x, y)`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+pub struct TokenData
+{
+	pub height: i64,
+	pub hidden: bool,
+	pub id: i32,
+	/// Path of the token's image, relative to the server's assets directory.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub image: Option<String>,
+	pub name: String,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub owner: Option<String>,
+	pub scene: i32,
+	pub width: i64,
+	pub x: i64,
+	pub y: i64,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct TokenIdData
+{
+	pub id: i32,
+}
+
+/// The tokens on a scene which the client may see.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct TokenListData
+{
+	pub scene: i32,
+	pub tokens: Vec<TokenData>,
+}
+
+/// Request for the tokens on the scene `scene`, or on the client's current scene when `None`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+pub struct TokenListRequestData
+{
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub scene: Option<i32>,
+}
+
+/// Request to move the centre of the token `id` to the pixel `(x, y)`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct TokenMoveData
+{
+	pub id: i32,
+	pub x: i64,
+	pub y: i64,
+}
+
+/// The role of the user `name`, within the campaign `campaign` or server wide when it is `None`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct UserRoleData
+{
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub campaign: Option<i32>,
+	pub name: String,
+	pub role: Role,
+}
+
+/// Request to add a wall from the pixel `start` to the pixel `end` of the scene `scene`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct WallCreateData
+{
+	/// The state of a new door, which is closed when `None`. Ignored for other kinds of wall.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub door: Option<DoorState>,
+	pub end: [i64; 2],
+	#[serde(default)]
+	pub kind: WallKind,
+	pub scene: i32,
+	pub start: [i64; 2],
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct WallData
+{
+	/// Whether a door is open, closed or locked. `None` for other kinds of wall.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub door: Option<DoorState>,
+	pub end: [i64; 2],
+	pub id: i32,
+	pub kind: WallKind,
+	pub scene: i32,
+	pub start: [i64; 2],
+}
+
+/// Request to open, close or lock the door `id`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct WallDoorData
+{
+	pub id: i32,
+	pub state: DoorState,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct WallIdData
+{
+	pub id: i32,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct WallListData
+{
+	pub scene: i32,
+	pub walls: Vec<WallData>,
+}
+
+/// Request for the walls of the scene `scene`, or of the client's current scene when `None`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+pub struct WallListRequestData
+{
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub scene: Option<i32>,
+}
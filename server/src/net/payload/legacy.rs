@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+use ::serde::de::{Error as _, IntoDeserializer, Unexpected, Visitor};
+use ::serde::de::value::{Error, MapDeserializer, SeqDeserializer};
+use ::serde::forward_to_deserialize_any;
+use ::serde_json::Value;
+
+/**
+Deserializes a payload from the string map sent in `Data` by clients which do
+not use typed payloads.
+
+Each field is held as a string and parsed into the type the payload expects.
+Lists are comma separated.
+*/
+pub struct LegacyData<'a>(pub &'a HashMap<String, String>);
+
+impl<'de> ::serde::Deserializer<'de> for LegacyData<'_>
+{
+	type Error = Error;
+	
+	fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+		where V: Visitor<'de>
+	{
+		let entries = self.0.iter()
+			.map(|(key, value)| (key.as_str(), LegacyValue(value)));
+		
+		return visitor.visit_map(MapDeserializer::new(entries));
+	}
+	
+	forward_to_deserialize_any! {
+		bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+		bytes byte_buf option unit unit_struct newtype_struct seq tuple
+		tuple_struct map struct enum identifier ignored_any
+	}
+}
+
+/**
+A single value of a legacy `Data` map.
+*/
+struct LegacyValue<'a>(&'a str);
+
+/// Parse the string as the requested number type before visiting it.
+macro_rules! deserializeParsed {
+	($($method:ident => $visit:ident: $type:ty,)*) => {
+		$(
+			fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+				where V: Visitor<'de>
+			{
+				return match self.0.trim().parse::<$type>()
+				{
+					Ok(value) => visitor.$visit(value),
+					Err(_) => Err(Error::invalid_value(Unexpected::Str(self.0), &stringify!($type))),
+				};
+			}
+		)*
+	};
+}
+
+impl<'de> ::serde::Deserializer<'de> for LegacyValue<'_>
+{
+	type Error = Error;
+	
+	fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+		where V: Visitor<'de>
+	{
+		return visitor.visit_str(self.0);
+	}
+	
+	fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+		where V: Visitor<'de>
+	{
+		return visitor.visit_some(self);
+	}
+	
+	fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+		where V: Visitor<'de>
+	{
+		let items = self.0.split(',')
+			.map(|item| item.trim())
+			.filter(|item| !item.is_empty())
+			.map(LegacyValue);
+		
+		return visitor.visit_seq(SeqDeserializer::new(items));
+	}
+	
+	fn deserialize_enum<V>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error>
+		where V: Visitor<'de>
+	{
+		return visitor.visit_enum(self.0.into_deserializer());
+	}
+	
+	deserializeParsed! {
+		deserialize_bool => visit_bool: bool,
+		deserialize_i8 => visit_i8: i8,
+		deserialize_i16 => visit_i16: i16,
+		deserialize_i32 => visit_i32: i32,
+		deserialize_i64 => visit_i64: i64,
+		deserialize_u8 => visit_u8: u8,
+		deserialize_u16 => visit_u16: u16,
+		deserialize_u32 => visit_u32: u32,
+		deserialize_u64 => visit_u64: u64,
+		deserialize_f32 => visit_f32: f32,
+		deserialize_f64 => visit_f64: f64,
+	}
+	
+	forward_to_deserialize_any! {
+		i128 u128 char str string bytes byte_buf unit unit_struct
+		newtype_struct tuple tuple_struct map struct identifier ignored_any
+	}
+}
+
+impl<'de> IntoDeserializer<'de, Error> for LegacyValue<'_>
+{
+	type Deserializer = Self;
+	
+	fn into_deserializer(self) -> Self
+	{
+		return self;
+	}
+}
+
+/**
+Convert a serialized payload into the string map sent in `Data` to clients
+which do not use typed payloads.
+
+Lists of plain values are comma separated. Objects, and lists holding objects
+or lists, are sent as JSON text.
+*/
+pub fn flattenPayload(payload: Value) -> HashMap<String, String>
+{
+	let fields = match payload
+	{
+		Value::Object(fields) => fields,
+		_ => return HashMap::default(),
+	};
+	
+	return fields.into_iter()
+		.filter_map(|(key, value)| flattenValue(value).map(|v| (key, v)))
+		.collect();
+}
+
+fn flattenValue(value: Value) -> Option<String>
+{
+	return match value
+	{
+		Value::Null => None,
+		Value::String(text) => Some(text),
+		Value::Array(items) if items.iter().all(|item| !item.is_array() && !item.is_object()) => Some(
+			items.into_iter()
+				.filter_map(flattenValue)
+				.collect::<Vec<_>>()
+				.join(",")
+		),
+		other => Some(other.to_string()),
+	};
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use ::serde::Deserialize;
+	
+	#[derive(Debug, Deserialize, PartialEq)]
+	#[serde(rename_all = "camelCase")]
+	enum Kind
+	{
+		First,
+		SecondKind,
+	}
+	
+	#[derive(Debug, Deserialize, PartialEq)]
+	struct Example
+	{
+		count: i64,
+		flag: bool,
+		kind: Kind,
+		list: Vec<String>,
+		name: String,
+		optional: Option<u32>,
+	}
+	
+	fn map(entries: &[(&str, &str)]) -> HashMap<String, String>
+	{
+		return entries.iter()
+			.map(|(k, v)| (k.to_string(), v.to_string()))
+			.collect();
+	}
+	
+	#[test]
+	fn parsesFields()
+	{
+		let data = map(&[("count", "-42"), ("flag", "true"), ("kind", "secondKind"), ("list", "a, b,,c"), ("name", "123"), ("ignored", "x")]);
+		let example = Example::deserialize(LegacyData(&data)).expect("Error parsing legacy data");
+		
+		assert_eq!(example, Example
+		{
+			count: -42,
+			flag: true,
+			kind: Kind::SecondKind,
+			list: vec!["a".into(), "b".into(), "c".into()],
+			name: "123".into(),
+			optional: None,
+		});
+	}
+	
+	#[test]
+	fn rejectsInvalidFields()
+	{
+		let badNumber = map(&[("count", "many"), ("flag", "true"), ("kind", "first"), ("list", ""), ("name", "")]);
+		assert!(Example::deserialize(LegacyData(&badNumber)).is_err());
+		
+		let badKind = map(&[("count", "1"), ("flag", "true"), ("kind", "third"), ("list", ""), ("name", "")]);
+		assert!(Example::deserialize(LegacyData(&badKind)).is_err());
+		
+		let missing = map(&[("count", "1"), ("flag", "true"), ("kind", "first"), ("list", "")]);
+		assert!(Example::deserialize(LegacyData(&missing)).is_err());
+	}
+	
+	#[test]
+	fn flattens()
+	{
+		let value = serde_json::json!({
+			"absent": null,
+			"features": ["binaryFrames", "typedPayloads"],
+			"id": 7,
+			"messages": [{ "id": 1 }, { "id": 2 }],
+			"text": "hello",
+		});
+		
+		let flattened = flattenPayload(value);
+		assert_eq!(flattened, map(&[
+			("features", "binaryFrames,typedPayloads"),
+			("id", "7"),
+			("messages", r#"[{"id":1},{"id":2}]"#),
+			("text", "hello"),
+		]));
+	}
+}
@@ -0,0 +1,15 @@
+mod command;
+mod data;
+mod legacy;
+
+pub use command::Command;
+pub use data::{AuthenticateFailData, AuthenticateSuccessData, BroadcastData, BroadcastGetData,
+	ChannelCreateData, ChannelData, ChannelIdData, ChannelListData, ChannelUpdateData, CredentialsData,
+	DirectMessageData, EditData, EmptyData, ErrorData, ExportData, ExportResultData, FogChangeData, FogData,
+	FogRegionData, FogRequestData, FogResetData, GridData, HelloData,
+	HelloFailData, HelloSuccessData, HistoryData, HistoryMessageData, HistoryRequestData, MessageIdData,
+	Payload, PermissionDeniedData, ResumeData, RevisionData, RevisionsData, RoomData, RoomPresenceData,
+	Scene2DBackgroundData, Scene2DCreateData, Scene2DData, Scene2DGridData, Scene2DIdData, Scene2DListData,
+	Scene2DRenameData, Scene2DRequestData, SearchData, SearchHitData, SearchResultData, TokenCreateData, TokenData,
+	TokenIdData, TokenListData, TokenListRequestData, TokenMoveData, UserRoleData, WallCreateData, WallData,
+	WallDoorData, WallIdData, WallListData, WallListRequestData};
@@ -0,0 +1,218 @@
+use crate::data::Role;
+use super::commands::Commands;
+
+/**
+Who may send a given command to the server.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access
+{
+	/// Any client, including those which have not yet authenticated.
+	Public,
+	/// Authenticated clients whose role is at least the given role.
+	Minimum(Role),
+	/// Commands which are only ever sent by the server.
+	ServerOnly,
+}
+
+/**
+Determine who may send `command`.
+*/
+pub fn access(command: Commands) -> Access
+{
+	return match command
+	{
+		Commands::AuthenticateCreate
+			| Commands::AuthenticateResume
+			| Commands::AuthenticateSend
+			| Commands::HelloSend => Access::Public,
+		
+		Commands::BroadcastExportRequest
+			| Commands::BroadcastGetRequest
+			| Commands::BroadcastHistoryRequest
+			| Commands::BroadcastSearchRequest
+			| Commands::ChannelListRequest
+			| Commands::FogRequest
+			| Commands::RoomJoin
+			| Commands::RoomLeave
+			| Commands::Scene2DListRequest
+			| Commands::Scene2DRequest
+			| Commands::TokenListRequest
+			| Commands::WallListRequest => Access::Minimum(Role::Spectator),
+		
+		Commands::BroadcastDeleteRequest
+			| Commands::BroadcastEditRequest
+			| Commands::BroadcastRequest
+			| Commands::DirectMessageSend
+			| Commands::TokenMove
+			| Commands::WallDoorUpdate => Access::Minimum(Role::Player),
+		
+		Commands::BroadcastRevisionsRequest
+			| Commands::ChannelCreate
+			| Commands::ChannelUpdate
+			| Commands::FogHide
+			| Commands::FogReset
+			| Commands::FogReveal
+			| Commands::Scene2DBackgroundUpdate
+			| Commands::Scene2DCreate
+			| Commands::Scene2DDelete
+			| Commands::Scene2DGridUpdate
+			| Commands::Scene2DRename
+			| Commands::TokenCreate
+			| Commands::TokenDelete
+			| Commands::UserRoleUpdate
+			| Commands::WallCreate
+			| Commands::WallDelete => Access::Minimum(Role::GameMaster),
+		
+		Commands::None
+			| Commands::PermissionDenied
+			| Commands::Error
+			| Commands::AuthenticateFail
+			| Commands::AuthenticateRequest
+			| Commands::AuthenticateSuccess
+			| Commands::BroadcastExportResponse
+			| Commands::BroadcastHistoryResponse
+			| Commands::BroadcastResponse
+			| Commands::BroadcastRevisionsResponse
+			| Commands::BroadcastSearchResponse
+			| Commands::BroadcastUpdated
+			| Commands::ChannelListResponse
+			| Commands::ChannelRemoved
+			| Commands::ChannelUpdated
+			| Commands::FogUpdated
+			| Commands::HelloFail
+			| Commands::HelloRequest
+			| Commands::HelloSuccess
+			| Commands::RoomJoined
+			| Commands::RoomLeft
+			| Commands::Scene2DListResponse
+			| Commands::Scene2DRemoved
+			| Commands::Scene2DResponse
+			| Commands::Scene2DUpdated
+			| Commands::TokenListResponse
+			| Commands::TokenRemoved
+			| Commands::TokenUpdated
+			| Commands::UserRoleUpdated
+			| Commands::WallListResponse
+			| Commands::WallRemoved
+			| Commands::WallUpdated => Access::ServerOnly,
+	};
+}
+
+/**
+The role used for permission checks.
+
+A user's role within the current campaign, when there is one, takes
+precedence over their server wide role.
+*/
+pub fn effectiveRole(userRole: Role, campaignRole: Option<Role>) -> Role
+{
+	return match campaignRole
+	{
+		None => userRole,
+		Some(role) => role,
+	};
+}
+
+/**
+Check whether a user may join a campaign, given their server wide role and
+their role within the campaign, if they have one.
+
+Only members of the campaign and server wide game masters may join it.
+*/
+pub fn isCampaignMember(userRole: Role, campaignRole: Option<Role>) -> bool
+{
+	return userRole == Role::GameMaster || campaignRole.is_some();
+}
+
+/**
+Check whether a client with `role` may send `command`.
+
+`role` is `None` for clients which have not yet authenticated.
+*/
+pub fn isAllowed(role: Option<Role>, command: Commands) -> bool
+{
+	return match access(command)
+	{
+		Access::Public => true,
+		Access::Minimum(minimum) => role.is_some_and(|r| r >= minimum),
+		Access::ServerOnly => false,
+	};
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	
+	#[test]
+	fn unauthenticated()
+	{
+		assert!(isAllowed(None, Commands::AuthenticateSend));
+		assert!(isAllowed(None, Commands::AuthenticateCreate));
+		assert!(isAllowed(None, Commands::AuthenticateResume));
+		assert!(isAllowed(None, Commands::HelloSend));
+		assert!(!isAllowed(None, Commands::BroadcastGetRequest));
+		assert!(!isAllowed(None, Commands::BroadcastRequest));
+	}
+	
+	#[test]
+	fn roles()
+	{
+		let spectator = Some(Role::Spectator);
+		assert!(isAllowed(spectator, Commands::BroadcastGetRequest));
+		assert!(isAllowed(spectator, Commands::Scene2DRequest));
+		assert!(isAllowed(spectator, Commands::RoomJoin));
+		assert!(!isAllowed(spectator, Commands::BroadcastRequest));
+		assert!(!isAllowed(spectator, Commands::DirectMessageSend));
+		assert!(!isAllowed(spectator, Commands::UserRoleUpdate));
+		
+		let player = Some(Role::Player);
+		assert!(isAllowed(player, Commands::BroadcastGetRequest));
+		assert!(isAllowed(player, Commands::BroadcastRequest));
+		assert!(isAllowed(player, Commands::DirectMessageSend));
+		assert!(isAllowed(player, Commands::BroadcastEditRequest));
+		assert!(!isAllowed(player, Commands::BroadcastRevisionsRequest));
+		assert!(!isAllowed(player, Commands::UserRoleUpdate));
+		assert!(!isAllowed(player, Commands::Scene2DCreate));
+		assert!(isAllowed(player, Commands::TokenMove));
+		assert!(!isAllowed(player, Commands::TokenCreate));
+		assert!(!isAllowed(player, Commands::FogReveal));
+		assert!(isAllowed(spectator, Commands::FogRequest));
+		assert!(isAllowed(player, Commands::WallDoorUpdate));
+		assert!(!isAllowed(player, Commands::WallCreate));
+		
+		let gm = Some(Role::GameMaster);
+		assert!(isAllowed(gm, Commands::BroadcastRequest));
+		assert!(isAllowed(gm, Commands::UserRoleUpdate));
+		assert!(isAllowed(gm, Commands::Scene2DDelete));
+	}
+	
+	#[test]
+	fn serverOnly()
+	{
+		let gm = Some(Role::GameMaster);
+		assert!(!isAllowed(gm, Commands::AuthenticateSuccess));
+		assert!(!isAllowed(gm, Commands::BroadcastResponse));
+		assert!(!isAllowed(gm, Commands::PermissionDenied));
+		assert!(!isAllowed(gm, Commands::FogUpdated));
+	}
+	
+	#[test]
+	fn campaignRoleOverrides()
+	{
+		assert_eq!(effectiveRole(Role::Player, None), Role::Player);
+		assert_eq!(effectiveRole(Role::Player, Some(Role::GameMaster)), Role::GameMaster);
+		assert_eq!(effectiveRole(Role::GameMaster, Some(Role::Spectator)), Role::Spectator);
+	}
+	
+	#[test]
+	fn campaignMembership()
+	{
+		assert!(isCampaignMember(Role::GameMaster, None));
+		assert!(isCampaignMember(Role::Player, Some(Role::Player)));
+		assert!(isCampaignMember(Role::Spectator, Some(Role::Spectator)));
+		assert!(!isCampaignMember(Role::Player, None));
+		assert!(!isCampaignMember(Role::Spectator, None));
+	}
+}
@@ -0,0 +1,186 @@
+use std::collections::BTreeSet;
+use std::str::FromStr;
+use ::anyhow::{Error, Result};
+use ::serde_repr::{Deserialize_repr, Serialize_repr};
+
+/// The protocol version spoken by this server.
+pub const ProtocolVersion: u32 = 1;
+/// The oldest protocol version this server can still speak.
+pub const MinimumProtocolVersion: u32 = 1;
+
+/**
+Optional protocol features which must be supported by both sides before use.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Feature
+{
+	/// `BinaryData` sent as binary WebSocket frames instead of base64.
+	BinaryFrames,
+	/// Compressed message payloads.
+	Compression,
+	/// Commands with typed payloads instead of string maps.
+	TypedPayloads,
+}
+
+impl Feature
+{
+	pub fn name(&self) -> &'static str
+	{
+		return match self
+		{
+			Self::BinaryFrames => "binaryFrames",
+			Self::Compression => "compression",
+			Self::TypedPayloads => "typedPayloads",
+		};
+	}
+}
+
+impl FromStr for Feature
+{
+	type Err = Error;
+	
+	fn from_str(s: &str) -> Result<Self>
+	{
+		return match s
+		{
+			"binaryFrames" => Ok(Self::BinaryFrames),
+			"compression" => Ok(Self::Compression),
+			"typedPayloads" => Ok(Self::TypedPayloads),
+			_ => Err(Error::msg(format!("Unknown feature: {}", s))),
+		};
+	}
+}
+
+/**
+The features implemented by this server.
+*/
+pub fn serverFeatures() -> BTreeSet<Feature>
+{
+	return BTreeSet::from([
+		Feature::BinaryFrames,
+		Feature::TypedPayloads,
+	]);
+}
+
+/**
+List the names of a set of features, as sent in hello commands.
+*/
+pub fn featureNames(features: &BTreeSet<Feature>) -> Vec<String>
+{
+	return features.iter()
+		.map(|f| f.name().to_string())
+		.collect();
+}
+
+/**
+Parse a list of feature names. Unknown features are ignored, as they may have
+been added by a newer client.
+*/
+pub fn parseFeatures(names: &[String]) -> BTreeSet<Feature>
+{
+	return names.iter()
+		.filter_map(|name| name.parse::<Feature>().ok())
+		.collect();
+}
+
+/**
+The reason sent in the `reason` field of a `HelloFail` command.
+*/
+#[derive(Clone, Copy, Debug, Deserialize_repr, PartialEq, Eq, Serialize_repr)]
+#[repr(i32)]
+pub enum HelloFailure
+{
+	/// The `HelloSend` could not be parsed, usually because `version` was missing or not a number.
+	MissingVersion = 1,
+	/// There is no protocol version supported by both the client and server.
+	IncompatibleVersion,
+	/// A command other than `HelloSend` was sent before the hello exchange completed.
+	HelloRequired,
+}
+
+/**
+The outcome of a successful hello exchange.
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Negotiated
+{
+	pub version: u32,
+	pub features: BTreeSet<Feature>,
+}
+
+/**
+Agree on the protocol version and features to use with a client.
+
+The newest version spoken by both sides is chosen, provided it is no older
+than either side's minimum. A client which does not send a minimum is assumed
+to speak only its own version.
+*/
+pub fn negotiate(clientVersion: u32, clientMinimum: Option<u32>, clientFeatures: &BTreeSet<Feature>) -> Result<Negotiated, HelloFailure>
+{
+	let version = clientVersion.min(ProtocolVersion);
+	let minimum = clientMinimum.unwrap_or(clientVersion)
+		.max(MinimumProtocolVersion);
+	
+	if version < minimum
+	{
+		return Err(HelloFailure::IncompatibleVersion);
+	}
+	
+	return Ok(Negotiated
+	{
+		version,
+		features: serverFeatures()
+			.intersection(clientFeatures)
+			.copied()
+			.collect(),
+	});
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	
+	#[test]
+	fn features()
+	{
+		let names = ["compression", "somethingNew", "binaryFrames"].map(String::from);
+		let parsed = parseFeatures(&names);
+		assert_eq!(parsed, BTreeSet::from([Feature::BinaryFrames, Feature::Compression]));
+		assert_eq!(featureNames(&parsed), vec!["binaryFrames", "compression"]);
+		assert!(parseFeatures(&[]).is_empty());
+	}
+	
+	#[test]
+	fn matchingVersion()
+	{
+		let clientFeatures = BTreeSet::from([Feature::BinaryFrames, Feature::Compression]);
+		let negotiated = negotiate(ProtocolVersion, None, &clientFeatures)
+			.expect("Matching versions should be compatible");
+		
+		assert_eq!(negotiated.version, ProtocolVersion);
+		assert_eq!(negotiated.features, BTreeSet::from([Feature::BinaryFrames]));
+	}
+	
+	#[test]
+	fn newerClient()
+	{
+		let negotiated = negotiate(ProtocolVersion + 5, Some(MinimumProtocolVersion), &BTreeSet::new())
+			.expect("A newer client which can speak our version should be compatible");
+		assert_eq!(negotiated.version, ProtocolVersion);
+		assert!(negotiated.features.is_empty());
+		
+		let result = negotiate(ProtocolVersion + 5, Some(ProtocolVersion + 1), &BTreeSet::new());
+		assert_eq!(result, Err(HelloFailure::IncompatibleVersion));
+		
+		let result = negotiate(ProtocolVersion + 1, None, &BTreeSet::new());
+		assert_eq!(result, Err(HelloFailure::IncompatibleVersion));
+	}
+	
+	#[test]
+	fn olderClient()
+	{
+		let result = negotiate(MinimumProtocolVersion - 1, None, &BTreeSet::new());
+		assert_eq!(result, Err(HelloFailure::IncompatibleVersion));
+	}
+}
@@ -0,0 +1,241 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Mutex, OnceLock};
+
+pub fn getRooms() -> &'static Mutex<Rooms>
+{
+	static RoomsLock: OnceLock<Mutex<Rooms>> = OnceLock::new();
+	return RoomsLock.get_or_init(|| Mutex::new(Rooms::default()));
+}
+
+/**
+A group of clients which receive each other's broadcasts.
+*/
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Room
+{
+	/// Authenticated clients which have not joined a campaign.
+	Lobby,
+	Campaign(i32),
+	Scene(i32),
+}
+
+impl Room
+{
+	/**
+	Whether chat and presence events are scoped to this room.
+	*/
+	pub fn isChat(&self) -> bool
+	{
+		return match self
+		{
+			Self::Lobby | Self::Campaign(_) => true,
+			Self::Scene(_) => false,
+		};
+	}
+}
+
+/**
+The rooms joined and left as the result of a single change in membership.
+*/
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RoomChanges
+{
+	pub joined: Vec<Room>,
+	pub left: Vec<Room>,
+}
+
+/**
+Tracks which client ids are members of each room.
+
+Every client belongs to exactly one chat room, either the lobby or a single
+campaign, and to at most one scene. Leaving a campaign also leaves its scene
+and returns the client to the lobby.
+*/
+#[derive(Default)]
+pub struct Rooms
+{
+	members: HashMap<Room, BTreeSet<i64>>,
+}
+
+impl Rooms
+{
+	/**
+	The chat room of the client `id`, or `None` if it is not in any room.
+	*/
+	pub fn chatRoom(&self, id: i64) -> Option<Room>
+	{
+		return self.roomsOf(id)
+			.into_iter()
+			.find(|room| room.isChat());
+	}
+	
+	/**
+	Add the client `id` to `room`, leaving any rooms it may not be in at the
+	same time.
+	*/
+	pub fn join(&mut self, id: i64, room: Room) -> RoomChanges
+	{
+		let mut changes = RoomChanges::default();
+		
+		for current in self.roomsOf(id)
+		{
+			let conflicts = match room
+			{
+				//A new chat room replaces the old one, and scenes belong to the campaign being left
+				Room::Lobby | Room::Campaign(_) => current != room,
+				Room::Scene(_) => matches!(current, Room::Scene(_)) && current != room,
+			};
+			
+			if conflicts
+			{
+				self.remove(id, current);
+				changes.left.push(current);
+			}
+		}
+		
+		if self.members.entry(room).or_default().insert(id)
+		{
+			changes.joined.push(room);
+		}
+		
+		return changes;
+	}
+	
+	/**
+	Remove the client `id` from `room`.
+	
+	Leaving a campaign also leaves the current scene and rejoins the lobby. The
+	lobby itself can only be left by joining a campaign.
+	*/
+	pub fn leave(&mut self, id: i64, room: Room) -> RoomChanges
+	{
+		let mut changes = RoomChanges::default();
+		
+		if room == Room::Lobby || !self.members(room).contains(&id)
+		{
+			return changes;
+		}
+		
+		if let Room::Campaign(_) = room
+		{
+			return self.join(id, Room::Lobby);
+		}
+		
+		self.remove(id, room);
+		changes.left.push(room);
+		return changes;
+	}
+	
+	/**
+	Remove the client `id` from every room, returning the rooms it was in.
+	*/
+	pub fn leaveAll(&mut self, id: i64) -> Vec<Room>
+	{
+		let rooms = self.roomsOf(id);
+		for room in rooms.iter()
+		{
+			self.remove(id, *room);
+		}
+		
+		return rooms;
+	}
+	
+	/**
+	The client ids in `room`.
+	*/
+	pub fn members(&self, room: Room) -> BTreeSet<i64>
+	{
+		return self.members.get(&room)
+			.cloned()
+			.unwrap_or_default();
+	}
+	
+	/**
+	The rooms the client `id` is in.
+	*/
+	pub fn roomsOf(&self, id: i64) -> Vec<Room>
+	{
+		let mut rooms = self.members.iter()
+			.filter(|(_, members)| members.contains(&id))
+			.map(|(room, _)| *room)
+			.collect::<Vec<_>>();
+		
+		rooms.sort();
+		return rooms;
+	}
+	
+	fn remove(&mut self, id: i64, room: Room)
+	{
+		if let Some(members) = self.members.get_mut(&room)
+		{
+			members.remove(&id);
+			if members.is_empty()
+			{
+				self.members.remove(&room);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	
+	#[test]
+	fn separateCampaigns()
+	{
+		let mut rooms = Rooms::default();
+		rooms.join(1, Room::Lobby);
+		rooms.join(2, Room::Lobby);
+		
+		let changes = rooms.join(1, Room::Campaign(10));
+		assert_eq!(changes.left, vec![Room::Lobby]);
+		assert_eq!(changes.joined, vec![Room::Campaign(10)]);
+		
+		rooms.join(2, Room::Campaign(20));
+		
+		assert_eq!(rooms.members(Room::Campaign(10)), BTreeSet::from([1]));
+		assert_eq!(rooms.members(Room::Campaign(20)), BTreeSet::from([2]));
+		assert!(rooms.members(Room::Lobby).is_empty());
+		assert_eq!(rooms.chatRoom(1), Some(Room::Campaign(10)));
+	}
+	
+	#[test]
+	fn scenes()
+	{
+		let mut rooms = Rooms::default();
+		rooms.join(1, Room::Campaign(10));
+		rooms.join(1, Room::Scene(5));
+		
+		let changes = rooms.join(1, Room::Scene(6));
+		assert_eq!(changes.left, vec![Room::Scene(5)]);
+		assert_eq!(rooms.roomsOf(1), vec![Room::Campaign(10), Room::Scene(6)]);
+		
+		//Rejoining the current room changes nothing
+		assert_eq!(rooms.join(1, Room::Scene(6)), RoomChanges::default());
+		
+		let changes = rooms.join(1, Room::Campaign(11));
+		assert_eq!(changes.left, vec![Room::Campaign(10), Room::Scene(6)]);
+		assert_eq!(rooms.roomsOf(1), vec![Room::Campaign(11)]);
+	}
+	
+	#[test]
+	fn leaving()
+	{
+		let mut rooms = Rooms::default();
+		rooms.join(1, Room::Campaign(10));
+		rooms.join(1, Room::Scene(5));
+		
+		assert_eq!(rooms.leave(1, Room::Lobby), RoomChanges::default());
+		assert_eq!(rooms.leave(1, Room::Scene(7)), RoomChanges::default());
+		
+		let changes = rooms.leave(1, Room::Campaign(10));
+		assert_eq!(changes.left, vec![Room::Campaign(10), Room::Scene(5)]);
+		assert_eq!(changes.joined, vec![Room::Lobby]);
+		
+		assert_eq!(rooms.leaveAll(1), vec![Room::Lobby]);
+		assert!(rooms.roomsOf(1).is_empty());
+		assert_eq!(rooms.chatRoom(1), None);
+	}
+}
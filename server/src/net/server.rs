@@ -7,10 +7,16 @@ use ::hyper::server::conn::http1;
 use ::hyper::service::service_fn;
 use ::hyper_util::rt::TokioIo;
 use ::log::{error, info};
+use ::tokio::io::{AsyncRead, AsyncWrite};
 use ::tokio::net::TcpListener;
+use ::tokio::time::{interval, Duration};
 use ::tokio_util::sync::CancellationToken;
 use crate::getConfig;
-use super::client::WebSocketClient;
+use super::client::{purgeExpiredSessions, WebSocketClient};
+use super::tls::loadTlsAcceptor;
+
+/// How often sessions whose grace period has elapsed are discarded, along with the commands held for them.
+const SessionPurgeInterval: Duration = Duration::from_secs(60);
 
 #[derive(Clone, Default)]
 pub struct WebSocketServer {}
@@ -19,9 +25,19 @@ impl WebSocketServer
 {
 	pub async fn start(&self, token: CancellationToken) -> Result<()>
 	{
-		let address = getConfig().network.fullAddress();
-		info!("Listening on {}", address);
+		let network = &getConfig().network;
+		let acceptor = loadTlsAcceptor(&network.tls, &network.ip)?;
+		
+		let scheme = match acceptor.is_some()
+		{
+			true => "wss",
+			false => "ws",
+		};
+		
+		let address = network.fullAddress();
+		info!("Listening on {}://{}", scheme, address);
 		let listener = TcpListener::bind(address).await?;
+		let mut purge = interval(SessionPurgeInterval);
 		
 		loop
 		{
@@ -29,23 +45,23 @@ impl WebSocketServer
 			tokio::select! {
 				_ = token.cancelled() => break,
 				
+				_ = purge.tick() => purgeExpiredSessions(),
+				
 				result = listener.accept() => {
 					if let Ok((stream, _)) = result
 					{
 						info!("Client connected");
+						let tlsAcceptor = acceptor.clone();
 						tokio::spawn(async move {
-							let io = TokioIo::new(stream);
-							let connectionFuture = http1::Builder::new()
-								.serve_connection(io, service_fn(|request: Request<Incoming>| async {
-									let ct = cancelToken.clone();
-									return serverUpgrade(request, ct).await;
-								}))
-								.with_upgrades();
-							
-							if let Err(e) = connectionFuture.await
+							match tlsAcceptor
 							{
-								error!("An error occurred: {:?}", e);
-								cancelToken.cancel();
+								None => serveConnection(stream, cancelToken).await,
+								
+								Some(tls) => match tls.accept(stream).await
+								{
+									Ok(tlsStream) => serveConnection(tlsStream, cancelToken).await,
+									Err(e) => error!("TLS handshake failed: {:?}", e),
+								},
 							}
 						});
 					}
@@ -57,6 +73,24 @@ impl WebSocketServer
 	}
 }
 
+async fn serveConnection<S>(stream: S, token: CancellationToken)
+	where S: AsyncRead + AsyncWrite + Unpin + Send + 'static
+{
+	let io = TokioIo::new(stream);
+	let connectionFuture = http1::Builder::new()
+		.serve_connection(io, service_fn(|request: Request<Incoming>| async {
+			let ct = token.clone();
+			return serverUpgrade(request, ct).await;
+		}))
+		.with_upgrades();
+	
+	if let Err(e) = connectionFuture.await
+	{
+		error!("An error occurred: {:?}", e);
+		token.cancel();
+	}
+}
+
 async fn handleClient(future: UpgradeFut, token: CancellationToken) -> Result<()>
 {
 	let mut client = WebSocketClient::fromUpgradeFut(future).await?;
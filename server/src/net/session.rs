@@ -0,0 +1,205 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use ::base64::prelude::*;
+use ::chrono::{DateTime, TimeDelta, Utc};
+use ::password_hash::rand_core::{OsRng, RngCore};
+
+const SessionTokenLength: usize = 32;
+
+pub fn getSessionManager() -> &'static Mutex<SessionManager>
+{
+	static SessionManagerLock: OnceLock<Mutex<SessionManager>> = OnceLock::new();
+	return SessionManagerLock.get_or_init(|| Mutex::new(SessionManager::default()));
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Session
+{
+	pub clientId: i64,
+	pub userId: i32,
+	/// When the socket owning this session closed. `None` while connected.
+	pub disconnectedAt: Option<DateTime<Utc>>,
+}
+
+/**
+Tracks the opaque session tokens issued on successful authentication.
+
+A session outlives its socket for a grace period, during which a reconnecting
+client may present the token to reclaim its client id and any messages queued
+for it in the meantime.
+*/
+#[derive(Clone, Default)]
+pub struct SessionManager
+{
+	sessions: RefCell<HashMap<String, Session>>,
+}
+
+unsafe impl Send for SessionManager {}
+
+impl SessionManager
+{
+	/**
+	Issue a new session token for `clientId`.
+	
+	Any other sessions belonging to `clientId` are discarded.
+	*/
+	pub fn create(&self, clientId: i64, userId: i32) -> String
+	{
+		let token = generateToken();
+		
+		let mut sessions = self.sessions.borrow_mut();
+		sessions.retain(|_, s| s.clientId != clientId);
+		sessions.insert(token.to_owned(), Session
+		{
+			clientId,
+			userId,
+			disconnectedAt: None,
+		});
+		
+		return token;
+	}
+	
+	/**
+	Mark the session identified by `token` as disconnected at `now`.
+	*/
+	pub fn disconnect(&self, token: &String, now: DateTime<Utc>)
+	{
+		if let Some(session) = self.sessions.borrow_mut().get_mut(token)
+		{
+			session.disconnectedAt = Some(now);
+		}
+	}
+	
+	/**
+	Remove every disconnected session whose grace period has elapsed.
+	
+	Returns the client ids of the removed sessions.
+	*/
+	pub fn purgeExpired(&self, gracePeriod: TimeDelta, now: DateTime<Utc>) -> Vec<i64>
+	{
+		let mut expired = vec![];
+		
+		self.sessions.borrow_mut().retain(|_, s| {
+			let keep = !isExpired(s, gracePeriod, now);
+			if !keep
+			{
+				expired.push(s.clientId);
+			}
+			return keep;
+		});
+		
+		return expired;
+	}
+	
+	/**
+	Reclaim the disconnected session identified by `token`.
+	
+	The session must still be within its grace period. On success the old token
+	is invalidated and the session is returned along with its replacement token.
+	*/
+	pub fn resume(&self, token: &String, gracePeriod: TimeDelta, now: DateTime<Utc>) -> Option<(String, Session)>
+	{
+		let mut sessions = self.sessions.borrow_mut();
+		
+		let session = sessions.get(token)?;
+		if session.disconnectedAt.is_none() || isExpired(session, gracePeriod, now)
+		{
+			return None;
+		}
+		
+		let mut session = sessions.remove(token)?;
+		session.disconnectedAt = None;
+		
+		let newToken = generateToken();
+		sessions.insert(newToken.to_owned(), session.clone());
+		
+		return Some((newToken, session));
+	}
+}
+
+fn generateToken() -> String
+{
+	let mut bytes = [0u8; SessionTokenLength];
+	OsRng.fill_bytes(&mut bytes);
+	return BASE64_URL_SAFE_NO_PAD.encode(bytes);
+}
+
+fn isExpired(session: &Session, gracePeriod: TimeDelta, now: DateTime<Utc>) -> bool
+{
+	return match session.disconnectedAt
+	{
+		None => false,
+		Some(at) => now - at > gracePeriod,
+	};
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	
+	#[test]
+	fn resumeWithinGracePeriod()
+	{
+		let manager = SessionManager::default();
+		let gracePeriod = TimeDelta::seconds(60);
+		let now = Utc::now();
+		
+		let token = manager.create(1, 10);
+		assert!(manager.resume(&token, gracePeriod, now).is_none());
+		
+		manager.disconnect(&token, now);
+		let resumed = manager.resume(&token, gracePeriod, now + TimeDelta::seconds(30));
+		assert!(resumed.is_some());
+		
+		let (newToken, session) = resumed.unwrap();
+		assert_ne!(newToken, token);
+		assert_eq!(session.clientId, 1);
+		assert_eq!(session.userId, 10);
+		assert!(session.disconnectedAt.is_none());
+		
+		manager.disconnect(&token, now);
+		assert!(manager.resume(&token, gracePeriod, now).is_none());
+	}
+	
+	#[test]
+	fn expiredSessions()
+	{
+		let manager = SessionManager::default();
+		let gracePeriod = TimeDelta::seconds(60);
+		let now = Utc::now();
+		
+		let token1 = manager.create(1, 10);
+		let token2 = manager.create(2, 20);
+		let _ = manager.create(3, 30);
+		
+		manager.disconnect(&token1, now);
+		manager.disconnect(&token2, now + TimeDelta::seconds(45));
+		
+		let later = now + TimeDelta::seconds(90);
+		assert!(manager.resume(&token1, gracePeriod, later).is_none());
+		
+		let expired = manager.purgeExpired(gracePeriod, later);
+		assert_eq!(expired, vec![1]);
+		
+		assert!(manager.resume(&token2, gracePeriod, later).is_some());
+	}
+	
+	#[test]
+	fn createReplacesClientSessions()
+	{
+		let manager = SessionManager::default();
+		let gracePeriod = TimeDelta::seconds(60);
+		let now = Utc::now();
+		
+		let token1 = manager.create(1, 10);
+		manager.disconnect(&token1, now);
+		
+		let token2 = manager.create(1, 10);
+		manager.disconnect(&token2, now);
+		
+		assert!(manager.resume(&token1, gracePeriod, now).is_none());
+		assert!(manager.resume(&token2, gracePeriod, now).is_some());
+	}
+}
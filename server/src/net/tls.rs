@@ -0,0 +1,169 @@
+use std::fs::{read, write, OpenOptions};
+use std::io::Write;
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use ::anyhow::{Error, Result};
+use ::log::info;
+use ::rcgen::generate_simple_self_signed;
+use ::tokio_rustls::TlsAcceptor;
+use ::tokio_rustls::rustls::ServerConfig;
+use ::tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use ::tokio_rustls::rustls::pki_types::pem::PemObject;
+use crate::config::{localDataPath, ConfigTls, TlsMode};
+
+const SelfSignedCertificateFile: &'static str = "selfSigned.certificate.pem";
+const SelfSignedKeyFile: &'static str = "selfSigned.key.pem";
+
+/**
+Build the TLS acceptor described by `config`, or `None` for plain `ws://`.
+
+`ip` is included in the subject alternative names of a generated self-signed
+certificate, alongside `localhost`.
+*/
+pub fn loadTlsAcceptor(config: &ConfigTls, ip: &str) -> Result<Option<TlsAcceptor>>
+{
+	return match config.mode
+	{
+		TlsMode::None => Ok(None),
+		
+		TlsMode::Files => {
+			let (certificatePath, keyPath) = match (&config.certificate, &config.key)
+			{
+				(Some(certificate), Some(key)) => (resolvePath(certificate), resolvePath(key)),
+				_ => return Err(Error::msg("TLS mode \"files\" requires both a certificate and a key path")),
+			};
+			
+			let acceptor = buildAcceptor(&read(certificatePath)?, &read(keyPath)?)?;
+			Ok(Some(acceptor))
+		},
+		
+		TlsMode::SelfSigned => {
+			let certificatePath = resolvePath(config.certificate.as_deref().unwrap_or(SelfSignedCertificateFile));
+			let keyPath = resolvePath(config.key.as_deref().unwrap_or(SelfSignedKeyFile));
+			
+			if !certificatePath.exists() || !keyPath.exists()
+			{
+				let (certificate, key) = generateSelfSigned(ip)?;
+				write(&certificatePath, certificate)?;
+				writePrivateKey(&keyPath, &key)?;
+				info!("Generated self-signed certificate at {:?}", certificatePath);
+			}
+			
+			let acceptor = buildAcceptor(&read(certificatePath)?, &read(keyPath)?)?;
+			Ok(Some(acceptor))
+		},
+	};
+}
+
+/**
+Build a TLS acceptor from a PEM encoded certificate chain and private key.
+*/
+fn buildAcceptor(certificatePem: &[u8], keyPem: &[u8]) -> Result<TlsAcceptor>
+{
+	let certificates = CertificateDer::pem_slice_iter(certificatePem)
+		.collect::<Result<Vec<_>, _>>()?;
+	
+	if certificates.is_empty()
+	{
+		return Err(Error::msg("No certificates found in TLS certificate file"));
+	}
+	
+	let key = PrivateKeyDer::from_pem_slice(keyPem)?;
+	
+	let config = ServerConfig::builder()
+		.with_no_client_auth()
+		.with_single_cert(certificates, key)?;
+	
+	return Ok(TlsAcceptor::from(Arc::new(config)));
+}
+
+/**
+Generate a PEM encoded self-signed certificate and private key valid for
+`localhost` and `ip`.
+*/
+fn generateSelfSigned(ip: &str) -> Result<(String, String)>
+{
+	let mut names = vec!["localhost".to_string()];
+	if !ip.is_empty() && ip != "0.0.0.0" && ip != "::"
+	{
+		names.push(ip.to_string());
+	}
+	
+	let generated = generate_simple_self_signed(names)?;
+	return Ok((generated.cert.pem(), generated.signing_key.serialize_pem()));
+}
+
+/**
+Write the PEM encoded private `key` to `path`, which on Unix is created
+readable and writable only by its owner.
+*/
+fn writePrivateKey(path: &Path, key: &str) -> Result<()>
+{
+	let mut options = OpenOptions::new();
+	options.write(true).create(true).truncate(true);
+	
+	#[cfg(unix)]
+	options.mode(0o600);
+	
+	options.open(path)?.write_all(key.as_bytes())?;
+	return Ok(());
+}
+
+fn resolvePath(path: &str) -> PathBuf
+{
+	return match localDataPath()
+	{
+		None => PathBuf::from(path),
+		Some(dir) => PathBuf::from(dir).join(path),
+	};
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	
+	#[test]
+	fn selfSignedAcceptor()
+	{
+		let (certificate, key) = generateSelfSigned("192.168.1.10").expect("Error generating certificate");
+		assert!(certificate.contains("BEGIN CERTIFICATE"));
+		assert!(key.contains("PRIVATE KEY"));
+		
+		let acceptor = buildAcceptor(certificate.as_bytes(), key.as_bytes());
+		assert!(acceptor.is_ok());
+	}
+	
+	#[test]
+	fn invalidPem()
+	{
+		let (certificate, key) = generateSelfSigned("0.0.0.0").expect("Error generating certificate");
+		
+		assert!(buildAcceptor(b"not a certificate", key.as_bytes()).is_err());
+		assert!(buildAcceptor(certificate.as_bytes(), b"not a key").is_err());
+	}
+	
+	#[cfg(unix)]
+	#[test]
+	fn privateKeyPermissions()
+	{
+		use std::os::unix::fs::PermissionsExt;
+		
+		let path = std::env::temp_dir().join(format!("vttPrivateKey{}.pem", std::process::id()));
+		writePrivateKey(&path, "key").expect("Error writing private key");
+		
+		let mode = std::fs::metadata(&path).expect("Error reading key metadata").permissions().mode();
+		_ = std::fs::remove_file(&path);
+		assert_eq!(mode & 0o777, 0o600);
+	}
+	
+	#[test]
+	fn disabled()
+	{
+		let config = ConfigTls::default();
+		let acceptor = loadTlsAcceptor(&config, "127.0.0.1").expect("Error loading TLS configuration");
+		assert!(acceptor.is_none());
+	}
+}
@@ -32,7 +32,6 @@ impl UserManager
 		return Some(*id);
 	}
 	
-	#[allow(dead_code)]
 	pub fn getUserId(&self, clientId: i64) -> Option<String>
 	{
 		let mut username = None;
@@ -52,7 +51,7 @@ impl UserManager
 	{
 		let mut nextId = self.nextId.borrow_mut();
 		*nextId += 1;
-		return nextId.clone();
+		return *nextId;
 	}
 }
 
@@ -0,0 +1,78 @@
+use super::geometry::{polygonContains, Point};
+
+/**
+An area of a scene which a game master revealed or hid again.
+*/
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FogArea
+{
+	pub polygon: Vec<Point>,
+	pub revealed: bool,
+}
+
+/**
+The fog of war covering a scene, as one viewer sees it.
+
+The whole scene starts out covered, and each area in turn reveals or hides
+what lies within it, so later areas take precedence over earlier ones.
+*/
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FogMask
+{
+	/// Without fog the whole scene is revealed.
+	pub enabled: bool,
+	pub areas: Vec<FogArea>,
+}
+
+impl FogMask
+{
+	/**
+	Whether `point` can be seen through the fog.
+	*/
+	pub fn isRevealed(&self, point: Point) -> bool
+	{
+		if !self.enabled
+		{
+			return true;
+		}
+		
+		return self.areas.iter()
+			.rev()
+			.find(|area| polygonContains(&area.polygon, point))
+			.is_some_and(|area| area.revealed);
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	
+	fn area(polygon: [[i64; 2]; 4], revealed: bool) -> FogArea
+	{
+		return FogArea { polygon: polygon.map(Point::from).to_vec(), revealed };
+	}
+	
+	#[test]
+	fn revealing()
+	{
+		let room = area([[0, 0], [100, 0], [100, 100], [0, 100]], true);
+		let corner = area([[50, 50], [100, 50], [100, 100], [50, 100]], false);
+		let inner = Point::new(75.0, 75.0);
+		
+		let mut mask = FogMask { enabled: true, areas: vec![] };
+		assert!(!mask.isRevealed(Point::new(10.0, 10.0)));
+		
+		mask.areas.push(room);
+		assert!(mask.isRevealed(Point::new(10.0, 10.0)));
+		assert!(mask.isRevealed(inner));
+		assert!(!mask.isRevealed(Point::new(150.0, 10.0)));
+		
+		mask.areas.push(corner);
+		assert!(mask.isRevealed(Point::new(10.0, 10.0)));
+		assert!(!mask.isRevealed(inner));
+		
+		mask.enabled = false;
+		assert!(mask.isRevealed(inner));
+	}
+}
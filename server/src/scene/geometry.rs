@@ -0,0 +1,78 @@
+/**
+A point on a scene, in pixels of the scene's background.
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Point
+{
+	pub x: f64,
+	pub y: f64,
+}
+
+impl Point
+{
+	pub fn new(x: f64, y: f64) -> Self
+	{
+		return Self { x, y };
+	}
+}
+
+impl From<[i64; 2]> for Point
+{
+	fn from(value: [i64; 2]) -> Self
+	{
+		return Self::new(value[0] as f64, value[1] as f64);
+	}
+}
+
+/**
+Whether `point` lies inside `polygon`, by the even-odd rule. Points exactly on
+an edge may fall on either side.
+*/
+pub fn polygonContains(polygon: &[Point], point: Point) -> bool
+{
+	let mut inside = false;
+	let mut previous = match polygon.last()
+	{
+		Some(last) => *last,
+		None => return false,
+	};
+	
+	for current in polygon
+	{
+		if (current.y > point.y) != (previous.y > point.y)
+		{
+			let crossing = current.x + (point.y - current.y) * (previous.x - current.x) / (previous.y - current.y);
+			if point.x < crossing
+			{
+				inside = !inside;
+			}
+		}
+		
+		previous = *current;
+	}
+	
+	return inside;
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	
+	#[test]
+	fn contains()
+	{
+		let square = [[0, 0], [10, 0], [10, 10], [0, 10]].map(Point::from);
+		assert!(polygonContains(&square, Point::new(5.0, 5.0)));
+		assert!(!polygonContains(&square, Point::new(15.0, 5.0)));
+		assert!(!polygonContains(&square, Point::new(5.0, -1.0)));
+		
+		//An L shape, whose notch is outside
+		let shape = [[0, 0], [10, 0], [10, 4], [4, 4], [4, 10], [0, 10]].map(Point::from);
+		assert!(polygonContains(&shape, Point::new(2.0, 8.0)));
+		assert!(polygonContains(&shape, Point::new(8.0, 2.0)));
+		assert!(!polygonContains(&shape, Point::new(8.0, 8.0)));
+		
+		assert!(!polygonContains(&[], Point::default()));
+	}
+}
@@ -0,0 +1,234 @@
+use crate::data::{GridType, Scene2D};
+
+/// Size of a grid cell in pixels on new scenes, matching the client's token size.
+pub const DefaultCellSize: i64 = 100;
+/// Distance across a grid cell on new scenes, in `DefaultUnit`s.
+pub const DefaultDistance: f64 = 5.0;
+pub const DefaultUnit: &'static str = "ft";
+/// Half the square root of three, the distance between hexagon rows or columns relative to the cell size.
+const Sqrt3Halves: f64 = 0.866_025_403_784_438_6;
+
+/**
+A cell of a grid.
+
+Square cells are numbered by `column` and `row`. Hexagonal cells use axial
+coordinates, where `column` is `q` and `row` is `r`.
+*/
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub struct Cell
+{
+	pub column: i64,
+	pub row: i64,
+}
+
+/**
+The layout of a scene's grid, converting between pixels of the scene's
+background and grid cells.
+*/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Grid
+{
+	pub kind: GridType,
+	/// Distance in pixels between the centres of neighbouring cells.
+	pub cellSize: i64,
+	/// Top left corner of the bounds of the cell at `(0, 0)`.
+	pub originX: i64,
+	pub originY: i64,
+}
+
+impl Default for Grid
+{
+	fn default() -> Self
+	{
+		return Self
+		{
+			kind: GridType::default(),
+			cellSize: DefaultCellSize,
+			originX: 0,
+			originY: 0,
+		};
+	}
+}
+
+impl From<&Scene2D> for Grid
+{
+	fn from(scene: &Scene2D) -> Self
+	{
+		return Self
+		{
+			kind: scene.gridType(),
+			cellSize: scene.gridCellSize,
+			originX: scene.gridOriginX,
+			originY: scene.gridOriginY,
+		};
+	}
+}
+
+impl Grid
+{
+	/**
+	The cell containing the pixel `(x, y)`, or `None` without a grid.
+	*/
+	pub fn cellAt(&self, x: f64, y: f64) -> Option<Cell>
+	{
+		let size = self.cellSize as f64;
+		let (centreX, centreY) = self.firstCentre();
+		let (dx, dy) = (x - centreX, y - centreY);
+		
+		return match self.kind
+		{
+			GridType::Gridless => None,
+			GridType::Square => Some(Cell
+			{
+				column: (dx / size + 0.5).floor() as i64,
+				row: (dy / size + 0.5).floor() as i64,
+			}),
+			GridType::HexFlat => {
+				let q = dx / (size * Sqrt3Halves);
+				Some(roundAxial(q, dy / size - q / 2.0))
+			},
+			GridType::HexPointy => {
+				let r = dy / (size * Sqrt3Halves);
+				Some(roundAxial(dx / size - r / 2.0, r))
+			},
+		};
+	}
+	
+	/**
+	The pixel at the centre of `cell`, or `None` without a grid.
+	*/
+	pub fn cellCentre(&self, cell: Cell) -> Option<(f64, f64)>
+	{
+		let size = self.cellSize as f64;
+		let (centreX, centreY) = self.firstCentre();
+		let (q, r) = (cell.column as f64, cell.row as f64);
+		
+		let (dx, dy) = match self.kind
+		{
+			GridType::Gridless => return None,
+			GridType::Square => (q * size, r * size),
+			GridType::HexFlat => (q * size * Sqrt3Halves, (r + q / 2.0) * size),
+			GridType::HexPointy => ((q + r / 2.0) * size, r * size * Sqrt3Halves),
+		};
+		
+		return Some((centreX + dx, centreY + dy));
+	}
+	
+	/**
+	Move the pixel `(x, y)` to the centre of the cell containing it, rounded to
+	the nearest pixel. Without a grid it is returned unchanged.
+	*/
+	pub fn snap(&self, x: i64, y: i64) -> (i64, i64)
+	{
+		return match self.cellAt(x as f64, y as f64).and_then(|cell| self.cellCentre(cell))
+		{
+			Some((snappedX, snappedY)) => (snappedX.round() as i64, snappedY.round() as i64),
+			None => (x, y),
+		};
+	}
+	
+	/**
+	The centre of the cell at `(0, 0)`, which lies half of the cell's bounds
+	from the origin.
+	*/
+	fn firstCentre(&self) -> (f64, f64)
+	{
+		let size = self.cellSize as f64;
+		let radius = size / 3f64.sqrt();
+		
+		let (width, height) = match self.kind
+		{
+			GridType::Gridless | GridType::Square => (size, size),
+			GridType::HexFlat => (radius * 2.0, size),
+			GridType::HexPointy => (size, radius * 2.0),
+		};
+		
+		return (self.originX as f64 + width / 2.0, self.originY as f64 + height / 2.0);
+	}
+}
+
+/**
+Round fractional axial hexagon coordinates to the hexagon containing them, by
+rounding the equivalent cube coordinates and fixing whichever moved most.
+*/
+fn roundAxial(q: f64, r: f64) -> Cell
+{
+	let s = -q - r;
+	let (mut roundedQ, mut roundedR, roundedS) = (q.round(), r.round(), s.round());
+	let (diffQ, diffR, diffS) = ((roundedQ - q).abs(), (roundedR - r).abs(), (roundedS - s).abs());
+	
+	if diffQ > diffR && diffQ > diffS
+	{
+		roundedQ = -roundedR - roundedS;
+	}
+	else if diffR > diffS
+	{
+		roundedR = -roundedQ - roundedS;
+	}
+	
+	return Cell
+	{
+		column: roundedQ as i64,
+		row: roundedR as i64,
+	};
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	
+	fn grid(kind: GridType) -> Grid
+	{
+		return Grid { kind, cellSize: 100, originX: 10, originY: 20 };
+	}
+	
+	#[test]
+	fn square()
+	{
+		let grid = grid(GridType::Square);
+		assert_eq!(grid.cellAt(10.0, 20.0), Some(Cell { column: 0, row: 0 }));
+		assert_eq!(grid.cellAt(109.0, 119.0), Some(Cell { column: 0, row: 0 }));
+		assert_eq!(grid.cellAt(110.0, 20.0), Some(Cell { column: 1, row: 0 }));
+		assert_eq!(grid.cellAt(5.0, 250.0), Some(Cell { column: -1, row: 2 }));
+		assert_eq!(grid.cellCentre(Cell { column: 2, row: 1 }), Some((260.0, 170.0)));
+		assert_eq!(grid.snap(230, 101), (260, 70));
+	}
+	
+	#[test]
+	fn hexagons()
+	{
+		for kind in [GridType::HexFlat, GridType::HexPointy]
+		{
+			let grid = grid(kind);
+			for column in -3..=3
+			{
+				for row in -3..=3
+				{
+					let cell = Cell { column, row };
+					let (x, y) = grid.cellCentre(cell).unwrap();
+					assert_eq!(grid.cellAt(x, y), Some(cell));
+					assert_eq!(grid.cellAt(x + 30.0, y - 30.0), Some(cell));
+				}
+			}
+		}
+		
+		let pointy = grid(GridType::HexPointy);
+		assert_eq!(pointy.cellCentre(Cell::default()), Some((60.0, 20.0 + 100.0 / 3f64.sqrt())));
+		assert_eq!(pointy.cellCentre(Cell { column: 1, row: 0 }).map(|(x, _)| x), Some(160.0));
+		assert_eq!(pointy.snap(155, 80), (160, 78));
+		
+		let flat = grid(GridType::HexFlat);
+		assert_eq!(flat.cellCentre(Cell { column: 0, row: 1 }).map(|(_, y)| y), Some(170.0));
+		assert_eq!(flat.cellAt(10.0 + 57.7, 20.0 + 130.0), Some(Cell { column: 0, row: 1 }));
+	}
+	
+	#[test]
+	fn gridless()
+	{
+		let grid = grid(GridType::Gridless);
+		assert_eq!(grid.cellAt(50.0, 50.0), None);
+		assert_eq!(grid.cellCentre(Cell::default()), None);
+		assert_eq!(grid.snap(123, 456), (123, 456));
+	}
+}
@@ -0,0 +1,5 @@
+pub mod fog;
+pub mod geometry;
+pub mod grid;
+pub mod sight;
+pub mod vision;
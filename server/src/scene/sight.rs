@@ -0,0 +1,287 @@
+use crate::data::{FogRegion, Token, Wall};
+use super::fog::{FogArea, FogMask};
+use super::geometry::{polygonContains, Point};
+use super::vision::{blockingSegments, visibilityPolygon};
+
+/// Distance in pixels between the points of a wall checked for being seen.
+const WallSampleSpacing: f64 = 10.0;
+/// Maximum number of points of a single wall checked for being seen.
+const MaxWallSamples: usize = 64;
+/// Distance in pixels either side of a wall at which it is checked, as a wall lies on the edge of what it blocks.
+const WallSampleOffset: f64 = 1.0;
+
+/**
+Everything on a scene which decides what its viewers may see.
+*/
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SceneState
+{
+	pub fogEnabled: bool,
+	/// Height of the scene's background in pixels.
+	pub height: i64,
+	pub regions: Vec<FogRegion>,
+	pub tokens: Vec<Token>,
+	pub walls: Vec<Wall>,
+	/// Width of the scene's background in pixels.
+	pub width: i64,
+}
+
+/**
+A client viewing a scene.
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Viewer
+{
+	pub clientId: i64,
+	pub gameMaster: bool,
+	pub userId: i32,
+}
+
+impl Viewer
+{
+	/**
+	What the viewer may see of `scene`. Fog regions meant for other users are
+	left out, and once the scene has walls, players only see what their own
+	tokens can see past them.
+	*/
+	pub fn sight(&self, scene: &SceneState) -> Sight
+	{
+		let areas = scene.regions.iter()
+			.filter(|region| region.appliesTo(self.userId))
+			.map(|region| FogArea
+			{
+				polygon: region.points().into_iter().map(Point::from).collect(),
+				revealed: region.revealed,
+			})
+			.collect();
+		
+		let vision = match self.gameMaster || scene.walls.is_empty()
+		{
+			true => None,
+			false => Some(scene.tokens.iter()
+				.filter(|token| token.ownerId == Some(self.userId))
+				.map(|token| {
+					let origin = Point::new(token.x as f64, token.y as f64);
+					let segments = blockingSegments(&scene.walls, origin);
+					return visibilityPolygon(origin, &segments, scene.width as f64, scene.height as f64);
+				})
+				.collect()),
+		};
+		
+		return Sight
+		{
+			fog: FogMask { enabled: scene.fogEnabled, areas },
+			gameMaster: self.gameMaster,
+			userId: self.userId,
+			vision,
+		};
+	}
+	
+	/**
+	Whether the viewer is sent the fog region `region`. Game masters are sent
+	every region so that they can see what each player sees.
+	*/
+	pub fn receives(&self, region: &FogRegion) -> bool
+	{
+		return self.gameMaster || region.appliesTo(self.userId);
+	}
+}
+
+/**
+What one viewer of a scene may see of it.
+*/
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Sight
+{
+	/// The fog covering the scene for this viewer.
+	pub fog: FogMask,
+	/// Game masters see the whole scene.
+	pub gameMaster: bool,
+	pub userId: i32,
+	/// The polygons which the viewer's tokens see, or `None` when the viewer's sight is not limited by walls.
+	pub vision: Option<Vec<Vec<Point>>>,
+}
+
+impl Sight
+{
+	/**
+	Whether the viewer may see `token`. Tokens are always seen by those who may
+	move them, and otherwise only when they are not hidden and their centre is
+	both revealed and within the viewer's vision.
+	*/
+	pub fn sees(&self, token: &Token) -> bool
+	{
+		if token.isMovableBy(self.gameMaster, self.userId)
+		{
+			return true;
+		}
+		
+		return !token.hidden && self.isVisible(Point::new(token.x as f64, token.y as f64));
+	}
+	
+	/**
+	Whether the viewer may see `wall`. Game masters see every wall, and others
+	only those which touch an area that is both revealed and within their
+	vision, so that the layout of unexplored areas stays hidden.
+	*/
+	pub fn seesWall(&self, wall: &Wall) -> bool
+	{
+		if self.gameMaster
+		{
+			return true;
+		}
+		
+		let start = Point::from([wall.startX, wall.startY]);
+		let end = Point::from([wall.endX, wall.endY]);
+		let (dx, dy) = (end.x - start.x, end.y - start.y);
+		let length = dx.hypot(dy);
+		
+		//Check just either side of the wall, along the normal
+		let normal = match length > 0.0
+		{
+			true => Point::new(-dy / length * WallSampleOffset, dx / length * WallSampleOffset),
+			false => Point::new(WallSampleOffset, 0.0),
+		};
+		
+		let samples = ((length / WallSampleSpacing).ceil() as usize).clamp(1, MaxWallSamples);
+		return (0..=samples)
+			.map(|index| index as f64 / samples as f64)
+			.map(|along| Point::new(start.x + dx * along, start.y + dy * along))
+			.flat_map(|point| [
+				Point::new(point.x + normal.x, point.y + normal.y),
+				Point::new(point.x - normal.x, point.y - normal.y),
+			])
+			.any(|point| self.isVisible(point));
+	}
+	
+	/**
+	Whether `point` is revealed and within the viewer's vision.
+	*/
+	fn isVisible(&self, point: Point) -> bool
+	{
+		let inVision = self.vision.as_ref()
+			.is_none_or(|polygons| polygons.iter().any(|polygon| polygonContains(polygon, point)));
+		
+		return self.fog.isRevealed(point) && inVision;
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	
+	#[test]
+	fn tokens()
+	{
+		let fog = FogMask
+		{
+			enabled: true,
+			areas: vec![FogArea
+			{
+				polygon: [[0, 0], [100, 0], [100, 100], [0, 100]].map(Point::from).to_vec(),
+				revealed: true,
+			}],
+		};
+		
+		let player = Sight { fog: fog.clone(), gameMaster: false, userId: 1, vision: None };
+		let gameMaster = Sight { fog, gameMaster: true, userId: 2, vision: None };
+		
+		let revealed = Token { x: 50, y: 50, ..Default::default() };
+		let fogged = Token { x: 150, y: 50, ..Default::default() };
+		let owned = Token { ownerId: Some(1), ..fogged.clone() };
+		let hidden = Token { hidden: true, ..revealed.clone() };
+		
+		assert!(player.sees(&revealed));
+		assert!(!player.sees(&fogged));
+		assert!(player.sees(&owned));
+		assert!(!player.sees(&hidden));
+		assert!(gameMaster.sees(&fogged));
+		assert!(gameMaster.sees(&hidden));
+	}
+	
+	#[test]
+	fn regions()
+	{
+		let region = |revealed: bool, userId: Option<i32>| FogRegion
+		{
+			points: "[[0, 0], [100, 0], [100, 100], [0, 100]]".into(),
+			revealed,
+			userId,
+			..Default::default()
+		};
+		
+		let regions = vec![region(true, None), region(false, Some(2))];
+		let token = Token { x: 50, y: 50, ..Default::default() };
+		let viewer = |userId: i32| Viewer { clientId: 1, gameMaster: false, userId };
+		let scene = SceneState { fogEnabled: true, regions: regions.clone(), ..Default::default() };
+		
+		assert!(viewer(1).sight(&scene).sees(&token));
+		assert!(!viewer(2).sight(&scene).sees(&token));
+		assert!(!viewer(1).sight(&SceneState { regions: vec![], ..scene.clone() }).sees(&token));
+		assert!(viewer(2).sight(&SceneState { fogEnabled: false, ..scene }).sees(&token));
+		assert!(!viewer(1).receives(&regions[1]));
+		assert!(Viewer { gameMaster: true, ..viewer(1) }.receives(&regions[1]));
+	}
+	
+	#[test]
+	fn vision()
+	{
+		let wall = Wall { startX: 50, startY: 0, endX: 50, endY: 100, ..Default::default() };
+		let scout = Token { id: 1, ownerId: Some(1), x: 25, y: 50, ..Default::default() };
+		let near = Token { id: 2, x: 40, y: 20, ..Default::default() };
+		let far = Token { id: 3, x: 75, y: 50, ..Default::default() };
+		
+		let scene = SceneState
+		{
+			height: 100,
+			tokens: vec![scout.clone(), near.clone(), far.clone()],
+			walls: vec![wall],
+			width: 100,
+			..Default::default()
+		};
+		
+		let player = Viewer { clientId: 1, gameMaster: false, userId: 1 };
+		let sight = player.sight(&scene);
+		assert!(sight.sees(&scout));
+		assert!(sight.sees(&near));
+		assert!(!sight.sees(&far));
+		
+		//Without tokens of their own, players see nothing past the walls
+		let stranger = Viewer { userId: 2, ..player }.sight(&scene);
+		assert!(!stranger.sees(&near));
+		
+		assert!(Viewer { gameMaster: true, ..player }.sight(&scene).sees(&far));
+		assert!(player.sight(&SceneState { walls: vec![], ..scene }).sees(&far));
+	}
+	
+	#[test]
+	fn walls()
+	{
+		let near = Wall { id: 1, startX: 50, startY: 0, endX: 50, endY: 100, ..Default::default() };
+		let behind = Wall { id: 2, startX: 80, startY: 20, endX: 80, endY: 80, ..Default::default() };
+		let scout = Token { ownerId: Some(1), x: 25, y: 50, ..Default::default() };
+		
+		let scene = SceneState
+		{
+			height: 100,
+			tokens: vec![scout],
+			walls: vec![near.clone(), behind.clone()],
+			width: 100,
+			..Default::default()
+		};
+		
+		let player = Viewer { clientId: 1, gameMaster: false, userId: 1 };
+		let sight = player.sight(&scene);
+		assert!(sight.seesWall(&near));
+		assert!(!sight.seesWall(&behind));
+		
+		let stranger = Viewer { userId: 2, ..player }.sight(&scene);
+		assert!(!stranger.seesWall(&near));
+		assert!(Viewer { gameMaster: true, ..player }.sight(&scene).seesWall(&behind));
+		
+		//Fog hides walls even within sight
+		let fogged = player.sight(&SceneState { fogEnabled: true, ..scene });
+		assert!(!fogged.seesWall(&near));
+	}
+}
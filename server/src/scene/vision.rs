@@ -0,0 +1,254 @@
+use std::f64::consts::PI;
+use crate::data::{Wall, WallKind};
+use super::geometry::Point;
+
+/// Angle in radians either side of each corner at which extra rays are cast, to see past the corner.
+const CornerOffset: f64 = 1e-5;
+/// Distances and lengths in pixels below which two positions are treated as the same.
+const Tolerance: f64 = 1e-9;
+
+/**
+A straight line between two points which sight cannot pass through.
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Segment
+{
+	pub start: Point,
+	pub end: Point,
+}
+
+impl Segment
+{
+	pub fn new(start: Point, end: Point) -> Self
+	{
+		return Self { start, end };
+	}
+	
+	/**
+	How far along the ray from `origin` in `direction` it meets this segment, in
+	multiples of `direction`, if it does. A segment passing through `origin` is
+	not met, so a viewer standing on a wall sees past it.
+	*/
+	fn distanceAlong(&self, origin: Point, direction: Point) -> Option<f64>
+	{
+		let edge = Point::new(self.end.x - self.start.x, self.end.y - self.start.y);
+		let denominator = cross(direction, edge);
+		if denominator.abs() < Tolerance
+		{
+			return None;
+		}
+		
+		let offset = Point::new(self.start.x - origin.x, self.start.y - origin.y);
+		let distance = cross(offset, edge) / denominator;
+		let along = cross(offset, direction) / denominator;
+		
+		return match distance > Tolerance && (0.0..=1.0).contains(&along)
+		{
+			true => Some(distance),
+			false => None,
+		};
+	}
+}
+
+/**
+The segments of `walls` which block sight from `origin`. Open doors block
+nothing, and one-way walls only block viewers on their left, facing from their
+start to their end on screen.
+*/
+pub fn blockingSegments(walls: &[Wall], origin: Point) -> Vec<Segment>
+{
+	return walls.iter()
+		.map(|wall| (wall, Segment::new(Point::from([wall.startX, wall.startY]), Point::from([wall.endX, wall.endY]))))
+		.filter(|(wall, segment)| match wall.kind()
+		{
+			WallKind::Wall => true,
+			WallKind::OneWay => isLeftOf(segment, origin),
+			WallKind::Door => !wall.isOpen(),
+		})
+		.map(|(_, segment)| segment)
+		.collect();
+}
+
+/**
+The polygon of a scene `width` by `height` pixels which can be seen from
+`origin` past `segments`, limited by the edges of the scene.
+
+Rays are cast from `origin` towards each corner of every segment, and just
+either side of it, and the nearest point each ray meets becomes a corner of the
+polygon, in order of angle. The result depends only on the corners and
+segments given, not on the order of `segments`.
+*/
+pub fn visibilityPolygon(origin: Point, segments: &[Segment], width: f64, height: f64) -> Vec<Point>
+{
+	let corners = [Point::new(0.0, 0.0), Point::new(width, 0.0), Point::new(width, height), Point::new(0.0, height)];
+	let mut barriers = segments.to_vec();
+	for (index, corner) in corners.iter().enumerate()
+	{
+		barriers.push(Segment::new(*corner, corners[(index + 1) % corners.len()]));
+	}
+	
+	let mut angles = vec![];
+	for point in barriers.iter().flat_map(|segment| [segment.start, segment.end])
+	{
+		let angle = (point.y - origin.y).atan2(point.x - origin.x);
+		angles.extend([angle - CornerOffset, angle, angle + CornerOffset].map(normalizeAngle));
+	}
+	
+	angles.sort_by(f64::total_cmp);
+	angles.dedup();
+	
+	let mut polygon: Vec<Point> = vec![];
+	for angle in angles
+	{
+		let direction = Point::new(angle.cos(), angle.sin());
+		let nearest = barriers.iter()
+			.filter_map(|segment| segment.distanceAlong(origin, direction))
+			.min_by(f64::total_cmp);
+		
+		if let Some(distance) = nearest
+		{
+			let point = Point::new(origin.x + direction.x * distance, origin.y + direction.y * distance);
+			let repeated = polygon.last()
+				.is_some_and(|last| (last.x - point.x).abs() < Tolerance && (last.y - point.y).abs() < Tolerance);
+			
+			if !repeated
+			{
+				polygon.push(point);
+			}
+		}
+	}
+	
+	return polygon;
+}
+
+/**
+Whether `point` lies on the left of `segment`, facing from its start to its end
+on screen, where y increases downwards.
+*/
+fn isLeftOf(segment: &Segment, point: Point) -> bool
+{
+	let edge = Point::new(segment.end.x - segment.start.x, segment.end.y - segment.start.y);
+	let offset = Point::new(point.x - segment.start.x, point.y - segment.start.y);
+	return cross(edge, offset) < 0.0;
+}
+
+fn cross(a: Point, b: Point) -> f64
+{
+	return a.x * b.y - a.y * b.x;
+}
+
+/**
+Wrap `angle` into the range from -π exclusive to π inclusive, so that rays
+sort in order around the origin.
+*/
+fn normalizeAngle(angle: f64) -> f64
+{
+	return match angle
+	{
+		a if a > PI => a - 2.0 * PI,
+		a if a <= -PI => a + 2.0 * PI,
+		a => a,
+	};
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::data::DoorState;
+	use crate::scene::geometry::polygonContains;
+	
+	fn wall(kind: WallKind, doorState: DoorState, start: [i64; 2], end: [i64; 2]) -> Wall
+	{
+		return Wall
+		{
+			doorState: doorState.into(),
+			endX: end[0],
+			endY: end[1],
+			kind: kind.into(),
+			startX: start[0],
+			startY: start[1],
+			..Default::default()
+		};
+	}
+	
+	fn sees(walls: &[Wall], from: [i64; 2], to: [i64; 2]) -> bool
+	{
+		let origin = Point::from(from);
+		let polygon = visibilityPolygon(origin, &blockingSegments(walls, origin), 100.0, 100.0);
+		return polygonContains(&polygon, Point::from(to));
+	}
+	
+	#[test]
+	fn openRoom()
+	{
+		let polygon = visibilityPolygon(Point::new(50.0, 50.0), &[], 100.0, 100.0);
+		assert_eq!(polygon.len(), 12);
+		assert!(polygon.iter().all(|p| (-Tolerance..=100.0 + Tolerance).contains(&p.x)
+			&& (-Tolerance..=100.0 + Tolerance).contains(&p.y)));
+		
+		assert!(sees(&[], [50, 50], [1, 1]));
+		assert!(sees(&[], [50, 50], [99, 99]));
+		assert!(!sees(&[], [50, 50], [101, 50]));
+	}
+	
+	#[test]
+	fn walls()
+	{
+		let walls = [wall(WallKind::Wall, DoorState::Closed, [50, 20], [50, 80])];
+		assert!(sees(&walls, [10, 50], [30, 50]));
+		assert!(!sees(&walls, [10, 50], [90, 50]));
+		assert!(!sees(&walls, [90, 50], [10, 50]));
+		
+		//Around the end of the wall
+		assert!(sees(&walls, [10, 50], [60, 5]));
+		assert!(sees(&walls, [10, 50], [55, 95]));
+		assert!(!sees(&walls, [10, 50], [60, 70]));
+	}
+	
+	#[test]
+	fn oneWay()
+	{
+		//Facing down the screen, the left is the larger x
+		let walls = [wall(WallKind::OneWay, DoorState::Closed, [50, 0], [50, 100])];
+		assert!(sees(&walls, [10, 50], [90, 50]));
+		assert!(!sees(&walls, [90, 50], [10, 50]));
+	}
+	
+	#[test]
+	fn doors()
+	{
+		let door = |state: DoorState| [wall(WallKind::Door, state, [50, 0], [50, 100])];
+		assert!(sees(&door(DoorState::Open), [10, 50], [90, 50]));
+		assert!(!sees(&door(DoorState::Closed), [10, 50], [90, 50]));
+		assert!(!sees(&door(DoorState::Locked), [90, 50], [10, 50]));
+		
+		//A door in a gap of a wall
+		let mut walls = door(DoorState::Open).to_vec();
+		walls[0].startY = 40;
+		walls[0].endY = 60;
+		walls.push(wall(WallKind::Wall, DoorState::Closed, [50, 0], [50, 40]));
+		walls.push(wall(WallKind::Wall, DoorState::Closed, [50, 60], [50, 100]));
+		assert!(sees(&walls, [10, 50], [90, 50]));
+		assert!(!sees(&walls, [10, 50], [90, 5]));
+	}
+	
+	#[test]
+	fn deterministic()
+	{
+		let walls = [
+			wall(WallKind::Wall, DoorState::Closed, [20, 20], [80, 20]),
+			wall(WallKind::Wall, DoorState::Closed, [30, 70], [60, 40]),
+			wall(WallKind::Door, DoorState::Closed, [10, 90], [90, 90]),
+		];
+		
+		let origin = Point::new(45.0, 55.0);
+		let segments = blockingSegments(&walls, origin);
+		let mut reversed = segments.clone();
+		reversed.reverse();
+		
+		let polygon = visibilityPolygon(origin, &segments, 100.0, 100.0);
+		assert_eq!(polygon, visibilityPolygon(origin, &segments, 100.0, 100.0));
+		assert_eq!(polygon, visibilityPolygon(origin, &reversed, 100.0, 100.0));
+	}
+}